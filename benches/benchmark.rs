@@ -1,6 +1,6 @@
 use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId, Throughput, black_box};
 
-use magnet_url::{Magnet, MagnetBuilder};
+use magnet_url::{Magnet, MagnetBuilder, TrackerDictionary};
 
 fn benchmark_magnet_parsing(c: &mut Criterion) {
     let magnet = black_box("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel&tr=udp%3A%2F%2Fexplodie.org%3A6969&tr=udp%3A%2F%2Ftracker.coppersurfer.tk%3A6969&tr=udp%3A%2F%2Ftracker.empire-js.us%3A1337&tr=udp%3A%2F%2Ftracker.leechers-paradise.org%3A6969&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337&tr=wss%3A%2F%2Ftracker.btorrent.xyz&tr=wss%3A%2F%2Ftracker.fastcast.nz&tr=wss%3A%2F%2Ftracker.openwebtorrent.com&ws=https%3A%2F%2Fwebtorrent.io%2Ftorrents%2F&xs=https%3A%2F%2Fwebtorrent.io%2Ftorrents%2Fsintel.torrent");
@@ -58,5 +58,47 @@ fn benchmark_builder(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, benchmark_magnet_parsing, benchmark_to_string, benchmark_builder);
+fn benchmark_compact(c: &mut Criterion) {
+    let magnet = black_box(
+        Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel&tr=udp%3A%2F%2Fexplodie.org%3A6969&tr=udp%3A%2F%2Ftracker.coppersurfer.tk%3A6969&tr=udp%3A%2F%2Ftracker.empire-js.us%3A1337&tr=udp%3A%2F%2Ftracker.leechers-paradise.org%3A6969&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337&tr=wss%3A%2F%2Ftracker.btorrent.xyz&tr=wss%3A%2F%2Ftracker.fastcast.nz&tr=wss%3A%2F%2Ftracker.openwebtorrent.com&ws=https%3A%2F%2Fwebtorrent.io%2Ftorrents%2F&xs=https%3A%2F%2Fwebtorrent.io%2Ftorrents%2Fsintel.torrent").unwrap()
+    );
+    let dictionary = TrackerDictionary::from_magnets([&magnet], 100);
+    let text = magnet.to_string();
+    let bytes = magnet.to_bytes();
+    let bytes_with_dictionary = magnet.to_bytes_with(&dictionary);
+
+    println!(
+        "Sizes: to_string {} bytes, to_bytes {} bytes, to_bytes_with dictionary {} bytes",
+        text.len(),
+        bytes.len(),
+        bytes_with_dictionary.len()
+    );
+
+    let mut group = c.benchmark_group("compact");
+
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("to_string", |b| {
+        b.iter(|| black_box(&magnet).to_string());
+    });
+    group.bench_function("to_bytes", |b| {
+        b.iter(|| black_box(&magnet).to_bytes());
+    });
+    group.bench_function("to_bytes_with", |b| {
+        b.iter(|| black_box(&magnet).to_bytes_with(&dictionary));
+    });
+    group.bench_function("parse", |b| {
+        b.iter(|| Magnet::new(black_box(&text)));
+    });
+    group.bench_function("from_bytes", |b| {
+        b.iter(|| Magnet::from_bytes(black_box(&bytes)));
+    });
+    group.bench_function("from_bytes_with", |b| {
+        b.iter(|| Magnet::from_bytes_with(black_box(&bytes_with_dictionary), &dictionary));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_magnet_parsing, benchmark_to_string, benchmark_builder, benchmark_compact);
 criterion_main!(benches);
\ No newline at end of file
@@ -0,0 +1,4 @@
+//! The C library of `magnet-url`: the functions of its `capi` module, declared in
+//! `include/magnet_url.h`, built as a static and a shared library
+
+pub use magnet_url::capi::*;
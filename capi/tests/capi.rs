@@ -0,0 +1,56 @@
+//! Builds the C tests in `capi_test.c` against the shared library and runs them
+//!
+//! Only the test runs need a C compiler (`$CC`, or `cc`); building the library does not.
+
+#![cfg(unix)]
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The variable the dynamic loader searches for shared libraries
+#[cfg(target_os = "macos")]
+const LIBRARY_PATH_VAR: &str = "DYLD_LIBRARY_PATH";
+#[cfg(not(target_os = "macos"))]
+const LIBRARY_PATH_VAR: &str = "LD_LIBRARY_PATH";
+
+/// The directory Cargo builds the shared library in for tests, next to this test binary
+fn library_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn c_test() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let library_dir = library_dir();
+    let exe = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capi_test");
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(&compiler)
+        .args(["-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(manifest_dir.join("../include"))
+        .arg(manifest_dir.join("tests/capi_test.c"))
+        .arg("-o")
+        .arg(&exe)
+        .arg("-L")
+        .arg(&library_dir)
+        .arg(format!("-Wl,-rpath,{}", library_dir.display()))
+        .arg("-lmagnet_url_capi")
+        .status()
+        .unwrap_or_else(|e| panic!("could not run {}: {}", compiler, e));
+    assert!(status.success(), "compiling capi_test.c failed");
+
+    // Cargo puts other target directories on the library path for tests, and that path is
+    // searched before the rpath, so a stale copy of the library there would be picked up
+    let mut library_path = library_dir.clone().into_os_string();
+    if let Some(path) = env::var_os(LIBRARY_PATH_VAR) {
+        library_path.push(":");
+        library_path.push(path);
+    }
+    let status = Command::new(&exe)
+        .env(LIBRARY_PATH_VAR, library_path)
+        .status()
+        .unwrap();
+    assert!(status.success(), "C API checks failed");
+}
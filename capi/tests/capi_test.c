@@ -0,0 +1,114 @@
+/* Tests of the C API, built against the shared library and run by tests/capi.rs */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "magnet_url.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                            \
+    do {                                                                       \
+        if (!(cond)) {                                                         \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__,   \
+                    #cond);                                                    \
+            failures++;                                                        \
+        }                                                                      \
+    } while (0)
+
+static int str_eq(const char *a, const char *b) {
+    return a != NULL && b != NULL && strcmp(a, b) == 0;
+}
+
+static void test_parse(void) {
+    const char *link =
+        "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel"
+        "&xl=42&tr=udp%3A%2F%2Fexplodie.org%3A6969&tr=wss%3A%2F%2Ftracker.btorrent.xyz"
+        "&x.pe=10.0.0.1:6881";
+    MagnetHandle *magnet = magnet_parse(link);
+    uint64_t length = 0;
+
+    CHECK(magnet != NULL);
+    CHECK(str_eq(magnet_display_name(magnet), "Sintel"));
+    CHECK(str_eq(magnet_hash_type(magnet), "btih"));
+    CHECK(str_eq(magnet_hash(magnet), "08ada5a7a6183aae1e09d831df6748d566095a10"));
+    CHECK(magnet_length(magnet, &length) && length == 42);
+    CHECK(magnet_web_seed(magnet) == NULL);
+    CHECK(magnet_source(magnet) == NULL);
+
+    CHECK(magnet_tracker_count(magnet) == 2);
+    CHECK(str_eq(magnet_tracker(magnet, 0), "udp%3A%2F%2Fexplodie.org%3A6969"));
+    CHECK(str_eq(magnet_tracker(magnet, 1), "wss%3A%2F%2Ftracker.btorrent.xyz"));
+    CHECK(magnet_tracker(magnet, 2) == NULL);
+
+    CHECK(magnet_peer_count(magnet) == 1);
+    CHECK(str_eq(magnet_peer(magnet, 0), "10.0.0.1:6881"));
+    CHECK(magnet_peer(magnet, 1) == NULL);
+
+    char *string = magnet_to_string(magnet);
+    CHECK(str_eq(string, link));
+    magnet_string_free(string);
+
+    magnet_free(magnet);
+}
+
+static void test_invalid(void) {
+    uint64_t length = 7;
+
+    CHECK(magnet_parse("https://example.com") == NULL);
+    CHECK(magnet_parse(NULL) == NULL);
+    CHECK(magnet_from_torrent((const uint8_t *)"garbage", 7) == NULL);
+
+    /* NULL handles are treated as missing values */
+    CHECK(magnet_display_name(NULL) == NULL);
+    CHECK(!magnet_length(NULL, &length) && length == 7);
+    CHECK(magnet_tracker_count(NULL) == 0);
+    CHECK(magnet_peer_count(NULL) == 0);
+    CHECK(magnet_to_string(NULL) == NULL);
+    magnet_free(NULL);
+    magnet_string_free(NULL);
+}
+
+static void test_builder(void) {
+    MagnetBuilderHandle *builder = magnet_builder_new();
+
+    CHECK(magnet_builder_display_name(builder, "Test"));
+    CHECK(magnet_builder_hash_type(builder, "btih"));
+    CHECK(magnet_builder_hash(builder, "1234567890abcdef1234567890abcdef12345678"));
+    CHECK(magnet_builder_length(builder, 12345));
+    CHECK(magnet_builder_add_tracker(builder, "udp://a.example:1"));
+    CHECK(magnet_builder_add_tracker(builder, "udp://b.example:2"));
+    CHECK(!magnet_builder_web_seed(builder, NULL));
+    CHECK(!magnet_builder_manifest(NULL, "x"));
+
+    MagnetHandle *magnet = magnet_builder_build(builder);
+    char *string = magnet_to_string(magnet);
+    CHECK(str_eq(string,
+                 "magnet:?xt=urn:btih:1234567890abcdef1234567890abcdef12345678"
+                 "&dn=Test&xl=12345&tr=udp://a.example:1&tr=udp://b.example:2"));
+    CHECK(magnet_tracker_count(magnet) == 2);
+    magnet_string_free(string);
+    magnet_free(magnet);
+
+    /* A builder that is never built is released with magnet_builder_free */
+    magnet_builder_free(magnet_builder_new());
+}
+
+static void test_from_torrent(void) {
+    const char torrent[] = "d4:infod6:lengthi5e4:name5:hello12:piece lengthi16384e"
+                           "6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+    MagnetHandle *magnet = magnet_from_torrent((const uint8_t *)torrent, sizeof(torrent) - 1);
+
+    CHECK(magnet != NULL);
+    CHECK(str_eq(magnet_display_name(magnet), "hello"));
+    CHECK(str_eq(magnet_hash_type(magnet), "btih"));
+    magnet_free(magnet);
+}
+
+int main(void) {
+    test_parse();
+    test_invalid();
+    test_builder();
+    test_from_torrent();
+    return failures == 0 ? 0 : 1;
+}
@@ -0,0 +1,310 @@
+#ifndef MAGNET_URL_H
+#define MAGNET_URL_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit by hand */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Builder for creating magnet URLs
+typedef struct MagnetBuilderHandle MagnetBuilderHandle;
+
+// A parsed magnet URL, with NUL-terminated copies of its fields for the getters
+typedef struct MagnetHandle MagnetHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Parse a magnet URL
+//
+// Returns `NULL` if `link` is `NULL`, not valid UTF-8 or not a magnet URL. The result must be
+// released with `magnet_free`.
+//
+// # Safety
+//
+// `link` must be `NULL` or a valid NUL-terminated string.
+struct MagnetHandle *magnet_parse(const char *link);
+
+// Create a magnet URL from the contents of a `.torrent` file
+//
+// Returns `NULL` if `data` is `NULL` or not a valid torrent. The result must be released with
+// `magnet_free`.
+//
+// # Safety
+//
+// `data` must be `NULL` or point to `len` readable bytes.
+struct MagnetHandle *magnet_from_torrent(const uint8_t *data, uintptr_t len);
+
+// Release a magnet returned by this library. Passing `NULL` does nothing.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a magnet returned by this library that was not freed yet.
+void magnet_free(struct MagnetHandle *magnet);
+
+// Get the display name of the torrent
+//
+// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+const char *magnet_display_name(const struct MagnetHandle *magnet);
+
+// Get the hash type used in the exact topic
+//
+// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+const char *magnet_hash_type(const struct MagnetHandle *magnet);
+
+// Get the torrent hash
+//
+// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+const char *magnet_hash(const struct MagnetHandle *magnet);
+
+// Get the download source for the file
+//
+// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+const char *magnet_source(const struct MagnetHandle *magnet);
+
+// Get the search keywords
+//
+// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+const char *magnet_search_keywords(const struct MagnetHandle *magnet);
+
+// Get the web seed URL
+//
+// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+const char *magnet_web_seed(const struct MagnetHandle *magnet);
+
+// Get the acceptable source
+//
+// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+const char *magnet_acceptable_source(const struct MagnetHandle *magnet);
+
+// Get the manifest link
+//
+// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+const char *magnet_manifest(const struct MagnetHandle *magnet);
+
+// Get the size (in bytes) of the torrent
+//
+// Returns `false` and leaves `length` untouched if the magnet has no length.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet, and `length` must be `NULL` or writable.
+bool magnet_length(const struct MagnetHandle *magnet, uint64_t *length);
+
+// Get the number of tracker URLs
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+uintptr_t magnet_tracker_count(const struct MagnetHandle *magnet);
+
+// Get the tracker URL at `index`, or `NULL` if it is out of range
+//
+// The string is borrowed from `magnet`.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+const char *magnet_tracker(const struct MagnetHandle *magnet, uintptr_t index);
+
+// Get the number of peer addresses (`x.pe`)
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+uintptr_t magnet_peer_count(const struct MagnetHandle *magnet);
+
+// Get the peer address at `index`, or `NULL` if it is out of range
+//
+// The string is borrowed from `magnet`.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+const char *magnet_peer(const struct MagnetHandle *magnet, uintptr_t index);
+
+// Generate the magnet URL string
+//
+// Returns `NULL` if `magnet` is `NULL`. The result must be released with `magnet_string_free`.
+//
+// # Safety
+//
+// `magnet` must be `NULL` or a valid magnet returned by this library.
+char *magnet_to_string(const struct MagnetHandle *magnet);
+
+// Release a string returned by `magnet_to_string`. Passing `NULL` does nothing.
+//
+// # Safety
+//
+// `string` must be `NULL` or a string returned by `magnet_to_string` that was not freed yet.
+void magnet_string_free(char *string);
+
+// Create a new builder, which must be consumed by `magnet_builder_build` or released with
+// `magnet_builder_free`
+struct MagnetBuilderHandle *magnet_builder_new(void);
+
+// Release a builder without building it. Passing `NULL` does nothing.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a builder returned by `magnet_builder_new` that was not freed
+// or built yet.
+void magnet_builder_free(struct MagnetBuilderHandle *builder);
+
+// Build the magnet, consuming the builder
+//
+// Returns `NULL` if `builder` is `NULL`. The result must be released with `magnet_free`.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a builder returned by `magnet_builder_new` that was not freed
+// or built yet.
+struct MagnetHandle *magnet_builder_build(struct MagnetBuilderHandle *builder);
+
+// Set the display name of the torrent
+//
+// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+// not valid UTF-8.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+// NUL-terminated string.
+bool magnet_builder_display_name(struct MagnetBuilderHandle *builder, const char *value);
+
+// Set the hash type used in the exact topic
+//
+// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+// not valid UTF-8.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+// NUL-terminated string.
+bool magnet_builder_hash_type(struct MagnetBuilderHandle *builder, const char *value);
+
+// Set the torrent hash
+//
+// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+// not valid UTF-8.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+// NUL-terminated string.
+bool magnet_builder_hash(struct MagnetBuilderHandle *builder, const char *value);
+
+// Set the download source for the file
+//
+// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+// not valid UTF-8.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+// NUL-terminated string.
+bool magnet_builder_source(struct MagnetBuilderHandle *builder, const char *value);
+
+// Add a tracker URL
+//
+// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+// not valid UTF-8.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+// NUL-terminated string.
+bool magnet_builder_add_tracker(struct MagnetBuilderHandle *builder, const char *value);
+
+// Set the search keywords
+//
+// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+// not valid UTF-8.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+// NUL-terminated string.
+bool magnet_builder_search_keywords(struct MagnetBuilderHandle *builder, const char *value);
+
+// Set the web seed URL
+//
+// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+// not valid UTF-8.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+// NUL-terminated string.
+bool magnet_builder_web_seed(struct MagnetBuilderHandle *builder, const char *value);
+
+// Set the acceptable source
+//
+// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+// not valid UTF-8.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+// NUL-terminated string.
+bool magnet_builder_acceptable_source(struct MagnetBuilderHandle *builder, const char *value);
+
+// Set the manifest link
+//
+// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+// not valid UTF-8.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+// NUL-terminated string.
+bool magnet_builder_manifest(struct MagnetBuilderHandle *builder, const char *value);
+
+// Set the size (in bytes) of the torrent
+//
+// Returns `false` if `builder` is `NULL`.
+//
+// # Safety
+//
+// `builder` must be `NULL` or a valid builder returned by `magnet_builder_new`.
+bool magnet_builder_length(struct MagnetBuilderHandle *builder, uint64_t length);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MAGNET_URL_H */
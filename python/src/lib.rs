@@ -0,0 +1,4 @@
+//! The `magnet_url` Python extension module, defined in the `python` module of `magnet-url`
+
+// Only linked for the `PyInit_magnet_url` entry point it exports
+extern crate magnet_url;
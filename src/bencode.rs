@@ -0,0 +1,297 @@
+//! A minimal bencode decoder, enough to read `.torrent` files, and the matching encoder
+
+use alloc::vec::Vec;
+
+/// A decoded bencode value, borrowing its byte strings from the input
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+    List(Vec<Value<'a>>),
+    /// Dictionary entries in the order they appeared in the input
+    Dict(Vec<(&'a [u8], Value<'a>)>),
+}
+
+impl<'a> Value<'a> {
+    /// Look up a key if this value is a dictionary
+    pub(crate) fn get(&self, key: &str) -> Option<&Value<'a>> {
+        match self {
+            Value::Dict(entries) => entries
+                .iter()
+                .find(|(k, _)| *k == key.as_bytes())
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&'a str> {
+        self.as_bytes().and_then(|b| core::str::from_utf8(b).ok())
+    }
+
+    pub(crate) fn as_list(&self) -> Option<&[Value<'a>]> {
+        match self {
+            Value::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_dict(&self) -> Option<&[(&'a [u8], Value<'a>)]> {
+        match self {
+            Value::Dict(d) => Some(d),
+            _ => None,
+        }
+    }
+}
+
+/// Errors that can occur while decoding bencode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DecodeError;
+
+/// Nesting limit, so deeply nested input can't overflow the stack. Torrents only nest deeply
+/// in the v2 file tree, one level per directory.
+const MAX_DEPTH: usize = 256;
+
+/// Decode a complete bencoded buffer, rejecting trailing data
+pub(crate) fn decode(data: &[u8]) -> Result<Value<'_>, DecodeError> {
+    let mut decoder = Decoder::new(data);
+    let value = decoder.value()?;
+    if decoder.pos != data.len() {
+        return Err(DecodeError);
+    }
+    Ok(value)
+}
+
+/// Decode one bencoded value at the start of a buffer, returning it with the number of bytes it
+/// took up
+#[cfg(feature = "metadata")]
+pub(crate) fn decode_prefix(data: &[u8]) -> Result<(Value<'_>, usize), DecodeError> {
+    let mut decoder = Decoder::new(data);
+    let value = decoder.value()?;
+    Ok((value, decoder.pos))
+}
+
+/// Encode a value, sorting dictionary keys as bencode requires
+#[cfg(feature = "std")]
+pub(crate) fn encode(value: &Value<'_>, out: &mut Vec<u8>) {
+    match value {
+        Value::Int(i) => {
+            out.push(b'i');
+            out.extend_from_slice(alloc::format!("{}", i).as_bytes());
+            out.push(b'e');
+        }
+        Value::Bytes(bytes) => encode_bytes(bytes, out),
+        Value::List(list) => {
+            out.push(b'l');
+            for item in list {
+                encode(item, out);
+            }
+            out.push(b'e');
+        }
+        Value::Dict(entries) => {
+            let mut sorted: Vec<_> = entries.iter().collect();
+            sorted.sort_by_key(|(key, _)| *key);
+            out.push(b'd');
+            for (key, value) in sorted {
+                encode_bytes(key, out);
+                encode(value, out);
+            }
+            out.push(b'e');
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn encode_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(alloc::format!("{}:", bytes.len()).as_bytes());
+    out.extend_from_slice(bytes);
+}
+
+/// Return the raw, still-encoded bytes of a key in a top-level dictionary
+///
+/// The info hash of a torrent is computed over the exact bytes of its `info` dictionary, so
+/// this avoids re-encoding (which would change the hash for non-canonical files).
+pub(crate) fn raw_dict_value<'a>(data: &'a [u8], key: &str) -> Option<&'a [u8]> {
+    let mut decoder = Decoder::new(data);
+    if decoder.peek()? != b'd' {
+        return None;
+    }
+    decoder.pos += 1;
+    while decoder.peek()? != b'e' {
+        let k = decoder.bytes().ok()?;
+        let start = decoder.pos;
+        decoder.value().ok()?;
+        if k == key.as_bytes() {
+            return Some(&data[start..decoder.pos]);
+        }
+    }
+    None
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Decoder {
+            data,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn value(&mut self) -> Result<Value<'a>, DecodeError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(DecodeError);
+        }
+        let value = self.value_inner();
+        self.depth -= 1;
+        value
+    }
+
+    fn value_inner(&mut self) -> Result<Value<'a>, DecodeError> {
+        match self.peek().ok_or(DecodeError)? {
+            b'i' => {
+                self.pos += 1;
+                let int = self.int_until(b'e')?;
+                Ok(Value::Int(int))
+            }
+            b'l' => {
+                self.pos += 1;
+                let mut list = Vec::new();
+                while self.peek().ok_or(DecodeError)? != b'e' {
+                    list.push(self.value()?);
+                }
+                self.pos += 1;
+                Ok(Value::List(list))
+            }
+            b'd' => {
+                self.pos += 1;
+                let mut entries = Vec::new();
+                while self.peek().ok_or(DecodeError)? != b'e' {
+                    let key = self.bytes()?;
+                    let value = self.value()?;
+                    entries.push((key, value));
+                }
+                self.pos += 1;
+                Ok(Value::Dict(entries))
+            }
+            b'0'..=b'9' => Ok(Value::Bytes(self.bytes()?)),
+            _ => Err(DecodeError),
+        }
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.int_until(b':')?;
+        let len = usize::try_from(len).map_err(|_| DecodeError)?;
+        let end = self.pos.checked_add(len).ok_or(DecodeError)?;
+        let bytes = self.data.get(self.pos..end).ok_or(DecodeError)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn int_until(&mut self, terminator: u8) -> Result<i64, DecodeError> {
+        let rest = &self.data[self.pos..];
+        let end = rest.iter().position(|&b| b == terminator).ok_or(DecodeError)?;
+        let text = core::str::from_utf8(&rest[..end]).map_err(|_| DecodeError)?;
+        let int = text.parse::<i64>().map_err(|_| DecodeError)?;
+        self.pos += end + 1;
+        Ok(int)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, raw_dict_value, Value};
+    #[cfg(feature = "std")]
+    use alloc::vec::Vec;
+
+    #[test]
+    fn decode_test() {
+        let value = decode(b"d3:bar4:spam3:fooi42e4:listl1:ai-3eee").unwrap();
+        assert_eq!(value.get("bar").and_then(Value::as_str), Some("spam"));
+        assert_eq!(value.get("foo").and_then(Value::as_int), Some(42));
+        assert_eq!(
+            value.get("list").and_then(Value::as_list),
+            Some(&[Value::Bytes(b"a"), Value::Int(-3)][..])
+        );
+        assert!(value.get("missing").is_none());
+    }
+
+    #[test]
+    fn invalid_input_test() {
+        assert!(decode(b"").is_err());
+        assert!(decode(b"i42").is_err());
+        assert!(decode(b"5:abc").is_err());
+        assert!(decode(b"i1ei2e").is_err());
+        assert!(decode(b"d3:fooe").is_err());
+    }
+
+    #[test]
+    fn depth_limit_test() {
+        let nested = |depth: usize| {
+            let mut data = alloc::vec![b'l'; depth];
+            data.extend(core::iter::repeat_n(b'e', depth));
+            data
+        };
+        assert!(decode(&nested(super::MAX_DEPTH)).is_ok());
+        assert!(decode(&nested(super::MAX_DEPTH + 1)).is_err());
+
+        // Far too deep to recurse into without the limit
+        let mut torrent = b"d4:info".to_vec();
+        torrent.extend(nested(1_000_000));
+        torrent.push(b'e');
+        assert!(decode(&torrent).is_err());
+        assert_eq!(raw_dict_value(&torrent, "info"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "metadata")]
+    fn decode_prefix_test() {
+        let (value, len) = super::decode_prefix(b"d1:ai1eeraw bytes").unwrap();
+        assert_eq!(value.get("a"), Some(&Value::Int(1)));
+        assert_eq!(len, 8);
+        assert!(super::decode_prefix(b"d1:ai1e").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn encode_test() {
+        let value = Value::Dict(alloc::vec![
+            (&b"foo"[..], Value::Int(-42)),
+            (&b"bar"[..], Value::List(alloc::vec![Value::Bytes(b"spam"), Value::Int(0)])),
+        ]);
+        let mut out = Vec::new();
+        super::encode(&value, &mut out);
+        assert_eq!(out, b"d3:barl4:spami0ee3:fooi-42ee");
+        assert_eq!(decode(&out).unwrap().get("foo"), Some(&Value::Int(-42)));
+    }
+
+    #[test]
+    fn raw_dict_value_test() {
+        let data = b"d8:announce3:url4:infod4:name1:xee";
+        assert_eq!(raw_dict_value(data, "info"), Some(&b"d4:name1:xe"[..]));
+        assert_eq!(raw_dict_value(data, "missing"), None);
+    }
+}
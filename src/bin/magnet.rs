@@ -0,0 +1,880 @@
+//! The `magnet` command-line tool, a thin wrapper around `Magnet` and `MagnetBuilder`
+//!
+//! Links are read from the command line or, when none are given, from stdin (one per line).
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use magnet_url::{Bitprint, Cid, Magnet, MagnetBuilder, MagnetError, QrErrorCorrection, Sha1Hash, TigerTreeHash};
+
+const USAGE: &str = "\
+usage: magnet <command> [options] [LINK...]
+
+commands:
+  parse [--json] [LINK...]   print the fields of each magnet link
+  build [OPTIONS]            build a magnet link from its fields
+  validate [LINK...]         check magnet links, exiting with status 1 if any is invalid
+  normalize [LINK...]        print magnet links in canonical form
+  from-torrent [FILE...]     print the magnet link of .torrent files (`-` or none for stdin)
+  qr [OPTIONS] [LINK]        print a magnet link as a QR code
+
+build options:
+  --display-name NAME        (dn)
+  --hash-type TYPE           (xt, defaults to btih)
+  --hash HASH                (xt)
+  --length BYTES             (xl)
+  --tracker URL              (tr, can be repeated)
+  --search-keywords WORDS    (kt)
+  --web-seed URL             (ws)
+  --source URL               (xs)
+  --acceptable-source URL    (as)
+  --manifest URL             (mt)
+
+qr options:
+  --format FORMAT            text (the default), svg or png
+  --level LEVEL              lowest error correction level: L, M (the default), Q or H
+  --scale PIXELS             pixels per module in PNG images (defaults to 8)
+  --border MODULES           width of the quiet zone (defaults to 4, at most 100)
+
+Without LINK arguments, links are read from stdin, one per line.";
+
+/// Exit status when every input was handled successfully
+const EXIT_OK: i32 = 0;
+/// Exit status when at least one input link or file was invalid
+const EXIT_INVALID: i32 = 1;
+/// Exit status for usage and I/O errors
+const EXIT_USAGE: i32 = 2;
+
+/// Widest quiet zone `qr` draws, so a typo can't produce gigabytes of output
+const MAX_BORDER: u32 = 100;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let stderr = io::stderr();
+
+    let code = run(&args, &mut stdin.lock(), &mut stdout.lock(), &mut stderr.lock());
+    process::exit(code);
+}
+
+/// Run the tool with the given arguments (without the program name) and return the exit status
+fn run(args: &[String], stdin: &mut dyn BufRead, out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    let result = match args.first().map(String::as_str) {
+        Some("parse") => parse(&args[1..], stdin, out, err),
+        Some("build") => build(&args[1..], out),
+        Some("validate") => validate(&args[1..], stdin, out),
+        Some("normalize") => normalize(&args[1..], stdin, out, err),
+        Some("from-torrent") => from_torrent(&args[1..], stdin, out, err),
+        Some("qr") => qr(&args[1..], stdin, out, err),
+        Some("-h") | Some("--help") | Some("help") => writeln!(out, "{}", USAGE)
+            .map(|_| EXIT_OK)
+            .map_err(CliError::Io),
+        Some(command) => Err(CliError::Usage(format!("unknown command `{}`", command))),
+        None => Err(CliError::Usage(String::from("missing command"))),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(CliError::Usage(message)) => {
+            let _ = writeln!(err, "error: {}\n\n{}", message, USAGE);
+            EXIT_USAGE
+        }
+        Err(CliError::Io(e)) => {
+            let _ = writeln!(err, "error: {}", e);
+            EXIT_USAGE
+        }
+    }
+}
+
+/// Errors that stop the tool before all inputs are processed
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Io(io::Error),
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        CliError::Io(e)
+    }
+}
+
+/// Call `f` with each link and its position, taken from `links` or else from the lines of stdin
+///
+/// Blank lines are skipped, but still counted so positions match the input line numbers.
+fn for_each_link(
+    links: &[String],
+    stdin: &mut dyn BufRead,
+    mut f: impl FnMut(usize, &str) -> Result<(), CliError>,
+) -> Result<(), CliError> {
+    if !links.is_empty() {
+        for (i, link) in links.iter().enumerate() {
+            f(i + 1, link.trim())?;
+        }
+        return Ok(());
+    }
+
+    let mut line = String::new();
+    let mut line_number = 0;
+    loop {
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        line_number += 1;
+        let link = line.trim();
+        if !link.is_empty() {
+            f(line_number, link)?;
+        }
+    }
+}
+
+fn parse(args: &[String], stdin: &mut dyn BufRead, out: &mut dyn Write, err: &mut dyn Write) -> Result<i32, CliError> {
+    let (json, links) = match args.first().map(String::as_str) {
+        Some("--json") => (true, &args[1..]),
+        _ => (false, args),
+    };
+    reject_options(links)?;
+
+    let mut code = EXIT_OK;
+    let mut first = true;
+    for_each_link(links, stdin, |line, link| {
+        match Magnet::new(link) {
+            Ok(magnet) if json => writeln!(out, "{}", to_json(&magnet))?,
+            Ok(magnet) => {
+                if !first {
+                    writeln!(out)?;
+                }
+                first = false;
+                write_text(&magnet, out)?;
+            }
+            Err(e) => {
+                writeln!(err, "{}: {}", line, e)?;
+                code = EXIT_INVALID;
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(code)
+}
+
+fn build(args: &[String], out: &mut dyn Write) -> Result<i32, CliError> {
+    let mut builder = MagnetBuilder::new().hash_type("btih");
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, value.to_string()),
+            _ => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("missing value for `{}`", arg)))?;
+                (arg.as_str(), value.clone())
+            }
+        };
+
+        builder = match flag {
+            "--display-name" => builder.display_name(&value),
+            "--hash-type" => builder.hash_type(&value),
+            "--hash" => builder.hash(&value),
+            "--length" => builder.length(
+                value
+                    .parse()
+                    .map_err(|_| CliError::Usage(format!("invalid length `{}`", value)))?,
+            ),
+            "--tracker" => builder.add_tracker(&value),
+            "--search-keywords" => builder.search_keywords(&value),
+            "--web-seed" => builder.web_seed(&value),
+            "--source" => builder.source(&value),
+            "--acceptable-source" => builder.acceptable_source(&value),
+            "--manifest" => builder.manifest(&value),
+            _ => return Err(CliError::Usage(format!("unknown option `{}`", flag))),
+        };
+    }
+
+    let magnet = builder.build();
+    if magnet.hash().is_none() {
+        return Err(CliError::Usage(String::from("`--hash` is required")));
+    }
+
+    writeln!(out, "{}", magnet)?;
+    Ok(EXIT_OK)
+}
+
+fn validate(args: &[String], stdin: &mut dyn BufRead, out: &mut dyn Write) -> Result<i32, CliError> {
+    reject_options(args)?;
+
+    let mut code = EXIT_OK;
+    for_each_link(args, stdin, |line, link| {
+        let problems = check_link(link);
+        if problems.is_empty() {
+            writeln!(out, "{}: ok", line)?;
+        } else {
+            code = EXIT_INVALID;
+            writeln!(out, "{}: invalid", line)?;
+            for problem in problems {
+                writeln!(out, "  - {}", problem)?;
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(code)
+}
+
+fn normalize(args: &[String], stdin: &mut dyn BufRead, out: &mut dyn Write, err: &mut dyn Write) -> Result<i32, CliError> {
+    reject_options(args)?;
+
+    let mut code = EXIT_OK;
+    for_each_link(args, stdin, |line, link| {
+        match Magnet::new(link) {
+            Ok(magnet) => writeln!(out, "{}", normalized(&magnet))?,
+            Err(e) => {
+                writeln!(err, "{}: {}", line, e)?;
+                code = EXIT_INVALID;
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(code)
+}
+
+fn from_torrent(args: &[String], stdin: &mut dyn BufRead, out: &mut dyn Write, err: &mut dyn Write) -> Result<i32, CliError> {
+    let stdin_only = [String::from("-")];
+    let files = if args.is_empty() { &stdin_only[..] } else { args };
+
+    let mut code = EXIT_OK;
+    for file in files {
+        let data = if file == "-" {
+            let mut data = Vec::new();
+            stdin.read_to_end(&mut data)?;
+            data
+        } else {
+            match fs::read(file) {
+                Ok(data) => data,
+                Err(e) => {
+                    writeln!(err, "{}: {}", file, e)?;
+                    code = EXIT_INVALID;
+                    continue;
+                }
+            }
+        };
+
+        match Magnet::from_torrent(&data) {
+            Ok(magnet) => writeln!(out, "{}", magnet)?,
+            Err(e) => {
+                writeln!(err, "{}: {}", file, e)?;
+                code = EXIT_INVALID;
+            }
+        }
+    }
+
+    Ok(code)
+}
+
+fn qr(args: &[String], stdin: &mut dyn BufRead, out: &mut dyn Write, err: &mut dyn Write) -> Result<i32, CliError> {
+    let mut format = String::from("text");
+    let mut level = QrErrorCorrection::Medium;
+    let mut scale = 8;
+    let mut border = 4;
+    let mut links = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            links.push(arg.clone());
+            continue;
+        }
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, value.to_string()),
+            None => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("missing value for `{}`", arg)))?;
+                (arg.as_str(), value.clone())
+            }
+        };
+        let number = |value: &str| {
+            value
+                .parse::<u32>()
+                .map_err(|_| CliError::Usage(format!("invalid number `{}` for `{}`", value, flag)))
+        };
+
+        match flag {
+            "--format" if matches!(value.as_str(), "text" | "svg" | "png") => format = value,
+            "--level" => {
+                level = match value.to_ascii_uppercase().as_str() {
+                    "L" => QrErrorCorrection::Low,
+                    "M" => QrErrorCorrection::Medium,
+                    "Q" => QrErrorCorrection::Quartile,
+                    "H" => QrErrorCorrection::High,
+                    _ => return Err(CliError::Usage(format!("invalid level `{}`", value))),
+                }
+            }
+            "--scale" => scale = number(&value)?,
+            "--border" => {
+                border = number(&value)?;
+                if border > MAX_BORDER {
+                    return Err(CliError::Usage(format!("`--border` must be at most {}", MAX_BORDER)));
+                }
+            }
+            "--format" => return Err(CliError::Usage(format!("invalid format `{}`", value))),
+            _ => return Err(CliError::Usage(format!("unknown option `{}`", flag))),
+        }
+    }
+    if links.len() > 1 {
+        return Err(CliError::Usage(String::from("qr takes a single link")));
+    }
+
+    // Only the first link is encoded when reading stdin
+    let mut link = None;
+    for_each_link(&links, stdin, |_, text| {
+        link.get_or_insert_with(|| text.to_string());
+        Ok(())
+    })?;
+    let link = link.ok_or_else(|| CliError::Usage(String::from("missing link")))?;
+
+    let magnet = match Magnet::new(&link) {
+        Ok(magnet) => magnet,
+        Err(e) => {
+            writeln!(err, "{}", e)?;
+            return Ok(EXIT_INVALID);
+        }
+    };
+
+    let code = match magnet.to_qr_code(level) {
+        Ok(code) => code,
+        Err(e) => {
+            // Fall back to the highest lower level the link still fits at, if any
+            let fallback = [QrErrorCorrection::Quartile, QrErrorCorrection::Medium, QrErrorCorrection::Low]
+                .into_iter()
+                .filter(|&lower| lower < level)
+                .find_map(|lower| magnet.to_qr_code(lower).ok());
+            match fallback {
+                Some(code) => {
+                    writeln!(err, "warning: {}, using level {}", e, code.error_correction())?;
+                    code
+                }
+                None => {
+                    writeln!(err, "error: {}", e)?;
+                    return Ok(EXIT_INVALID);
+                }
+            }
+        }
+    };
+
+    match format.as_str() {
+        "svg" => out.write_all(code.to_svg(border).as_bytes())?,
+        "png" => {
+            let png = code.to_png(scale, border).map_err(|e| CliError::Usage(format!("{}, use a smaller `--scale`", e)))?;
+            out.write_all(&png)?
+        }
+        _ => out.write_all(code.to_unicode(border).as_bytes())?,
+    }
+    Ok(EXIT_OK)
+}
+
+fn reject_options(args: &[String]) -> Result<(), CliError> {
+    match args.iter().find(|arg| arg.starts_with("--")) {
+        Some(arg) => Err(CliError::Usage(format!("unknown option `{}`", arg))),
+        None => Ok(()),
+    }
+}
+
+/// Check a link more strictly than `Magnet::new`, returning a description of every problem found
+fn check_link(link: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let params = match link.strip_prefix("magnet:?") {
+        Some(params) => params,
+        None => {
+            problems.push(MagnetError::NotAMagnetURL.to_string());
+            return problems;
+        }
+    };
+
+    let mut has_exact_topic = false;
+    for (i, param) in params.split('&').enumerate() {
+        let (key, value) = match param.split_once('=') {
+            Some((key, value)) if !key.is_empty() => (key, value),
+            _ => {
+                problems.push(format!("parameter {} (`{}`) is not of the form key=value", i + 1, param));
+                continue;
+            }
+        };
+
+        match key {
+            _ if is_exact_topic_key(key) => {
+                has_exact_topic = true;
+                match value.strip_prefix("urn:").and_then(split_urn) {
+                    Some((hash_type, hash)) if !hash_type.is_empty() && !hash.is_empty() => {
+                        if let Some(problem) = check_hash(hash_type, hash) {
+                            problems.push(problem);
+                        }
+                    }
+                    _ => problems.push(format!("exact topic `{}` is not of the form urn:<type>:<hash>", value)),
+                }
+            }
+            "xl" if value.parse::<u64>().is_err() => {
+                problems.push(format!("exact length `{}` is not a number of bytes", value))
+            }
+            _ => {}
+        }
+    }
+
+    if !has_exact_topic {
+        problems.push(String::from("missing exact topic (xt)"));
+    }
+
+    problems
+}
+
+/// Whether a parameter is an exact topic: `xt`, or `xt.1`, `xt.2`, ... in magnets with several
+fn is_exact_topic_key(key: &str) -> bool {
+    key == "xt"
+        || key
+            .strip_prefix("xt.")
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Split the part of an exact topic after `urn:` into its hash type and hash, like
+/// `Magnet::new`: only the `tree:tiger` hash types contain a colon
+fn split_urn(urn: &str) -> Option<(&str, &str)> {
+    for hash_type in ["tree:tiger", "tree:tiger/", "tree:tiger/1024"] {
+        let prefix = urn.get(..hash_type.len()).filter(|prefix| prefix.eq_ignore_ascii_case(hash_type));
+        if let Some(hash) = prefix.and_then(|_| urn[hash_type.len()..].strip_prefix(':')) {
+            return Some((&urn[..hash_type.len()], hash));
+        }
+    }
+    urn.split_once(':')
+}
+
+/// Check the encoding of the hash types with a well-known format: BitTorrent info hashes and
+/// the Gnutella SHA-1, Tiger tree and bitprint hashes
+fn check_hash(hash_type: &str, hash: &str) -> Option<String> {
+    let (valid, format) = match hash_type.to_ascii_lowercase().as_str() {
+        "btih" => {
+            let is_hex = hash.len() == 40 && hash.bytes().all(|b| b.is_ascii_hexdigit());
+            let is_base32 = hash.len() == 32 && hash.bytes().all(|b| matches!(b.to_ascii_uppercase(), b'A'..=b'Z' | b'2'..=b'7'));
+            (is_hex || is_base32, "40 hexadecimal or 32 base32 characters")
+        }
+        "sha1" => (Sha1Hash::parse(hash).is_some(), "32 base32 or 40 hexadecimal characters"),
+        "tree:tiger" | "tree:tiger/" | "tree:tiger/1024" => (TigerTreeHash::parse(hash).is_some(), "39 base32 characters"),
+        "bitprint" => (Bitprint::parse(hash).is_some(), "32 base32 characters, a `.` and 39 base32 characters"),
+        _ => return None,
+    };
+    if valid {
+        None
+    } else {
+        Some(format!("{} hash `{}` must be {}", hash_type, hash, format))
+    }
+}
+
+/// Hash types written in hexadecimal or base32, whose case doesn't matter
+const CASE_INSENSITIVE_HASH_TYPES: [&str; 8] = ["btih", "btmh", "sha1", "tree:tiger", "ed2k", "aich", "bitprint", "md5"];
+
+/// Write a hash in canonical form: lowercase hex or uppercase base32 for the hash types that
+/// ignore case, IPFS CIDs as IPFS writes them, and any other hash as it is
+fn normalized_hash(hash_type: &str, hash: &str) -> String {
+    // `tree:tiger/` and `tree:tiger/1024` are other spellings of `tree:tiger`
+    let base_type = hash_type.split('/').next().unwrap_or(hash_type);
+    if hash_type == "ipfs" {
+        Cid::parse(hash).map_or_else(|| hash.to_string(), |cid| cid.to_string())
+    } else if !CASE_INSENSITIVE_HASH_TYPES.contains(&base_type) {
+        hash.to_string()
+    } else if hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        hash.to_ascii_lowercase()
+    } else {
+        hash.to_ascii_uppercase()
+    }
+}
+
+/// Rebuild a magnet in canonical form: lowercase hash types, canonical hashes, no duplicate
+/// exact topics, trackers or peers
+fn normalized(magnet: &Magnet) -> Magnet {
+    let mut builder = MagnetBuilder::new();
+
+    let mut topics: Vec<(String, String)> = Vec::new();
+    for (hash_type, hash) in magnet.exact_topics() {
+        let hash_type = hash_type.to_ascii_lowercase();
+        let hash = normalized_hash(&hash_type, hash);
+        let topic = (hash_type, hash);
+        if !topics.contains(&topic) {
+            topics.push(topic);
+        }
+    }
+    for (hash_type, hash) in &topics {
+        builder = builder.add_exact_topic(hash_type, hash);
+    }
+    if let Some(name) = magnet.display_name() {
+        builder = builder.display_name(name);
+    }
+    if let Some(length) = magnet.length() {
+        builder = builder.length(length);
+    }
+
+    let mut trackers: Vec<&str> = Vec::new();
+    for tracker in magnet.trackers() {
+        if !tracker.is_empty() && !trackers.contains(&tracker.as_str()) {
+            trackers.push(tracker);
+        }
+    }
+    builder = builder.add_trackers(&trackers);
+
+    let mut peers: Vec<&str> = Vec::new();
+    for peer in magnet.peers() {
+        if !peer.is_empty() && !peers.contains(&peer.as_str()) {
+            peers.push(peer);
+        }
+    }
+    for peer in peers {
+        builder = builder.add_peer(peer);
+    }
+
+    if let Some(web_seed) = magnet.web_seed() {
+        builder = builder.web_seed(web_seed);
+    }
+    if let Some(source) = magnet.source() {
+        builder = builder.source(source);
+    }
+    if let Some(keywords) = magnet.search_keywords() {
+        builder = builder.search_keywords(keywords);
+    }
+    if let Some(source) = magnet.acceptable_source() {
+        builder = builder.acceptable_source(source);
+    }
+    if let Some(manifest) = magnet.manifest() {
+        builder = builder.manifest(manifest);
+    }
+
+    builder.build()
+}
+
+fn write_text(magnet: &Magnet, out: &mut dyn Write) -> io::Result<()> {
+    let fields = [
+        ("display_name", magnet.display_name()),
+        ("hash_type", magnet.hash_type()),
+        ("hash", magnet.hash()),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            writeln!(out, "{}: {}", name, value)?;
+        }
+    }
+    for (hash_type, hash) in magnet.exact_topics().skip(1) {
+        writeln!(out, "extra_topic: urn:{}:{}", hash_type, hash)?;
+    }
+    if let Some(length) = magnet.length() {
+        writeln!(out, "length: {}", length)?;
+    }
+    for tracker in magnet.trackers() {
+        writeln!(out, "tracker: {}", tracker)?;
+    }
+    for peer in magnet.peers() {
+        writeln!(out, "peer: {}", peer)?;
+    }
+
+    let fields = [
+        ("web_seed", magnet.web_seed()),
+        ("source", magnet.source()),
+        ("search_keywords", magnet.search_keywords()),
+        ("acceptable_source", magnet.acceptable_source()),
+        ("manifest", magnet.manifest()),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            writeln!(out, "{}: {}", name, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Format a magnet as a single-line JSON object, with `null` for missing fields
+fn to_json(magnet: &Magnet) -> String {
+    let string = |value: Option<&str>| value.map_or_else(|| String::from("null"), json_string);
+
+    let extra_topics: Vec<String> = magnet
+        .exact_topics()
+        .skip(1)
+        .map(|(hash_type, hash)| json_string(&format!("urn:{}:{}", hash_type, hash)))
+        .collect();
+    let trackers: Vec<String> = magnet.trackers().iter().map(|t| json_string(t)).collect();
+    let peers: Vec<String> = magnet.peers().iter().map(|p| json_string(p)).collect();
+
+    format!(
+        "{{\"display_name\":{},\"hash_type\":{},\"hash\":{},\"extra_topics\":[{}],\"length\":{},\"trackers\":[{}],\"peers\":[{}],\"web_seed\":{},\"source\":{},\"search_keywords\":{},\"acceptable_source\":{},\"manifest\":{}}}",
+        string(magnet.display_name()),
+        string(magnet.hash_type()),
+        string(magnet.hash()),
+        extra_topics.join(","),
+        magnet.length().map_or_else(|| String::from("null"), |l| l.to_string()),
+        trackers.join(","),
+        peers.join(","),
+        string(magnet.web_seed()),
+        string(magnet.source()),
+        string(magnet.search_keywords()),
+        string(magnet.acceptable_source()),
+        string(magnet.manifest()),
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, EXIT_INVALID, EXIT_OK, EXIT_USAGE};
+    use magnet_url::{QrCode, QrErrorCorrection};
+    use std::io::Cursor;
+
+    const SINTEL: &str = "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel&tr=udp%3A%2F%2Fexplodie.org%3A6969";
+
+    /// Run the tool and return its exit status, stdout and stderr
+    fn run_with(args: &[&str], stdin: &str) -> (i32, String, String) {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let code = run(&args, &mut Cursor::new(stdin.as_bytes()), &mut out, &mut err);
+        (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn parse_text_test() {
+        let (code, out, err) = run_with(&["parse", SINTEL], "");
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(
+            out,
+            "display_name: Sintel\nhash_type: btih\nhash: 08ada5a7a6183aae1e09d831df6748d566095a10\ntracker: udp%3A%2F%2Fexplodie.org%3A6969\n"
+        );
+        assert!(err.is_empty());
+
+        let (_, out, _) = run_with(&["parse", &format!("{}&x.pe=10.0.0.1:6881", SINTEL)], "");
+        assert!(out.ends_with("tracker: udp%3A%2F%2Fexplodie.org%3A6969\npeer: 10.0.0.1:6881\n"));
+
+        let (_, out, _) = run_with(&["parse", "--json", &format!("{}&xt=urn:aich:W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW", SINTEL)], "");
+        assert!(out.contains("\"extra_topics\":[\"urn:aich:W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW\"]"));
+        let (_, out, _) = run_with(&["parse", &format!("{}&xt=urn:aich:W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW", SINTEL)], "");
+        assert!(out.contains("hash: 08ada5a7a6183aae1e09d831df6748d566095a10\nextra_topic: urn:aich:W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW\n"));
+    }
+
+    #[test]
+    fn parse_json_from_stdin_test() {
+        let stdin = format!("{}\n\nhttps://example.com\n", SINTEL);
+        let (code, out, err) = run_with(&["parse", "--json"], &stdin);
+        assert_eq!(code, EXIT_INVALID);
+        assert_eq!(
+            out,
+            "{\"display_name\":\"Sintel\",\"hash_type\":\"btih\",\"hash\":\"08ada5a7a6183aae1e09d831df6748d566095a10\",\"extra_topics\":[],\"length\":null,\"trackers\":[\"udp%3A%2F%2Fexplodie.org%3A6969\"],\"peers\":[],\"web_seed\":null,\"source\":null,\"search_keywords\":null,\"acceptable_source\":null,\"manifest\":null}\n"
+        );
+        assert_eq!(err, "3: provided link is not a valid magnet URL\n");
+    }
+
+    #[test]
+    fn build_test() {
+        let (code, out, _) = run_with(
+            &[
+                "build",
+                "--hash",
+                "1234567890abcdef1234567890abcdef12345678",
+                "--display-name=Test",
+                "--length",
+                "42",
+                "--tracker",
+                "udp://a.example:1",
+                "--tracker",
+                "udp://b.example:2",
+            ],
+            "",
+        );
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(
+            out,
+            "magnet:?xt=urn:btih:1234567890abcdef1234567890abcdef12345678&dn=Test&xl=42&tr=udp://a.example:1&tr=udp://b.example:2\n"
+        );
+
+        assert_eq!(run_with(&["build", "--length", "x", "--hash", "ab"], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["build", "--display-name", "x"], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["build", "--bogus", "x"], "").0, EXIT_USAGE);
+    }
+
+    #[test]
+    fn validate_test() {
+        let (code, out, _) = run_with(&["validate", SINTEL], "");
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(out, "1: ok\n");
+
+        let (code, out, _) = run_with(&["validate"], "magnet:?xt=urn:btih:xyz&xl=big&dn\nfoo\n");
+        assert_eq!(code, EXIT_INVALID);
+        assert_eq!(
+            out,
+            "1: invalid\n  - btih hash `xyz` must be 40 hexadecimal or 32 base32 characters\n  - exact length `big` is not a number of bytes\n  - parameter 3 (`dn`) is not of the form key=value\n2: invalid\n  - provided link is not a valid magnet URL\n"
+        );
+
+        let (code, out, _) = run_with(&["validate", "magnet:?dn=x"], "");
+        assert_eq!(code, EXIT_INVALID);
+        assert_eq!(out, "1: invalid\n  - missing exact topic (xt)\n");
+
+        let (code, out, _) = run_with(
+            &["validate", "magnet:?xt.1=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&xt.2=urn:btih:xyz"],
+            "",
+        );
+        assert_eq!(code, EXIT_INVALID);
+        assert_eq!(out, "1: invalid\n  - btih hash `xyz` must be 40 hexadecimal or 32 base32 characters\n");
+
+        let (code, out, _) = run_with(
+            &["validate", "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&xt=urn:tree:tiger:abc"],
+            "",
+        );
+        assert_eq!(code, EXIT_INVALID);
+        assert_eq!(out, "1: invalid\n  - tree:tiger hash `abc` must be 39 base32 characters\n");
+
+        let (code, out, _) = run_with(
+            &["validate"],
+            "magnet:?xt=urn:sha1:abc\nmagnet:?xt=urn:tree:tiger/1024:abc\nmagnet:?xt=urn:bitprint:abc.def\n",
+        );
+        assert_eq!(code, EXIT_INVALID);
+        assert_eq!(
+            out,
+            "1: invalid\n  - sha1 hash `abc` must be 32 base32 or 40 hexadecimal characters\n2: invalid\n  - tree:tiger/1024 hash `abc` must be 39 base32 characters\n3: invalid\n  - bitprint hash `abc.def` must be 32 base32 characters, a `.` and 39 base32 characters\n"
+        );
+
+        // Valid Gnutella hashes, and colons in the hash of other types
+        let (code, out, _) = run_with(
+            &["validate", "magnet:?xt=urn:tree:tiger/:LWPNACQDBZRYXW3VHJVCJ64QBZNGHOHHHZWCLNQ&xt=urn:sha1:3I42H3S6NNFQ2MSVX7XZKYAYSCX5QBYJ&xt=urn:x-custom:a:b"],
+            "",
+        );
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(out, "1: ok\n");
+    }
+
+    #[test]
+    fn normalize_test() {
+        let (code, out, _) = run_with(
+            &["normalize", "magnet:?tr=udp://a:1&x.pe=10.0.0.1:6881&dn=x&xt=urn:BTIH:08ADA5A7A6183AAE1E09D831DF6748D566095A10&tr=udp://a:1&x.pe=10.0.0.1:6881"],
+            "",
+        );
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(
+            out,
+            "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=x&tr=udp://a:1&x.pe=10.0.0.1:6881\n"
+        );
+
+        let (_, out, _) = run_with(
+            &["normalize", "magnet:?xt.1=urn:ED2K:354B15E68FB8F36D7CD88FF94116CDC1&xt.2=urn:aich:w4oia4p5sdxgz7scwzhcmgrjlhsgs4dw&xt.3=urn:ed2k:354b15e68fb8f36d7cd88ff94116cdc1"],
+            "",
+        );
+        assert_eq!(
+            out,
+            "magnet:?xt=urn:ed2k:354b15e68fb8f36d7cd88ff94116cdc1&xt=urn:aich:W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW\n"
+        );
+    }
+
+    #[test]
+    fn normalize_case_sensitive_hash_test() {
+        let (_, out, _) = run_with(
+            &["normalize", "magnet:?xt=urn:ipfs:QmY7Yh4UquoXHLPFo2XbhXkhBvFoPwmQUSa92pxnxjQuPU&dn=x"],
+            "",
+        );
+        assert_eq!(out, "magnet:?xt=urn:ipfs:QmY7Yh4UquoXHLPFo2XbhXkhBvFoPwmQUSa92pxnxjQuPU&dn=x\n");
+
+        // Base32 CIDs are written in lowercase, and unknown hashes are left alone
+        let (_, out, _) = run_with(
+            &["normalize", "magnet:?xt=urn:ipfs:BAFYBEIERHGBZ4ZP2X2U67URQRGFNRNLUKCIUPZENPQPIPIZ5NWTQ7UXPX4&xt=urn:x-custom:AbC&xt=urn:tree:tiger/:pzmryhgy6ltbeh63zwahdorhsytlo4lefuikhwy"],
+            "",
+        );
+        assert_eq!(
+            out,
+            "magnet:?xt=urn:ipfs:bafybeierhgbz4zp2x2u67urqrgfnrnlukciupzenpqpipiz5nwtq7uxpx4&xt=urn:x-custom:AbC&xt=urn:tree:tiger/:PZMRYHGY6LTBEH63ZWAHDORHSYTLO4LEFUIKHWY\n"
+        );
+    }
+
+    #[test]
+    fn from_torrent_test() {
+        let torrent = "d4:infod6:lengthi5e4:name5:hello12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+        let (code, out, _) = run_with(&["from-torrent"], torrent);
+        assert_eq!(code, EXIT_OK);
+        assert!(out.starts_with("magnet:?xt=urn:btih:"));
+        assert!(out.ends_with("&dn=hello&xl=5\n"));
+
+        let (code, _, err) = run_with(&["from-torrent", "-"], "garbage");
+        assert_eq!(code, EXIT_INVALID);
+        assert_eq!(err, "-: provided data is not a valid torrent file\n");
+    }
+
+    #[test]
+    fn qr_test() {
+        let (code, out, err) = run_with(&["qr", "--border", "1", SINTEL], "");
+        assert_eq!(code, EXIT_OK);
+        assert!(err.is_empty());
+        let code = QrCode::encode(SINTEL.as_bytes(), QrErrorCorrection::Medium).unwrap();
+        assert_eq!(out, code.to_unicode(1));
+
+        let (code, out, _) = run_with(&["qr", "--format=svg", "--level", "h"], SINTEL);
+        assert_eq!(code, EXIT_OK);
+        assert!(out.starts_with("<svg"));
+
+        let args: Vec<String> = ["qr", "--format", "png", SINTEL].iter().map(|a| a.to_string()).collect();
+        let mut out = Vec::new();
+        assert_eq!(run(&args, &mut Cursor::new(Vec::new()), &mut out, &mut Vec::new()), EXIT_OK);
+        assert_eq!(&out[1..4], b"PNG");
+
+        // Too long for level H, but not for L
+        let long = format!("{}&dn={}", SINTEL, "x".repeat(1500));
+        let (code, out, err) = run_with(&["qr", "--level", "H", &long], "");
+        assert_eq!(code, EXIT_OK);
+        assert!(!out.is_empty());
+        assert!(err.starts_with("warning: "));
+        assert!(err.ends_with("at error correction level H (at most 1273), using level Q\n"));
+
+        let long = format!("{}&dn={}", SINTEL, "x".repeat(3000));
+        let (code, out, err) = run_with(&["qr", &long], "");
+        assert_eq!(code, EXIT_INVALID);
+        assert!(out.is_empty());
+        assert!(err.starts_with("error: "));
+
+        assert_eq!(run_with(&["qr", "--format", "gif", SINTEL], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["qr", "--level", "X", SINTEL], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["qr", SINTEL, SINTEL], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["qr"], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["qr", "https://example.com"], "").0, EXIT_INVALID);
+
+        // Images too large to render are rejected instead of overflowing
+        let (code, out, err) = run_with(&["qr", "--format", "png", "--scale", "4000000000", SINTEL], "");
+        assert_eq!(code, EXIT_USAGE);
+        assert!(out.is_empty());
+        assert!(err.starts_with("error: PNG image would be larger than 16384 by 16384 pixels, use a smaller `--scale`\n"));
+        assert_eq!(run_with(&["qr", "--format", "png", "--scale", "1000", SINTEL], "").0, EXIT_USAGE);
+        let (code, _, err) = run_with(&["qr", "--border", "4000000000", SINTEL], "");
+        assert_eq!(code, EXIT_USAGE);
+        assert!(err.starts_with("error: `--border` must be at most 100\n"));
+    }
+
+    #[test]
+    fn usage_test() {
+        assert_eq!(run_with(&[], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["frobnicate"], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["parse", "--yaml"], "").0, EXIT_USAGE);
+        assert_eq!(run_with(&["--help"], "").0, EXIT_OK);
+    }
+}
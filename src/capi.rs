@@ -0,0 +1,531 @@
+//! C bindings, exported when the `capi` feature is enabled
+//!
+//! The matching header is `include/magnet_url.h`. Ownership rules:
+//!
+//! * `magnet_parse`, `magnet_from_torrent` and `magnet_builder_build` return a `MagnetHandle`
+//!   owned by the caller, which must be released with `magnet_free`.
+//! * Strings returned by the getters (`magnet_display_name`, `magnet_tracker`, ...) are borrowed
+//!   from the `MagnetHandle` and stay valid until it is freed. They must not be freed by the
+//!   caller.
+//! * `magnet_to_string` returns a string owned by the caller, which must be released with
+//!   `magnet_string_free`.
+//! * `magnet_builder_new` returns a builder owned by the caller. It is consumed by
+//!   `magnet_builder_build`, or else must be released with `magnet_builder_free`.
+//!
+//! Every function accepts `NULL` handles and strings, and treats them as missing values.
+
+use alloc::boxed::Box;
+use alloc::ffi::CString;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::ffi::{c_char, CStr};
+use core::{mem, ptr, slice};
+
+use crate::{Magnet as RustMagnet, MagnetBuilder as RustMagnetBuilder};
+
+/// A parsed magnet URL, with NUL-terminated copies of its fields for the getters
+pub struct MagnetHandle {
+    display_name: Option<CString>,
+    hash_type: Option<CString>,
+    hash: Option<CString>,
+    length: Option<u64>,
+    source: Option<CString>,
+    trackers: Vec<CString>,
+    peers: Vec<CString>,
+    search_keywords: Option<CString>,
+    web_seed: Option<CString>,
+    acceptable_source: Option<CString>,
+    manifest: Option<CString>,
+    magnet: RustMagnet,
+}
+
+impl MagnetHandle {
+    fn new(magnet: RustMagnet) -> Box<MagnetHandle> {
+        // Fields only come from C strings or percent-encoded text, so they never contain a NUL
+        let c_string = |value: Option<&str>| value.and_then(|v| CString::new(v).ok());
+
+        Box::new(MagnetHandle {
+            display_name: c_string(magnet.display_name()),
+            hash_type: c_string(magnet.hash_type()),
+            hash: c_string(magnet.hash()),
+            length: magnet.length(),
+            source: c_string(magnet.source()),
+            trackers: magnet
+                .trackers()
+                .iter()
+                .filter_map(|t| CString::new(t.as_str()).ok())
+                .collect(),
+            peers: magnet
+                .peers()
+                .iter()
+                .filter_map(|p| CString::new(p.as_str()).ok())
+                .collect(),
+            search_keywords: c_string(magnet.search_keywords()),
+            web_seed: c_string(magnet.web_seed()),
+            acceptable_source: c_string(magnet.acceptable_source()),
+            manifest: c_string(magnet.manifest()),
+            magnet,
+        })
+    }
+}
+
+/// Builder for creating magnet URLs
+pub struct MagnetBuilderHandle(RustMagnetBuilder);
+
+/// Borrow a C string as UTF-8, returning `None` for `NULL` or invalid UTF-8
+unsafe fn to_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+fn as_ptr(value: &Option<CString>) -> *const c_char {
+    value.as_ref().map_or(ptr::null(), |v| v.as_ptr())
+}
+
+/// Parse a magnet URL
+///
+/// Returns `NULL` if `link` is `NULL`, not valid UTF-8 or not a magnet URL. The result must be
+/// released with `magnet_free`.
+///
+/// # Safety
+///
+/// `link` must be `NULL` or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_parse(link: *const c_char) -> *mut MagnetHandle {
+    match to_str(link).map(RustMagnet::new) {
+        Some(Ok(magnet)) => Box::into_raw(MagnetHandle::new(magnet)),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Create a magnet URL from the contents of a `.torrent` file
+///
+/// Returns `NULL` if `data` is `NULL` or not a valid torrent. The result must be released with
+/// `magnet_free`.
+///
+/// # Safety
+///
+/// `data` must be `NULL` or point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_from_torrent(data: *const u8, len: usize) -> *mut MagnetHandle {
+    if data.is_null() {
+        return ptr::null_mut();
+    }
+    match RustMagnet::from_torrent(slice::from_raw_parts(data, len)) {
+        Ok(magnet) => Box::into_raw(MagnetHandle::new(magnet)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Release a magnet returned by this library. Passing `NULL` does nothing.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a magnet returned by this library that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_free(magnet: *mut MagnetHandle) {
+    if !magnet.is_null() {
+        drop(Box::from_raw(magnet));
+    }
+}
+
+/// Borrow an optional field of a magnet, or return `NULL`
+unsafe fn field(
+    magnet: *const MagnetHandle,
+    field: impl FnOnce(&MagnetHandle) -> &Option<CString>,
+) -> *const c_char {
+    match magnet.as_ref() {
+        Some(magnet) => as_ptr(field(magnet)),
+        None => ptr::null(),
+    }
+}
+
+/// Get the display name of the torrent
+///
+/// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_display_name(magnet: *const MagnetHandle) -> *const c_char {
+    field(magnet, |m| &m.display_name)
+}
+
+/// Get the hash type used in the exact topic
+///
+/// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_hash_type(magnet: *const MagnetHandle) -> *const c_char {
+    field(magnet, |m| &m.hash_type)
+}
+
+/// Get the torrent hash
+///
+/// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_hash(magnet: *const MagnetHandle) -> *const c_char {
+    field(magnet, |m| &m.hash)
+}
+
+/// Get the download source for the file
+///
+/// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_source(magnet: *const MagnetHandle) -> *const c_char {
+    field(magnet, |m| &m.source)
+}
+
+/// Get the search keywords
+///
+/// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_search_keywords(magnet: *const MagnetHandle) -> *const c_char {
+    field(magnet, |m| &m.search_keywords)
+}
+
+/// Get the web seed URL
+///
+/// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_web_seed(magnet: *const MagnetHandle) -> *const c_char {
+    field(magnet, |m| &m.web_seed)
+}
+
+/// Get the acceptable source
+///
+/// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_acceptable_source(magnet: *const MagnetHandle) -> *const c_char {
+    field(magnet, |m| &m.acceptable_source)
+}
+
+/// Get the manifest link
+///
+/// Returns `NULL` if the field is missing. The string is borrowed from `magnet`.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_manifest(magnet: *const MagnetHandle) -> *const c_char {
+    field(magnet, |m| &m.manifest)
+}
+
+/// Get the size (in bytes) of the torrent
+///
+/// Returns `false` and leaves `length` untouched if the magnet has no length.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet, and `length` must be `NULL` or writable.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_length(magnet: *const MagnetHandle, length: *mut u64) -> bool {
+    match (magnet.as_ref().and_then(|m| m.length), length.is_null()) {
+        (Some(value), false) => {
+            *length = value;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Get the number of tracker URLs
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_tracker_count(magnet: *const MagnetHandle) -> usize {
+    magnet.as_ref().map_or(0, |m| m.trackers.len())
+}
+
+/// Get the tracker URL at `index`, or `NULL` if it is out of range
+///
+/// The string is borrowed from `magnet`.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_tracker(magnet: *const MagnetHandle, index: usize) -> *const c_char {
+    match magnet.as_ref().and_then(|m| m.trackers.get(index)) {
+        Some(tracker) => tracker.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Get the number of peer addresses (`x.pe`)
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_peer_count(magnet: *const MagnetHandle) -> usize {
+    magnet.as_ref().map_or(0, |m| m.peers.len())
+}
+
+/// Get the peer address at `index`, or `NULL` if it is out of range
+///
+/// The string is borrowed from `magnet`.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_peer(magnet: *const MagnetHandle, index: usize) -> *const c_char {
+    match magnet.as_ref().and_then(|m| m.peers.get(index)) {
+        Some(peer) => peer.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Generate the magnet URL string
+///
+/// Returns `NULL` if `magnet` is `NULL`. The result must be released with `magnet_string_free`.
+///
+/// # Safety
+///
+/// `magnet` must be `NULL` or a valid magnet returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_to_string(magnet: *const MagnetHandle) -> *mut c_char {
+    match magnet.as_ref().and_then(|m| CString::new(m.magnet.to_string()).ok()) {
+        Some(string) => string.into_raw(),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Release a string returned by `magnet_to_string`. Passing `NULL` does nothing.
+///
+/// # Safety
+///
+/// `string` must be `NULL` or a string returned by `magnet_to_string` that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Create a new builder, which must be consumed by `magnet_builder_build` or released with
+/// `magnet_builder_free`
+#[no_mangle]
+pub extern "C" fn magnet_builder_new() -> *mut MagnetBuilderHandle {
+    Box::into_raw(Box::new(MagnetBuilderHandle(RustMagnetBuilder::new())))
+}
+
+/// Release a builder without building it. Passing `NULL` does nothing.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a builder returned by `magnet_builder_new` that was not freed
+/// or built yet.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_free(builder: *mut MagnetBuilderHandle) {
+    if !builder.is_null() {
+        drop(Box::from_raw(builder));
+    }
+}
+
+/// Build the magnet, consuming the builder
+///
+/// Returns `NULL` if `builder` is `NULL`. The result must be released with `magnet_free`.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a builder returned by `magnet_builder_new` that was not freed
+/// or built yet.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_build(builder: *mut MagnetBuilderHandle) -> *mut MagnetHandle {
+    if builder.is_null() {
+        return ptr::null_mut();
+    }
+    let builder = Box::from_raw(builder);
+    Box::into_raw(MagnetHandle::new(builder.0.build()))
+}
+
+/// Apply a builder method taking a string, returning `false` for `NULL` or invalid UTF-8 input
+unsafe fn set(
+    builder: *mut MagnetBuilderHandle,
+    value: *const c_char,
+    method: impl FnOnce(RustMagnetBuilder, &str) -> RustMagnetBuilder,
+) -> bool {
+    match (builder.as_mut(), to_str(value)) {
+        (Some(builder), Some(value)) => {
+            builder.0 = method(mem::take(&mut builder.0), value);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Set the display name of the torrent
+///
+/// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+/// not valid UTF-8.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_display_name(builder: *mut MagnetBuilderHandle, value: *const c_char) -> bool {
+    set(builder, value, RustMagnetBuilder::display_name)
+}
+
+/// Set the hash type used in the exact topic
+///
+/// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+/// not valid UTF-8.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_hash_type(builder: *mut MagnetBuilderHandle, value: *const c_char) -> bool {
+    set(builder, value, RustMagnetBuilder::hash_type)
+}
+
+/// Set the torrent hash
+///
+/// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+/// not valid UTF-8.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_hash(builder: *mut MagnetBuilderHandle, value: *const c_char) -> bool {
+    set(builder, value, RustMagnetBuilder::hash)
+}
+
+/// Set the download source for the file
+///
+/// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+/// not valid UTF-8.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_source(builder: *mut MagnetBuilderHandle, value: *const c_char) -> bool {
+    set(builder, value, RustMagnetBuilder::source)
+}
+
+/// Add a tracker URL
+///
+/// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+/// not valid UTF-8.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_add_tracker(builder: *mut MagnetBuilderHandle, value: *const c_char) -> bool {
+    set(builder, value, RustMagnetBuilder::add_tracker)
+}
+
+/// Set the search keywords
+///
+/// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+/// not valid UTF-8.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_search_keywords(builder: *mut MagnetBuilderHandle, value: *const c_char) -> bool {
+    set(builder, value, RustMagnetBuilder::search_keywords)
+}
+
+/// Set the web seed URL
+///
+/// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+/// not valid UTF-8.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_web_seed(builder: *mut MagnetBuilderHandle, value: *const c_char) -> bool {
+    set(builder, value, RustMagnetBuilder::web_seed)
+}
+
+/// Set the acceptable source
+///
+/// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+/// not valid UTF-8.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_acceptable_source(builder: *mut MagnetBuilderHandle, value: *const c_char) -> bool {
+    set(builder, value, RustMagnetBuilder::acceptable_source)
+}
+
+/// Set the manifest link
+///
+/// The value is copied. Returns `false` if `builder` or `value` is `NULL`, or if `value` is
+/// not valid UTF-8.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a valid builder, and `value` must be `NULL` or a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_manifest(builder: *mut MagnetBuilderHandle, value: *const c_char) -> bool {
+    set(builder, value, RustMagnetBuilder::manifest)
+}
+
+/// Set the size (in bytes) of the torrent
+///
+/// Returns `false` if `builder` is `NULL`.
+///
+/// # Safety
+///
+/// `builder` must be `NULL` or a valid builder returned by `magnet_builder_new`.
+#[no_mangle]
+pub unsafe extern "C" fn magnet_builder_length(builder: *mut MagnetBuilderHandle, length: u64) -> bool {
+    match builder.as_mut() {
+        Some(builder) => {
+            builder.0 = mem::take(&mut builder.0).length(length);
+            true
+        }
+        None => false,
+    }
+}
+
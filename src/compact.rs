@@ -0,0 +1,680 @@
+//! A compact binary encoding of magnets, for storing large numbers of them
+//!
+//! Hashes are stored as raw bytes, numbers as varints and URLs without their percent-encoding,
+//! which is restored on decoding. Trackers can also be replaced by their index in a
+//! [`TrackerDictionary`] shared by every encoded magnet. Decoding gives back exactly the
+//! magnet that was encoded: anything that can't be stored compactly without losing its exact
+//! spelling (like lowercase `%xx` escapes) is stored as it is.
+//!
+//! Every encoded magnet starts with the two bytes `mg` and a format version, currently 1.
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::fmt;
+
+use crate::encoding::{
+    base32_decode_vec, base32_encode, hex_decode_vec, hex_encode, percent_decode, percent_encode,
+    percent_encode_bytes, read_varint, write_varint, MAX_VARINT_LEN,
+};
+use crate::Magnet;
+
+const MAGIC: [u8; 2] = *b"mg";
+const VERSION: u8 = 1;
+
+/// Hash types stored as a one byte code (their index plus 2; 0 is no type, 1 a custom one)
+const HASH_TYPES: [&str; 8] = [
+    "btih",
+    "btmh",
+    "sha1",
+    "tree:tiger",
+    "ed2k",
+    "bitprint",
+    "md5",
+    "ipfs",
+];
+
+// Bits of the flags byte, for the fields that are present
+const DISPLAY_NAME: u8 = 1 << 0;
+const LENGTH: u8 = 1 << 1;
+const WEB_SEED: u8 = 1 << 2;
+const SOURCE: u8 = 1 << 3;
+const SEARCH_KEYWORDS: u8 = 1 << 4;
+const ACCEPTABLE_SOURCE: u8 = 1 << 5;
+const MANIFEST: u8 = 1 << 6;
+const EXACT_TOPIC: u8 = 1 << 7;
+
+/// How a hash is stored, in the byte before it
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HashForm {
+    None = 0,
+    Text = 1,
+    LowerHex = 2,
+    UpperHex = 3,
+    UpperBase32 = 4,
+    LowerBase32 = 5,
+}
+
+/// How a text field was percent-encoded, in the low 2 bits of its tag
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Escaping {
+    /// Stored as it is
+    Verbatim = 0,
+    /// Escaped like the builders do, keeping URL delimiters
+    Url = 1,
+    /// Every character but the unreserved ones escaped, like most clients do
+    Component = 2,
+}
+
+/// The ways decoding a compact magnet can fail
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum CompactError {
+    /// The data does not start with the compact magnet header
+    NotCompactMagnet,
+    /// The data was written by a newer version of the format
+    UnsupportedVersion(u8),
+    /// The data ends in the middle of a field, has bytes left after the magnet, or holds
+    /// invalid UTF-8
+    Malformed,
+    /// A tracker refers to an entry missing from the dictionary
+    UnknownTracker(u64),
+}
+
+impl fmt::Display for CompactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompactError::NotCompactMagnet => write!(f, "data is not a compact magnet"),
+            CompactError::UnsupportedVersion(version) => {
+                write!(f, "unsupported compact magnet version {}", version)
+            }
+            CompactError::Malformed => write!(f, "compact magnet is truncated or malformed"),
+            CompactError::UnknownTracker(index) => {
+                write!(f, "tracker {} is not in the dictionary", index)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompactError {}
+
+/// A list of tracker URLs shared by many compact magnets, which then store only their index
+///
+/// The same dictionary must be used to encode and decode; new trackers can be appended to it
+/// without breaking the magnets encoded before. Entries are percent-decoded URLs.
+///
+/// # Example
+///
+/// ```
+/// use magnet_url::{Magnet, TrackerDictionary};
+///
+/// let magnets = [
+///     Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337").unwrap(),
+///     Magnet::new("magnet:?xt=urn:btih:c9e15763f722f23e98a29decdfae341b98d53056&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337").unwrap(),
+/// ];
+/// let dictionary = TrackerDictionary::from_magnets(&magnets, 100);
+/// assert_eq!(dictionary.trackers(), ["udp://tracker.opentrackr.org:1337"]);
+///
+/// let bytes = magnets[0].to_bytes_with(&dictionary);
+/// assert_eq!(Magnet::from_bytes_with(&bytes, &dictionary), Ok(magnets[0].clone()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackerDictionary {
+    trackers: Vec<String>,
+    indices: BTreeMap<String, u64>,
+}
+
+impl TrackerDictionary {
+    /// Create an empty dictionary
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a dictionary of the `max_trackers` most common trackers of `magnets`, the most
+    /// common first (as smaller indices take fewer bytes)
+    pub fn from_magnets<'a>(
+        magnets: impl IntoIterator<Item = &'a Magnet>,
+        max_trackers: usize,
+    ) -> Self {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for magnet in magnets {
+            for tracker in magnet.trackers() {
+                *counts
+                    .entry(stored_text(tracker).0.into_owned())
+                    .or_default() += 1;
+            }
+        }
+        let mut counted: Vec<(String, usize)> = counts.into_iter().collect();
+        counted.sort_by_key(|&(_, count)| Reverse(count));
+
+        let mut dictionary = Self::new();
+        for (tracker, _) in counted.into_iter().take(max_trackers) {
+            dictionary.push(&tracker);
+        }
+        dictionary
+    }
+
+    /// Append a tracker (percent-encoded or not) and return its index, or the index it
+    /// already has
+    pub fn push(&mut self, tracker: &str) -> u64 {
+        let tracker = percent_decode(tracker).unwrap_or_else(|| tracker.to_string());
+        if let Some(&index) = self.indices.get(&tracker) {
+            return index;
+        }
+        let index = self.trackers.len() as u64;
+        self.trackers.push(tracker.clone());
+        self.indices.insert(tracker, index);
+        index
+    }
+
+    /// Get the tracker at `index`
+    pub fn get(&self, index: u64) -> Option<&str> {
+        self.trackers.get(index as usize).map(String::as_str)
+    }
+
+    /// Get every tracker, in index order
+    pub fn trackers(&self) -> &[String] {
+        &self.trackers
+    }
+
+    /// Get the number of trackers in the dictionary
+    pub fn len(&self) -> usize {
+        self.trackers.len()
+    }
+
+    /// Check whether the dictionary has no trackers
+    pub fn is_empty(&self) -> bool {
+        self.trackers.is_empty()
+    }
+}
+
+impl Magnet {
+    /// Encode the magnet in the compact binary format
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use magnet_url::Magnet;
+    ///
+    /// let magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel&tr=udp%3A%2F%2Fexplodie.org%3A6969").unwrap();
+    /// let bytes = magnet.to_bytes();
+    /// assert!(bytes.len() < magnet.to_string().len());
+    /// assert_eq!(Magnet::from_bytes(&bytes), Ok(magnet));
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_with(&TrackerDictionary::new())
+    }
+
+    /// Encode the magnet in the compact binary format, storing the trackers found in
+    /// `dictionary` as their index
+    pub fn to_bytes_with(&self, dictionary: &TrackerDictionary) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64);
+        out.extend_from_slice(&MAGIC);
+        out.push(VERSION);
+
+        let optional = [
+            (DISPLAY_NAME, self.display_name.is_some()),
+            (LENGTH, self.length.is_some()),
+            (WEB_SEED, self.web_seed.is_some()),
+            (SOURCE, self.source.is_some()),
+            (SEARCH_KEYWORDS, self.search_keywords.is_some()),
+            (ACCEPTABLE_SOURCE, self.acceptable_source.is_some()),
+            (MANIFEST, self.manifest.is_some()),
+            (EXACT_TOPIC, self.hash.is_some() || self.hash_type.is_some()),
+        ];
+        out.push(
+            optional
+                .iter()
+                .filter(|(_, present)| *present)
+                .fold(0, |flags, (bit, _)| flags | bit),
+        );
+
+        if self.hash.is_some() || self.hash_type.is_some() {
+            write_hash_type(&mut out, self.hash_type.as_deref());
+            write_hash(&mut out, self.hash.as_deref());
+        }
+        write_varint(&mut out, self.extra_topics.len() as u64);
+        for (hash_type, hash) in &self.extra_topics {
+            write_hash_type(&mut out, Some(hash_type));
+            write_hash(&mut out, Some(hash));
+        }
+
+        if let Some(name) = &self.display_name {
+            write_text(&mut out, name);
+        }
+        if let Some(length) = self.length {
+            write_varint(&mut out, length);
+        }
+
+        // Trackers are a dictionary index or a text, told apart by the lowest bit of the tag
+        write_varint(&mut out, self.trackers.len() as u64);
+        for tracker in &self.trackers {
+            let (stored, escaping) = stored_text(tracker);
+            match dictionary.indices.get(stored.as_ref()) {
+                Some(&index) => write_varint(&mut out, (index << 3) | (escaping as u64) << 1 | 1),
+                None => {
+                    write_varint(
+                        &mut out,
+                        (stored.len() as u64) << 3 | (escaping as u64) << 1,
+                    );
+                    out.extend_from_slice(stored.as_bytes());
+                }
+            }
+        }
+
+        write_varint(&mut out, self.peers.len() as u64);
+        for peer in &self.peers {
+            write_text(&mut out, peer);
+        }
+
+        let texts = [
+            &self.web_seed,
+            &self.source,
+            &self.search_keywords,
+            &self.acceptable_source,
+            &self.manifest,
+        ];
+        for text in texts.into_iter().flatten() {
+            write_text(&mut out, text);
+        }
+        out
+    }
+
+    /// Decode a magnet encoded with [`Magnet::to_bytes`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CompactError`] if the data is not a valid compact magnet, or if its trackers
+    /// were encoded with a dictionary (see [`Magnet::from_bytes_with`])
+    pub fn from_bytes(bytes: &[u8]) -> Result<Magnet, CompactError> {
+        Self::from_bytes_with(bytes, &TrackerDictionary::new())
+    }
+
+    /// Decode a magnet encoded with [`Magnet::to_bytes_with`] and the same `dictionary`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CompactError`] if the data is not a valid compact magnet or refers to trackers
+    /// missing from the dictionary
+    pub fn from_bytes_with(
+        bytes: &[u8],
+        dictionary: &TrackerDictionary,
+    ) -> Result<Magnet, CompactError> {
+        let rest = bytes
+            .strip_prefix(&MAGIC)
+            .ok_or(CompactError::NotCompactMagnet)?;
+        let (&version, rest) = rest.split_first().ok_or(CompactError::NotCompactMagnet)?;
+        if version != VERSION {
+            return Err(CompactError::UnsupportedVersion(version));
+        }
+        let mut reader = Reader(rest);
+        let flags = reader.byte()?;
+        let has = |bit: u8| flags & bit != 0;
+
+        let mut magnet = crate::MagnetBuilder::new().build();
+        if has(EXACT_TOPIC) {
+            magnet.hash_type = reader.hash_type()?;
+            magnet.hash = reader.hash()?;
+        }
+        for _ in 0..reader.count()? {
+            let hash_type = reader.hash_type()?.unwrap_or_default();
+            let hash = reader.hash()?.unwrap_or_default();
+            magnet.extra_topics.push((hash_type, hash));
+        }
+
+        if has(DISPLAY_NAME) {
+            magnet.display_name = Some(reader.text()?);
+        }
+        if has(LENGTH) {
+            magnet.length = Some(reader.varint()?);
+        }
+
+        for _ in 0..reader.count()? {
+            let tag = reader.varint()?;
+            let escaping = escaping((tag >> 1) & 3)?;
+            let stored = if tag & 1 == 1 {
+                let index = tag >> 3;
+                dictionary
+                    .get(index)
+                    .ok_or(CompactError::UnknownTracker(index))?
+            } else {
+                reader.str(tag >> 3)?
+            };
+            magnet.trackers.push(escape(stored, escaping));
+        }
+
+        for _ in 0..reader.count()? {
+            magnet.peers.push(reader.text()?);
+        }
+
+        let texts = [
+            (WEB_SEED, &mut magnet.web_seed),
+            (SOURCE, &mut magnet.source),
+            (SEARCH_KEYWORDS, &mut magnet.search_keywords),
+            (ACCEPTABLE_SOURCE, &mut magnet.acceptable_source),
+            (MANIFEST, &mut magnet.manifest),
+        ];
+        for (bit, field) in texts {
+            if has(bit) {
+                *field = Some(reader.text()?);
+            }
+        }
+
+        if !reader.0.is_empty() {
+            return Err(CompactError::Malformed);
+        }
+        Ok(magnet)
+    }
+}
+
+/// Get the form a text is stored in: percent-decoded when one of the escapings gives back the
+/// exact original, as it is otherwise
+fn stored_text(text: &str) -> (Cow<'_, str>, Escaping) {
+    if text.contains('%') {
+        if let Some(decoded) = percent_decode(text) {
+            // Most clients escape everything, so that is tried first
+            for escaping in [Escaping::Component, Escaping::Url] {
+                if escape(&decoded, escaping) == text {
+                    return (Cow::Owned(decoded), escaping);
+                }
+            }
+        }
+    }
+    (Cow::Borrowed(text), Escaping::Verbatim)
+}
+
+fn escape(text: &str, escaping: Escaping) -> String {
+    match escaping {
+        Escaping::Verbatim => text.to_string(),
+        Escaping::Url => percent_encode(text),
+        Escaping::Component => percent_encode_bytes(text.as_bytes()),
+    }
+}
+
+fn escaping(bits: u64) -> Result<Escaping, CompactError> {
+    match bits {
+        0 => Ok(Escaping::Verbatim),
+        1 => Ok(Escaping::Url),
+        2 => Ok(Escaping::Component),
+        _ => Err(CompactError::Malformed),
+    }
+}
+
+/// Write a text as a tag (its stored length and escaping) followed by its stored bytes
+fn write_text(out: &mut Vec<u8>, text: &str) {
+    let (stored, escaping) = stored_text(text);
+    write_varint(out, (stored.len() as u64) << 2 | escaping as u64);
+    out.extend_from_slice(stored.as_bytes());
+}
+
+fn write_hash_type(out: &mut Vec<u8>, hash_type: Option<&str>) {
+    match hash_type {
+        None => out.push(0),
+        Some(hash_type) => match HASH_TYPES.iter().position(|&known| known == hash_type) {
+            Some(index) => out.push(index as u8 + 2),
+            None => {
+                out.push(1);
+                write_text(out, hash_type);
+            }
+        },
+    }
+}
+
+/// Write a hash as raw bytes when its hex or base32 spelling can be restored exactly
+fn write_hash(out: &mut Vec<u8>, hash: Option<&str>) {
+    let Some(hash) = hash else {
+        out.push(HashForm::None as u8);
+        return;
+    };
+
+    let raw = if let Some(bytes) = hex_decode_vec(hash).filter(|bytes| !bytes.is_empty()) {
+        let lower = hex_encode(&bytes);
+        if lower == hash {
+            Some((HashForm::LowerHex, bytes))
+        } else if lower.to_ascii_uppercase() == hash {
+            Some((HashForm::UpperHex, bytes))
+        } else {
+            None
+        }
+    } else if let Some(bytes) = base32_decode_vec(hash).filter(|bytes| !bytes.is_empty()) {
+        let upper = base32_encode(&bytes);
+        if upper == hash {
+            Some((HashForm::UpperBase32, bytes))
+        } else if upper.to_ascii_lowercase() == hash {
+            Some((HashForm::LowerBase32, bytes))
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    match raw {
+        Some((form, bytes)) => {
+            out.push(form as u8);
+            write_varint(out, bytes.len() as u64);
+            out.extend_from_slice(&bytes);
+        }
+        None => {
+            out.push(HashForm::Text as u8);
+            write_text(out, hash);
+        }
+    }
+}
+
+/// The rest of the data being decoded
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, CompactError> {
+        let (&byte, rest) = self.0.split_first().ok_or(CompactError::Malformed)?;
+        self.0 = rest;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64, CompactError> {
+        read_varint(&mut self.0, MAX_VARINT_LEN).ok_or(CompactError::Malformed)
+    }
+
+    /// Read a number of items, which can't be more than the bytes left
+    fn count(&mut self) -> Result<u64, CompactError> {
+        let count = self.varint()?;
+        if count > self.0.len() as u64 {
+            return Err(CompactError::Malformed);
+        }
+        Ok(count)
+    }
+
+    fn bytes(&mut self, length: u64) -> Result<&'a [u8], CompactError> {
+        if length > self.0.len() as u64 {
+            return Err(CompactError::Malformed);
+        }
+        let (bytes, rest) = self.0.split_at(length as usize);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn str(&mut self, length: u64) -> Result<&'a str, CompactError> {
+        core::str::from_utf8(self.bytes(length)?).map_err(|_| CompactError::Malformed)
+    }
+
+    fn text(&mut self) -> Result<String, CompactError> {
+        let tag = self.varint()?;
+        let escaping = escaping(tag & 3)?;
+        let stored = self.str(tag >> 2)?;
+        Ok(escape(stored, escaping))
+    }
+
+    fn hash_type(&mut self) -> Result<Option<String>, CompactError> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => self.text().map(Some),
+            code => HASH_TYPES
+                .get(code as usize - 2)
+                .map(|hash_type| Some(hash_type.to_string()))
+                .ok_or(CompactError::Malformed),
+        }
+    }
+
+    fn hash(&mut self) -> Result<Option<String>, CompactError> {
+        let form = self.byte()?;
+        if form == HashForm::None as u8 {
+            return Ok(None);
+        }
+        if form == HashForm::Text as u8 {
+            return self.text().map(Some);
+        }
+
+        let length = self.varint()?;
+        let bytes = self.bytes(length)?;
+        let hash = match form {
+            f if f == HashForm::LowerHex as u8 => hex_encode(bytes),
+            f if f == HashForm::UpperHex as u8 => hex_encode(bytes).to_ascii_uppercase(),
+            f if f == HashForm::UpperBase32 as u8 => base32_encode(bytes),
+            f if f == HashForm::LowerBase32 as u8 => base32_encode(bytes).to_ascii_lowercase(),
+            _ => return Err(CompactError::Malformed),
+        };
+        Ok(Some(hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MagnetBuilder;
+    use alloc::format;
+    use alloc::vec;
+
+    const SINTEL: &str = "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel&tr=udp%3A%2F%2Fexplodie.org%3A6969&tr=udp%3A%2F%2Ftracker.coppersurfer.tk%3A6969&tr=udp%3A%2F%2Ftracker.empire-js.us%3A1337&tr=udp%3A%2F%2Ftracker.leechers-paradise.org%3A6969&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337&tr=wss%3A%2F%2Ftracker.btorrent.xyz&tr=wss%3A%2F%2Ftracker.fastcast.nz&tr=wss%3A%2F%2Ftracker.openwebtorrent.com&ws=https%3A%2F%2Fwebtorrent.io%2Ftorrents%2F&xs=https%3A%2F%2Fwebtorrent.io%2Ftorrents%2Fsintel.torrent";
+
+    fn round_trip(magnet: &Magnet) -> Vec<u8> {
+        let bytes = magnet.to_bytes();
+        assert_eq!(
+            Magnet::from_bytes(&bytes).as_ref(),
+            Ok(magnet),
+            "{}",
+            magnet
+        );
+        bytes
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let magnet = Magnet::new(SINTEL).unwrap();
+        let bytes = round_trip(&magnet);
+        assert_eq!(&bytes[..3], b"mg\x01");
+        assert!(bytes.len() < SINTEL.len() * 3 / 4, "{} bytes", bytes.len());
+
+        let links = [
+            "magnet:?xt=urn:btih:08ADA5A7A6183AAE1E09D831DF6748D566095A10&xl=129241752",
+            "magnet:?xt=urn:btih:BCW2LJ5GDA5K4HQJ3AY56Z2I2VTASWQQ&x.pe=10.0.0.1:6881",
+            "magnet:?xt=urn:btih:bcw2lj5gda5k4hqj3ay56z2i2vtaswqq&kt=cool+stuff",
+            "magnet:?xt=urn:btih:08ada5A7a6183aae1e09d831df6748d566095a10&tr=udp%3a%2f%2fexplodie.org%3a6969",
+            "magnet:?xt=urn:ed2k:31d6cfe0d16ae931b73c59d7e0c089c0&xt=urn:tree:tiger:LWPNACQDBZRYXW3VHJVCJ64QBZNGHOHHHZWCLNQ&xt=urn:x-custom:not-a-hash",
+            "magnet:?dn=Only%20a%20name&as=https://example.com/a%20b&mt=https://example.com/manifest",
+            "magnet:?xt=urn:btih:&dn=%E2%9C%93%20done&tr=udp://tracker.example.com:6969/announce",
+            "magnet:?",
+        ];
+        for link in links {
+            round_trip(&Magnet::new(link).unwrap());
+        }
+
+        // Fields only the builder can leave empty
+        round_trip(&MagnetBuilder::new().hash_type("btih").build());
+        round_trip(&MagnetBuilder::new().hash("abc").build());
+        round_trip(&MagnetBuilder::new().length(u64::MAX).build());
+    }
+
+    #[test]
+    fn raw_hash_test() {
+        let magnet =
+            Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10").unwrap();
+        let mut expected = vec![b'm', b'g', 1, EXACT_TOPIC, 2, HashForm::LowerHex as u8, 20];
+        expected.extend_from_slice(magnet.info_hash().unwrap().as_bytes());
+        expected.extend_from_slice(&[0, 0, 0]);
+        assert_eq!(magnet.to_bytes(), expected);
+    }
+
+    #[test]
+    fn stored_text_test() {
+        assert_eq!(
+            stored_text("udp%3A%2F%2Fexplodie.org%3A6969").0,
+            "udp://explodie.org:6969"
+        );
+        assert_eq!(
+            stored_text("https://example.com/a%20b").0,
+            "https://example.com/a b"
+        );
+        assert_eq!(stored_text("plain").0, "plain");
+        // Lowercase escapes can't be restored, so they are kept
+        assert_eq!(stored_text("a%3ab").0, "a%3ab");
+        assert_eq!(stored_text("100%").0, "100%");
+    }
+
+    #[test]
+    fn dictionary_test() {
+        let magnet = Magnet::new(SINTEL).unwrap();
+        let other = Magnet::new("magnet:?xt=urn:btih:c9e15763f722f23e98a29decdfae341b98d53056&tr=wss%3A%2F%2Ftracker.btorrent.xyz&tr=udp://tracker.example.com:1").unwrap();
+        let dictionary = TrackerDictionary::from_magnets([&magnet, &other], 100);
+        assert_eq!(dictionary.len(), 9);
+        assert_eq!(dictionary.get(0), Some("wss://tracker.btorrent.xyz"));
+
+        let bytes = magnet.to_bytes_with(&dictionary);
+        assert!(bytes.len() < magnet.to_bytes().len() / 2);
+        assert_eq!(Magnet::from_bytes_with(&bytes, &dictionary), Ok(magnet));
+        assert_eq!(
+            Magnet::from_bytes(&bytes),
+            Err(CompactError::UnknownTracker(1))
+        );
+
+        // Trackers missing from the dictionary are stored inline
+        let small = TrackerDictionary::from_magnets([&other], 1);
+        let bytes = other.to_bytes_with(&small);
+        assert_eq!(Magnet::from_bytes_with(&bytes, &small).as_ref(), Ok(&other));
+
+        let mut dictionary = TrackerDictionary::new();
+        assert_eq!(dictionary.push("udp%3A%2F%2Fa.example%3A1"), 0);
+        assert_eq!(dictionary.push("udp://b.example:1"), 1);
+        assert_eq!(dictionary.push("udp://a.example:1"), 0);
+        assert_eq!(
+            dictionary.trackers(),
+            ["udp://a.example:1", "udp://b.example:1"]
+        );
+    }
+
+    #[test]
+    fn errors_test() {
+        let bytes = Magnet::new(SINTEL).unwrap().to_bytes();
+        assert_eq!(Magnet::from_bytes(b""), Err(CompactError::NotCompactMagnet));
+        assert_eq!(
+            Magnet::from_bytes(SINTEL.as_bytes()),
+            Err(CompactError::NotCompactMagnet)
+        );
+        assert_eq!(
+            Magnet::from_bytes(b"mg\x02\x00"),
+            Err(CompactError::UnsupportedVersion(2))
+        );
+        for end in 3..bytes.len() {
+            assert_eq!(
+                Magnet::from_bytes(&bytes[..end]),
+                Err(CompactError::Malformed),
+                "{}",
+                end
+            );
+        }
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert_eq!(Magnet::from_bytes(&extra), Err(CompactError::Malformed));
+
+        assert_eq!(
+            Magnet::from_bytes(&[b'm', b'g', 1, EXACT_TOPIC, 99, 0, 0, 0, 0]),
+            Err(CompactError::Malformed)
+        );
+        assert_eq!(
+            format!("{}", CompactError::UnsupportedVersion(2)),
+            "unsupported compact magnet version 2"
+        );
+    }
+}
@@ -0,0 +1,811 @@
+//! Creating BitTorrent `.torrent` files (v1, v2 or hybrid), and their magnets, from files on disk
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::bencode::{self, Value};
+use crate::sha1::Sha1;
+use crate::sha256::Sha256;
+use crate::Magnet;
+
+/// Size of the blocks hashed into the leaves of v2 Merkle trees, and the smallest piece length
+const BLOCK_SIZE: u64 = 16 * 1024;
+/// Largest piece length picked automatically (16 MiB)
+const MAX_PIECE_LENGTH: u64 = 16 * 1024 * 1024;
+/// Number of pieces the automatic piece length aims to stay under
+const TARGET_PIECES: u64 = 1500;
+
+/// The ways creating a torrent can fail
+#[derive(Debug)]
+pub enum CreateError {
+    /// A file could not be listed or read
+    Io(io::Error),
+    /// The path is an empty directory (or holds only empty directories)
+    NoFiles,
+    /// A file or directory name is not valid UTF-8
+    InvalidFileName(PathBuf),
+    /// The piece length given is not a power of two of at least 16 KiB
+    InvalidPieceLength(u64),
+}
+
+impl fmt::Display for CreateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreateError::Io(err) => write!(f, "could not read the files: {}", err),
+            CreateError::NoFiles => write!(f, "no files to put in the torrent"),
+            CreateError::InvalidFileName(path) => {
+                write!(f, "file name is not valid UTF-8: {}", path.display())
+            }
+            CreateError::InvalidPieceLength(length) => write!(
+                f,
+                "piece length {} is not a power of two of at least 16 KiB",
+                length
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CreateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CreateError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CreateError {
+    fn from(err: io::Error) -> Self {
+        CreateError::Io(err)
+    }
+}
+
+/// The BitTorrent versions a created torrent is made for
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq)]
+pub enum TorrentVersion {
+    /// BEP 3: SHA-1 hashes of the pieces of the files laid end to end, for the `btih` info hash
+    #[default]
+    V1,
+    /// BEP 52: a SHA-256 Merkle tree of 16 KiB blocks per file, for the `btmh` info hash
+    V2,
+    /// Both, so v1 and v2 clients share one swarm; files are padded to piece boundaries with
+    /// padding files (BEP 47) so the v1 pieces line up with the v2 files
+    Hybrid,
+}
+
+/// A created torrent: the `.torrent` file and the matching magnet
+#[derive(Debug, Clone)]
+pub struct CreatedTorrent {
+    /// The bencoded `.torrent` file
+    pub torrent: Vec<u8>,
+    /// The magnet of the torrent, as [`Magnet::from_torrent`] gives for it
+    pub magnet: Magnet,
+}
+
+/// Builder for a BitTorrent torrent of a file or a directory
+///
+/// A directory becomes a multi-file torrent named after it, holding every file below it in path
+/// order; symbolic links to directories are not followed. Unless set, the piece length is the
+/// smallest power of two from 16 KiB to 16 MiB that keeps the torrent under 1500 pieces.
+///
+/// # Example
+///
+/// ```no_run
+/// use magnet_url::{TorrentCreator, TorrentVersion};
+///
+/// let created = TorrentCreator::new("dataset")
+///     .version(TorrentVersion::Hybrid)
+///     .add_tracker("udp://tracker.example.com:6969/announce")
+///     .add_web_seed("https://example.com/files/")
+///     .create()
+///     .unwrap();
+/// std::fs::write("dataset.torrent", &created.torrent).unwrap();
+/// println!("{}", created.magnet);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TorrentCreator {
+    path: PathBuf,
+    version: TorrentVersion,
+    piece_length: Option<u64>,
+    trackers: Vec<String>,
+    web_seeds: Vec<String>,
+    private: bool,
+    comment: Option<String>,
+}
+
+impl TorrentCreator {
+    /// Create a torrent of the file or directory at `path`
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            version: TorrentVersion::V1,
+            piece_length: None,
+            trackers: Vec::new(),
+            web_seeds: Vec::new(),
+            private: false,
+            comment: None,
+        }
+    }
+
+    /// Set the BitTorrent version of the torrent (v1 by default)
+    pub fn version(mut self, version: TorrentVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Set the piece length, a power of two of at least 16 KiB, instead of picking one
+    pub fn piece_length(mut self, piece_length: u64) -> Self {
+        self.piece_length = Some(piece_length);
+        self
+    }
+
+    /// Add a tracker; the first one is the `announce` URL, and each one gets its own tier in
+    /// `announce-list`
+    pub fn add_tracker(mut self, tracker: &str) -> Self {
+        self.trackers.push(tracker.to_string());
+        self
+    }
+
+    /// Add a web seed (BEP 19) to `url-list`
+    pub fn add_web_seed(mut self, web_seed: &str) -> Self {
+        self.web_seeds.push(web_seed.to_string());
+        self
+    }
+
+    /// Mark the torrent as private (BEP 27), so clients only get peers from its trackers
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// Set the free-form comment of the torrent
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+
+    /// Read the files, hash them and build the torrent and its magnet
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CreateError`] if the files cannot be read, there are none, a name is not
+    /// UTF-8 or the piece length is invalid
+    pub fn create(&self) -> Result<CreatedTorrent, CreateError> {
+        if let Some(length) = self.piece_length {
+            if length < BLOCK_SIZE || !length.is_power_of_two() {
+                return Err(CreateError::InvalidPieceLength(length));
+            }
+        }
+
+        // A path like `.` has no name of its own
+        let name = match self.path.file_name() {
+            Some(_) => utf8_name(&self.path)?,
+            None => utf8_name(&fs::canonicalize(&self.path)?)?,
+        };
+        let metadata = fs::metadata(&self.path)?;
+        let multi_file = metadata.is_dir();
+        let files = if multi_file {
+            let mut files = Vec::new();
+            walk(&self.path, &mut Vec::new(), &mut files)?;
+            if files.is_empty() {
+                return Err(CreateError::NoFiles);
+            }
+            files
+        } else {
+            vec![FileEntry {
+                path: self.path.clone(),
+                components: vec![name.clone()],
+                length: metadata.len(),
+            }]
+        };
+
+        let total: u64 = files.iter().map(|file| file.length).sum();
+        let piece_length = self
+            .piece_length
+            .unwrap_or_else(|| auto_piece_length(total));
+        let v1 = self.version != TorrentVersion::V2;
+        let v2 = self.version != TorrentVersion::V1;
+        let hashes = hash_files(&files, piece_length, self.version)?;
+
+        let mut info = vec![
+            (&b"name"[..], Value::Bytes(name.as_bytes())),
+            (b"piece length", Value::Int(piece_length as i64)),
+        ];
+        let padding_names: Vec<String> = files
+            .iter()
+            .map(|file| padding_length(file.length, piece_length).to_string())
+            .collect();
+        if v1 {
+            info.push((b"pieces", Value::Bytes(&hashes.pieces)));
+            if multi_file {
+                let mut list = Vec::new();
+                for (i, file) in files.iter().enumerate() {
+                    let path = file
+                        .components
+                        .iter()
+                        .map(|part| Value::Bytes(part.as_bytes()))
+                        .collect();
+                    list.push(Value::Dict(vec![
+                        (&b"length"[..], Value::Int(file.length as i64)),
+                        (b"path", Value::List(path)),
+                    ]));
+
+                    let padding = padding_length(file.length, piece_length);
+                    if v2 && padding > 0 && i + 1 < files.len() {
+                        list.push(Value::Dict(vec![
+                            (&b"attr"[..], Value::Bytes(b"p")),
+                            (b"length", Value::Int(padding as i64)),
+                            (
+                                b"path",
+                                Value::List(vec![
+                                    Value::Bytes(b".pad"),
+                                    Value::Bytes(padding_names[i].as_bytes()),
+                                ]),
+                            ),
+                        ]));
+                    }
+                }
+                info.push((b"files", Value::List(list)));
+            } else {
+                info.push((b"length", Value::Int(total as i64)));
+            }
+        }
+        if v2 {
+            info.push((b"meta version", Value::Int(2)));
+            info.push((b"file tree", file_tree(&files, &hashes.files, 0)));
+        }
+        if self.private {
+            info.push((b"private", Value::Int(1)));
+        }
+
+        let created_by = alloc::format!("magnet-url/{}", env!("CARGO_PKG_VERSION"));
+        let mut torrent = vec![
+            (&b"info"[..], Value::Dict(info)),
+            (b"created by", Value::Bytes(created_by.as_bytes())),
+        ];
+        if v2 {
+            let layers = hashes
+                .files
+                .iter()
+                .filter(|file| !file.piece_layer.is_empty())
+                .filter_map(|file| {
+                    let root = file.root.as_ref()?;
+                    Some((&root[..], Value::Bytes(&file.piece_layer)))
+                })
+                .collect();
+            torrent.push((b"piece layers", Value::Dict(layers)));
+        }
+        if let Some(first) = self.trackers.first() {
+            torrent.push((b"announce", Value::Bytes(first.as_bytes())));
+        }
+        if self.trackers.len() > 1 {
+            let tiers = self
+                .trackers
+                .iter()
+                .map(|tracker| Value::List(vec![Value::Bytes(tracker.as_bytes())]))
+                .collect();
+            torrent.push((b"announce-list", Value::List(tiers)));
+        }
+        if !self.web_seeds.is_empty() {
+            let urls = self
+                .web_seeds
+                .iter()
+                .map(|url| Value::Bytes(url.as_bytes()))
+                .collect();
+            torrent.push((b"url-list", Value::List(urls)));
+        }
+        if let Some(comment) = &self.comment {
+            torrent.push((b"comment", Value::Bytes(comment.as_bytes())));
+        }
+
+        let mut bytes = Vec::new();
+        bencode::encode(&Value::Dict(torrent), &mut bytes);
+        let magnet = Magnet::from_torrent(&bytes).expect("created torrents are valid");
+        Ok(CreatedTorrent {
+            torrent: bytes,
+            magnet,
+        })
+    }
+}
+
+struct FileEntry {
+    path: PathBuf,
+    /// The path of the file in the torrent: below the torrent's directory, or the torrent's name
+    /// for a single-file torrent
+    components: Vec<String>,
+    length: u64,
+}
+
+/// The v2 hashes of a file
+struct FileHashes {
+    /// The root of the file's Merkle tree, `None` for an empty file
+    root: Option<[u8; 32]>,
+    /// The hashes of the tree's layer with one node per piece, end to end, or empty if the file
+    /// fits in a single piece
+    piece_layer: Vec<u8>,
+}
+
+struct Hashes {
+    /// The v1 piece hashes, end to end
+    pieces: Vec<u8>,
+    /// The v2 hashes of each file
+    files: Vec<FileHashes>,
+}
+
+fn utf8_name(path: &Path) -> Result<String, CreateError> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(ToString::to_string)
+        .ok_or_else(|| CreateError::InvalidFileName(path.to_path_buf()))
+}
+
+/// Collect the files below `dir` in path order
+fn walk(
+    dir: &Path,
+    components: &mut Vec<String>,
+    files: &mut Vec<FileEntry>,
+) -> Result<(), CreateError> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        components.push(utf8_name(&path)?);
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(&path, components, files)?;
+        } else {
+            // Follow symbolic links to files, but not to directories
+            let metadata = fs::metadata(&path)?;
+            if metadata.is_file() {
+                files.push(FileEntry {
+                    path,
+                    components: components.clone(),
+                    length: metadata.len(),
+                });
+            }
+        }
+        components.pop();
+    }
+    Ok(())
+}
+
+fn auto_piece_length(total: u64) -> u64 {
+    total
+        .div_ceil(TARGET_PIECES)
+        .next_power_of_two()
+        .clamp(BLOCK_SIZE, MAX_PIECE_LENGTH)
+}
+
+/// Length of the padding file needed after a file to start the next one on a piece boundary
+fn padding_length(length: u64, piece_length: u64) -> u64 {
+    (piece_length - length % piece_length) % piece_length
+}
+
+/// SHA-1 hashes of fixed-size pieces of a stream of data
+struct PieceHasher {
+    piece_length: u64,
+    piece: Sha1,
+    piece_len: u64,
+    pieces: Vec<u8>,
+}
+
+impl PieceHasher {
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let take = (self.piece_length - self.piece_len).min(data.len() as u64) as usize;
+            self.piece.update(&data[..take]);
+            self.piece_len += take as u64;
+            data = &data[take..];
+
+            if self.piece_len == self.piece_length {
+                let piece = core::mem::replace(&mut self.piece, Sha1::new());
+                self.pieces.extend_from_slice(&piece.finish());
+                self.piece_len = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.piece_len > 0 {
+            self.pieces.extend_from_slice(&self.piece.finish());
+        }
+        self.pieces
+    }
+}
+
+/// Read every file once, computing the hashes `version` needs
+fn hash_files(
+    files: &[FileEntry],
+    piece_length: u64,
+    version: TorrentVersion,
+) -> Result<Hashes, CreateError> {
+    let v1 = version != TorrentVersion::V2;
+    let v2 = version != TorrentVersion::V1;
+    let mut pieces = PieceHasher {
+        piece_length,
+        piece: Sha1::new(),
+        piece_len: 0,
+        pieces: Vec::new(),
+    };
+    let mut file_hashes = Vec::new();
+    let mut block = vec![0u8; BLOCK_SIZE as usize];
+
+    for (i, entry) in files.iter().enumerate() {
+        let mut file = File::open(&entry.path)?;
+        let mut leaves = Vec::new();
+        let mut remaining = entry.length;
+        while remaining > 0 {
+            let len = remaining.min(BLOCK_SIZE) as usize;
+            file.read_exact(&mut block[..len])?;
+            if v1 {
+                pieces.update(&block[..len]);
+            }
+            if v2 {
+                leaves.push(Sha256::digest(&block[..len]));
+            }
+            remaining -= len as u64;
+        }
+
+        if v2 {
+            file_hashes.push(merkle_tree(leaves, (piece_length / BLOCK_SIZE) as usize));
+        }
+        if v1 && v2 && i + 1 < files.len() {
+            let zeros = [0u8; BLOCK_SIZE as usize];
+            let mut padding = padding_length(entry.length, piece_length);
+            while padding > 0 {
+                let len = padding.min(BLOCK_SIZE);
+                pieces.update(&zeros[..len as usize]);
+                padding -= len;
+            }
+        }
+    }
+
+    Ok(Hashes {
+        pieces: if v1 { pieces.finish() } else { Vec::new() },
+        files: file_hashes,
+    })
+}
+
+/// Build a file's Merkle tree from the hashes of its blocks
+///
+/// The leaves are padded with zero hashes up to a power of two. The piece layer is kept if the
+/// file spans more than one piece.
+fn merkle_tree(mut layer: Vec<[u8; 32]>, blocks_per_piece: usize) -> FileHashes {
+    if layer.is_empty() {
+        return FileHashes {
+            root: None,
+            piece_layer: Vec::new(),
+        };
+    }
+
+    let width = layer.len().next_power_of_two();
+    let mut span = 1;
+    let mut padding = [0u8; 32];
+    let mut piece_layer = Vec::new();
+    loop {
+        if span == blocks_per_piece && layer.len() > 1 {
+            piece_layer = layer.concat();
+        }
+        if span == width {
+            break;
+        }
+        if layer.len() % 2 == 1 {
+            layer.push(padding);
+        }
+        layer = layer
+            .chunks_exact(2)
+            .map(|pair| merkle_node(&pair[0], &pair[1]))
+            .collect();
+        padding = merkle_node(&padding, &padding);
+        span *= 2;
+    }
+    FileHashes {
+        root: Some(layer[0]),
+        piece_layer,
+    }
+}
+
+fn merkle_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut node = Sha256::new();
+    node.update(left);
+    node.update(right);
+    node.finish()
+}
+
+/// Build the v2 `file tree` of the files whose paths share their first `depth` components
+///
+/// Directories are dictionaries of their entries, and files are `{"": {"length": ..,
+/// "pieces root": ..}}`, with no root for empty files.
+fn file_tree<'a>(files: &'a [FileEntry], hashes: &'a [FileHashes], depth: usize) -> Value<'a> {
+    let mut entries = Vec::new();
+    let mut start = 0;
+    while start < files.len() {
+        let name = &files[start].components[depth];
+        let end = start
+            + files[start..]
+                .iter()
+                .take_while(|file| &file.components[depth] == name)
+                .count();
+
+        let value = if files[start].components.len() == depth + 1 {
+            let mut file = vec![(&b"length"[..], Value::Int(files[start].length as i64))];
+            if let Some(root) = &hashes[start].root {
+                file.push((b"pieces root", Value::Bytes(root)));
+            }
+            Value::Dict(vec![(&b""[..], Value::Dict(file))])
+        } else {
+            file_tree(&files[start..end], &hashes[start..end], depth + 1)
+        };
+        entries.push((name.as_bytes(), value));
+        start = end;
+    }
+    Value::Dict(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bencode::decode;
+    use crate::encoding::hex_encode;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn single_file_test() {
+        let dir = TempDir::new("create-single");
+        let path = dir.join("hello.txt");
+        fs::write(&path, b"hello").unwrap();
+
+        let created = TorrentCreator::new(&path)
+            .add_tracker("udp://tracker.example.com:6969")
+            .create()
+            .unwrap();
+        let torrent = decode(&created.torrent).unwrap();
+        let info = torrent.get("info").unwrap();
+        assert_eq!(info.get("name").and_then(Value::as_str), Some("hello.txt"));
+        assert_eq!(info.get("length").and_then(Value::as_int), Some(5));
+        assert_eq!(
+            info.get("piece length").and_then(Value::as_int),
+            Some(16384)
+        );
+        assert_eq!(
+            info.get("pieces").and_then(Value::as_bytes),
+            Some(&Sha1::digest(b"hello")[..])
+        );
+        assert_eq!(
+            torrent.get("announce").and_then(Value::as_str),
+            Some("udp://tracker.example.com:6969")
+        );
+        assert!(torrent.get("announce-list").is_none());
+
+        // The info dictionary is the canonical encoding of the expected one
+        let info_bytes = bencode::raw_dict_value(&created.torrent, "info").unwrap();
+        assert_eq!(
+            info_bytes,
+            &b"d6:lengthi5e4:name9:hello.txt12:piece lengthi16384e6:pieces20:\xaa\xf4\xc6\x1d\xdc\xc5\xe8\xa2\xda\xbe\xde\x0f\x3b\x48\x2c\xd9\xae\xa9\x43\x4de"[..]
+        );
+
+        let magnet = &created.magnet;
+        assert_eq!(magnet.hash_type(), Some("btih"));
+        assert_eq!(
+            magnet.hash(),
+            Some(hex_encode(&Sha1::digest(info_bytes)).as_str())
+        );
+        assert_eq!(magnet.display_name(), Some("hello.txt"));
+        assert_eq!(magnet.length(), Some(5));
+        assert_eq!(magnet.trackers(), ["udp://tracker.example.com:6969"]);
+    }
+
+    #[test]
+    fn directory_test() {
+        let dir = TempDir::new("create-dir");
+        let root = dir.join("data set");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        let a: Vec<u8> = (0..20_000u32).map(|i| i as u8).collect();
+        let b: Vec<u8> = (0..30_000u32).map(|i| (i * 3) as u8).collect();
+        fs::write(root.join("b.bin"), &b).unwrap();
+        fs::write(root.join("sub").join("a.bin"), &a).unwrap();
+        fs::write(root.join("zero"), b"").unwrap();
+
+        let created = TorrentCreator::new(&root)
+            .add_tracker("udp://one.example:6969")
+            .add_tracker("http://two.example/announce")
+            .add_web_seed("https://example.com/")
+            .private(true)
+            .comment("test data")
+            .create()
+            .unwrap();
+        let torrent = decode(&created.torrent).unwrap();
+        let info = torrent.get("info").unwrap();
+        assert_eq!(info.get("private").and_then(Value::as_int), Some(1));
+
+        let files = info.get("files").and_then(Value::as_list).unwrap();
+        let paths: Vec<Vec<&str>> = files
+            .iter()
+            .map(|file| {
+                let path = file.get("path").and_then(Value::as_list).unwrap();
+                path.iter().map(|part| part.as_str().unwrap()).collect()
+            })
+            .collect();
+        assert_eq!(paths, [vec!["b.bin"], vec!["sub", "a.bin"], vec!["zero"]]);
+
+        // Pieces span file boundaries
+        let mut data = b.clone();
+        data.extend_from_slice(&a);
+        let expected: Vec<u8> = data.chunks(16384).flat_map(Sha1::digest).collect();
+        assert_eq!(
+            info.get("pieces").and_then(Value::as_bytes),
+            Some(&expected[..])
+        );
+
+        let tiers = torrent
+            .get("announce-list")
+            .and_then(Value::as_list)
+            .unwrap();
+        assert_eq!(tiers.len(), 2);
+        assert_eq!(
+            torrent.get("comment").and_then(Value::as_str),
+            Some("test data")
+        );
+
+        let magnet = &created.magnet;
+        assert_eq!(magnet.display_name(), Some("data%20set"));
+        assert_eq!(magnet.length(), Some(50_000));
+        assert_eq!(
+            magnet.trackers(),
+            ["udp://one.example:6969", "http://two.example/announce"]
+        );
+        assert_eq!(magnet.web_seed(), Some("https://example.com/"));
+    }
+
+    /// A directory whose v2 and hybrid info hashes were computed by a separate implementation
+    fn v2_data(dir: &Path) -> PathBuf {
+        let root = dir.join("v2 data");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        let a: Vec<u8> = (0..40_000u32).map(|i| (i % 251) as u8).collect();
+        let b: Vec<u8> = (0..70_000u32).map(|i| (i * 7) as u8).collect();
+        fs::write(root.join("a.bin"), a).unwrap();
+        fs::write(root.join("e"), b"").unwrap();
+        fs::write(root.join("sub").join("b.bin"), b).unwrap();
+        root
+    }
+
+    #[test]
+    fn v2_test() {
+        let dir = TempDir::new("create-v2");
+        let root = v2_data(&dir);
+        let created = TorrentCreator::new(&root)
+            .version(TorrentVersion::V2)
+            .piece_length(32768)
+            .create()
+            .unwrap();
+
+        let magnet = &created.magnet;
+        assert_eq!(
+            magnet.exact_topics().collect::<Vec<_>>(),
+            [(
+                "btmh",
+                "1220b80fb7e9b91aaa633830e5f55551fd524f4c6ad2bd5b1d4b20a22938813667a7"
+            )]
+        );
+        assert_eq!(magnet.length(), Some(110_000));
+
+        let torrent = decode(&created.torrent).unwrap();
+        let info = torrent.get("info").unwrap();
+        assert!(info.get("pieces").is_none());
+        let tree = info.get("file tree").unwrap();
+        let empty = tree.get("e").and_then(|e| e.get("")).unwrap();
+        assert_eq!(empty.get("length").and_then(Value::as_int), Some(0));
+        assert!(empty.get("pieces root").is_none());
+
+        // Both non-empty files span more than one piece
+        let layers = torrent
+            .get("piece layers")
+            .and_then(Value::as_dict)
+            .unwrap();
+        let mut counts: Vec<usize> = layers
+            .iter()
+            .map(|(_, layer)| layer.as_bytes().unwrap().len() / 32)
+            .collect();
+        counts.sort();
+        assert_eq!(counts, [2, 3]);
+    }
+
+    #[test]
+    fn hybrid_test() {
+        let dir = TempDir::new("create-hybrid");
+        let root = v2_data(&dir);
+        let created = TorrentCreator::new(&root)
+            .version(TorrentVersion::Hybrid)
+            .piece_length(32768)
+            .create()
+            .unwrap();
+
+        let magnet = &created.magnet;
+        assert_eq!(
+            magnet.to_string(),
+            "magnet:?xt=urn:btih:f47f836fa6a3fdcf827c58c8eefe11819625a519\
+             &xt=urn:btmh:12202a4dd9ff01fe19ded89bd109a00185b54913e472b9800eefaa5b3596e2e31ef1\
+             &dn=v2%20data&xl=110000"
+        );
+
+        // Padding after `a.bin` only: `e` is empty and `sub/b.bin` is last
+        let torrent = decode(&created.torrent).unwrap();
+        let files = torrent
+            .get("info")
+            .and_then(|info| info.get("files"))
+            .and_then(Value::as_list)
+            .unwrap();
+        let lengths: Vec<i64> = files
+            .iter()
+            .map(|file| file.get("length").and_then(Value::as_int).unwrap())
+            .collect();
+        assert_eq!(lengths, [40_000, 25_536, 0, 70_000]);
+        assert_eq!(files[1].get("attr").and_then(Value::as_str), Some("p"));
+    }
+
+    #[test]
+    fn merkle_tree_test() {
+        let leaf = |n: u8| Sha256::digest(&[n]);
+        let single = merkle_tree(vec![leaf(0)], 4);
+        assert_eq!(single.root, Some(leaf(0)));
+        assert!(single.piece_layer.is_empty());
+
+        // Three blocks, two per piece: the fourth leaf is a zero hash
+        let tree = merkle_tree(vec![leaf(0), leaf(1), leaf(2)], 2);
+        let left = merkle_node(&leaf(0), &leaf(1));
+        let right = merkle_node(&leaf(2), &[0; 32]);
+        assert_eq!(tree.root, Some(merkle_node(&left, &right)));
+        assert_eq!(tree.piece_layer, [left, right].concat());
+
+        // Five blocks, one per piece: the padding grows with the height
+        let tree = merkle_tree((0..5).map(leaf).collect(), 1);
+        assert_eq!(tree.piece_layer.len(), 5 * 32);
+        let zero = merkle_node(&[0; 32], &[0; 32]);
+        let right = merkle_node(&merkle_node(&leaf(4), &[0; 32]), &zero);
+        let left = merkle_node(
+            &merkle_node(&leaf(0), &leaf(1)),
+            &merkle_node(&leaf(2), &leaf(3)),
+        );
+        assert_eq!(tree.root, Some(merkle_node(&left, &right)));
+
+        assert_eq!(merkle_tree(Vec::new(), 1).root, None);
+    }
+
+    #[test]
+    fn errors_test() {
+        let dir = TempDir::new("create-errors");
+        assert!(matches!(
+            TorrentCreator::new(&dir).create(),
+            Err(CreateError::NoFiles)
+        ));
+        assert!(matches!(
+            TorrentCreator::new(dir.join("missing")).create(),
+            Err(CreateError::Io(_))
+        ));
+        fs::write(dir.join("file"), b"x").unwrap();
+        assert!(matches!(
+            TorrentCreator::new(&dir).piece_length(20_000).create(),
+            Err(CreateError::InvalidPieceLength(20_000))
+        ));
+        assert!(matches!(
+            TorrentCreator::new(&dir).piece_length(8192).create(),
+            Err(CreateError::InvalidPieceLength(8192))
+        ));
+    }
+
+    #[test]
+    fn auto_piece_length_test() {
+        assert_eq!(auto_piece_length(0), BLOCK_SIZE);
+        assert_eq!(auto_piece_length(1500 * 16384), 16384);
+        assert_eq!(auto_piece_length(1500 * 16384 + 1), 32768);
+        assert_eq!(auto_piece_length(4 << 30), 4 << 20);
+        assert_eq!(auto_piece_length(u64::MAX / 2), MAX_PIECE_LENGTH);
+    }
+}
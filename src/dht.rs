@@ -0,0 +1,773 @@
+//! Mainline DHT (BEP 5) peer lookups, enabled by the `dht` feature
+//!
+//! [`Dht`] runs the iterative `get_peers` lookup of
+//! [BEP 5](https://www.bittorrent.org/beps/bep_0005.html) for an info hash: it repeatedly asks the
+//! closest nodes it knows for peers or for nodes even closer to the hash, until the closest nodes
+//! have all answered. The nodes that answered are kept in a Kademlia routing table for the next
+//! lookup, and the tokens they handed out can be used to announce ourselves as a peer.
+//!
+//! The node only makes queries and marks them read-only
+//! ([BEP 43](https://www.bittorrent.org/beps/bep_0043.html)), so it never has to answer any.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Instant;
+
+use crate::bencode::{self, Value};
+use crate::random::{random_bytes, random_u64};
+use crate::InfoHash;
+
+/// Bucket size, and how many of the closest nodes a lookup tracks
+const K: usize = 8;
+
+/// Number of queries a lookup keeps in flight
+const ALPHA: usize = 3;
+
+/// A 160-bit DHT node ID, in the same space as info hashes
+pub type NodeId = [u8; 20];
+
+/// A DHT node: its ID and the address it answers on
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Node {
+    /// The node's ID
+    pub id: NodeId,
+    /// The UDP address the node answers on
+    pub addr: SocketAddr,
+}
+
+/// A Kademlia routing table with up to 8 nodes per bucket
+///
+/// Bucket `i` holds the nodes whose ID shares exactly `i` leading bits with our own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutingTable {
+    id: NodeId,
+    buckets: Vec<Vec<Node>>,
+}
+
+impl RoutingTable {
+    /// Create an empty routing table for the node `id`
+    pub fn new(id: NodeId) -> Self {
+        Self {
+            id,
+            buckets: vec![Vec::new(); 160],
+        }
+    }
+
+    /// Add a node, or mark it as recently seen if it is already known
+    ///
+    /// Returns `false` if the node was not added because its bucket is full (or it is us).
+    pub fn insert(&mut self, node: Node) -> bool {
+        let Some(bucket) = self.bucket(&node.id) else {
+            return false;
+        };
+        let bucket = &mut self.buckets[bucket];
+        if let Some(i) = bucket.iter().position(|n| n.id == node.id) {
+            // Keep buckets ordered from least to most recently seen
+            bucket.remove(i);
+        } else if bucket.len() >= K {
+            return false;
+        }
+        bucket.push(node);
+        true
+    }
+
+    /// Remove a node, for example because it stopped answering
+    pub fn remove(&mut self, id: &NodeId) {
+        if let Some(bucket) = self.bucket(id) {
+            self.buckets[bucket].retain(|n| n.id != *id);
+        }
+    }
+
+    /// Get up to `count` known nodes, closest to `target` first
+    pub fn closest(&self, target: &NodeId, count: usize) -> Vec<Node> {
+        let mut nodes: Vec<Node> = self.buckets.iter().flatten().copied().collect();
+        nodes.sort_by_key(|n| distance(&n.id, target));
+        nodes.truncate(count);
+        nodes
+    }
+
+    /// Get the number of known nodes
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(Vec::len).sum()
+    }
+
+    /// Check whether no nodes are known
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn bucket(&self, id: &NodeId) -> Option<usize> {
+        let distance = distance(&self.id, id);
+        let first = distance.iter().position(|&b| b != 0)?;
+        Some(first * 8 + distance[first].leading_zeros() as usize)
+    }
+}
+
+/// The result of a `get_peers` lookup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lookup {
+    /// The info hash that was looked up
+    pub info_hash: InfoHash,
+    /// Every peer returned by any node
+    pub peers: Vec<SocketAddr>,
+    /// The closest nodes that answered, closest first, with the token each one gave us
+    pub closest: Vec<(Node, Vec<u8>)>,
+}
+
+/// A DHT node that looks up peers
+///
+/// # Example
+///
+/// ```no_run
+/// use magnet_url::dht::Dht;
+/// use magnet_url::Magnet;
+/// use std::net::ToSocketAddrs;
+///
+/// let magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10").unwrap();
+/// let bootstrap: Vec<_> = "router.bittorrent.com:6881".to_socket_addrs().unwrap().collect();
+///
+/// let mut dht = Dht::bind("0.0.0.0:0").unwrap().bootstrap_nodes(bootstrap);
+/// let lookup = dht.get_peers(&magnet.info_hash().unwrap()).unwrap();
+/// println!("{:?}", lookup.peers);
+/// ```
+#[derive(Debug)]
+pub struct Dht {
+    socket: UdpSocket,
+    table: RoutingTable,
+    bootstrap: Vec<SocketAddr>,
+    timeout: Duration,
+    transaction: u16,
+}
+
+/// A node being considered by a lookup
+struct Candidate {
+    addr: SocketAddr,
+    /// Unknown for bootstrap nodes until they answer
+    id: Option<NodeId>,
+    state: State,
+}
+
+enum State {
+    New,
+    Pending {
+        transaction: [u8; 2],
+        deadline: Instant,
+    },
+    Responded {
+        token: Option<Vec<u8>>,
+    },
+    Failed,
+}
+
+impl Dht {
+    /// Bind the node's UDP socket, with a random node ID
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr)?,
+            table: RoutingTable::new(random_bytes()),
+            bootstrap: Vec::new(),
+            timeout: Duration::from_secs(2),
+            transaction: random_u64() as u16,
+        })
+    }
+
+    /// Use a fixed node ID instead of a random one. This clears the routing table.
+    pub fn node_id(mut self, id: NodeId) -> Self {
+        self.table = RoutingTable::new(id);
+        self
+    }
+
+    /// Set how long to wait for a node's answer before skipping it
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the nodes a lookup starts from while the routing table has fewer than 8 nodes
+    pub fn bootstrap_nodes(mut self, nodes: impl IntoIterator<Item = SocketAddr>) -> Self {
+        self.bootstrap = nodes.into_iter().collect();
+        self
+    }
+
+    /// Get our node ID
+    pub fn id(&self) -> &NodeId {
+        &self.table.id
+    }
+
+    /// Get the routing table, filled with the nodes that answered previous lookups
+    pub fn routing_table(&self) -> &RoutingTable {
+        &self.table
+    }
+
+    /// Get the address the node's socket is bound to
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Look up the peers of an info hash
+    pub fn get_peers(&mut self, info_hash: &InfoHash) -> io::Result<Lookup> {
+        let target = *info_hash.as_bytes();
+        let ipv6 = self.socket.local_addr()?.is_ipv6();
+
+        let mut candidates: Vec<Candidate> = self
+            .table
+            .closest(&target, K)
+            .into_iter()
+            .map(|node| Candidate {
+                addr: node.addr,
+                id: Some(node.id),
+                state: State::New,
+            })
+            .collect();
+        if candidates.len() < K {
+            for &addr in &self.bootstrap {
+                if !candidates.iter().any(|c| c.addr == addr) {
+                    candidates.push(Candidate {
+                        addr,
+                        id: None,
+                        state: State::New,
+                    });
+                }
+            }
+        }
+        let mut peers = Vec::new();
+
+        loop {
+            // Bootstrap nodes (without an ID) come first, then the closest nodes
+            candidates.sort_by_key(|c| c.id.map(|id| distance(&id, &target)));
+
+            // Query the closest unqueried nodes among the K closest that have not failed
+            let mut in_flight = candidates
+                .iter()
+                .filter(|c| matches!(c.state, State::Pending { .. }))
+                .count();
+            for candidate in candidates
+                .iter_mut()
+                .filter(|c| !matches!(c.state, State::Failed))
+                .take(K)
+            {
+                if in_flight >= ALPHA {
+                    break;
+                }
+                if !matches!(candidate.state, State::New) {
+                    continue;
+                }
+
+                let transaction = self.next_transaction();
+                let args = vec![(&b"info_hash"[..], Value::Bytes(&target))];
+                let query = self.query(transaction, "get_peers", args);
+                candidate.state = match self.socket.send_to(&query, candidate.addr) {
+                    Ok(_) => {
+                        in_flight += 1;
+                        State::Pending {
+                            transaction,
+                            deadline: Instant::now() + self.timeout,
+                        }
+                    }
+                    Err(_) => State::Failed,
+                };
+            }
+            if in_flight == 0 {
+                break;
+            }
+
+            let deadline = candidates
+                .iter()
+                .filter_map(|c| match c.state {
+                    State::Pending { deadline, .. } => Some(deadline),
+                    _ => None,
+                })
+                .min()
+                .unwrap_or_else(Instant::now);
+            let Some((from, packet)) = self.receive(deadline)? else {
+                // Give up on the nodes that did not answer in time
+                let now = Instant::now();
+                for candidate in &mut candidates {
+                    if matches!(candidate.state, State::Pending { deadline, .. } if deadline <= now)
+                    {
+                        candidate.state = State::Failed;
+                        if let Some(id) = candidate.id {
+                            self.table.remove(&id);
+                        }
+                    }
+                }
+                continue;
+            };
+
+            let Ok(message) = bencode::decode(&packet) else {
+                continue;
+            };
+            let transaction = message.get("t").and_then(Value::as_bytes);
+            let Some(candidate) = candidates.iter_mut().find(|c| {
+                c.addr == from
+                    && matches!(&c.state, State::Pending { transaction: t, .. } if Some(&t[..]) == transaction)
+            }) else {
+                continue;
+            };
+
+            let response = message.get("r");
+            let id = response
+                .and_then(|r| r.get("id"))
+                .and_then(Value::as_bytes)
+                .and_then(|id| NodeId::try_from(id).ok());
+            let (Some(response), Some(id)) = (response, id) else {
+                // An error message, or a malformed answer
+                candidate.state = State::Failed;
+                continue;
+            };
+
+            candidate.id = Some(id);
+            candidate.state = State::Responded {
+                token: response
+                    .get("token")
+                    .and_then(Value::as_bytes)
+                    .map(<[u8]>::to_vec),
+            };
+            self.table.insert(Node { id, addr: from });
+
+            let values = response
+                .get("values")
+                .and_then(Value::as_list)
+                .unwrap_or(&[]);
+            for peer in values
+                .iter()
+                .filter_map(Value::as_bytes)
+                .filter_map(compact_addr)
+            {
+                if !peers.contains(&peer) {
+                    peers.push(peer);
+                }
+            }
+
+            let nodes = match ipv6 {
+                false => response.get("nodes"),
+                true => response.get("nodes6"),
+            };
+            let nodes = nodes.and_then(Value::as_bytes).unwrap_or(&[]);
+            for node in compact_nodes(nodes, ipv6) {
+                let known = candidates
+                    .iter()
+                    .any(|c| c.addr == node.addr || c.id == Some(node.id));
+                if node.id != self.table.id && !known {
+                    candidates.push(Candidate {
+                        addr: node.addr,
+                        id: Some(node.id),
+                        state: State::New,
+                    });
+                }
+            }
+        }
+
+        candidates.sort_by_key(|c| c.id.map(|id| distance(&id, &target)));
+        let closest = candidates
+            .into_iter()
+            .filter_map(|c| match (c.id, c.state) {
+                (Some(id), State::Responded { token: Some(token) }) => {
+                    Some((Node { id, addr: c.addr }, token))
+                }
+                _ => None,
+            })
+            .take(K)
+            .collect();
+
+        Ok(Lookup {
+            info_hash: *info_hash,
+            peers,
+            closest,
+        })
+    }
+
+    /// Announce that we are a peer for the looked up info hash, listening on `port`
+    ///
+    /// Every node in `lookup.closest` is told, using the token it gave us. Returns how many of
+    /// them acknowledged the announce.
+    pub fn announce_peer(&mut self, lookup: &Lookup, port: u16) -> io::Result<usize> {
+        let mut pending = Vec::new();
+        for (node, token) in &lookup.closest {
+            let transaction = self.next_transaction();
+            let args = vec![
+                (&b"implied_port"[..], Value::Int(0)),
+                (&b"info_hash"[..], Value::Bytes(lookup.info_hash.as_bytes())),
+                (&b"port"[..], Value::Int(i64::from(port))),
+                (&b"token"[..], Value::Bytes(token)),
+            ];
+            let query = self.query(transaction, "announce_peer", args);
+            if self.socket.send_to(&query, node.addr).is_ok() {
+                pending.push((node.addr, transaction));
+            }
+        }
+
+        let deadline = Instant::now() + self.timeout;
+        let mut acknowledged = 0;
+        while !pending.is_empty() {
+            let Some((from, packet)) = self.receive(deadline)? else {
+                break;
+            };
+            let Ok(message) = bencode::decode(&packet) else {
+                continue;
+            };
+            let transaction = message.get("t").and_then(Value::as_bytes);
+            if let Some(i) = pending
+                .iter()
+                .position(|(addr, t)| *addr == from && Some(&t[..]) == transaction)
+            {
+                pending.swap_remove(i);
+                if message.get("r").is_some() {
+                    acknowledged += 1;
+                }
+            }
+        }
+        Ok(acknowledged)
+    }
+
+    fn next_transaction(&mut self) -> [u8; 2] {
+        self.transaction = self.transaction.wrapping_add(1);
+        self.transaction.to_be_bytes()
+    }
+
+    /// Encode a KRPC query
+    fn query<'a>(
+        &'a self,
+        transaction: [u8; 2],
+        method: &str,
+        mut args: Vec<(&'a [u8], Value<'a>)>,
+    ) -> Vec<u8> {
+        args.push((b"id", Value::Bytes(&self.table.id)));
+        let message = Value::Dict(vec![
+            (b"a", Value::Dict(args)),
+            (b"q", Value::Bytes(method.as_bytes())),
+            (b"ro", Value::Int(1)),
+            (b"t", Value::Bytes(&transaction)),
+            (b"y", Value::Bytes(b"q")),
+        ]);
+        let mut packet = Vec::new();
+        bencode::encode(&message, &mut packet);
+        packet
+    }
+
+    /// Wait for a packet until `deadline`, returning `None` on timeout
+    fn receive(&self, deadline: Instant) -> io::Result<Option<(SocketAddr, Vec<u8>)>> {
+        let mut buffer = [0u8; 2048];
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+        self.socket.set_read_timeout(Some(remaining))?;
+        match self.socket.recv_from(&mut buffer) {
+            Ok((len, from)) => Ok(Some((from, buffer[..len].to_vec()))),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// XOR distance between two IDs, which orders like a big-endian number
+fn distance(a: &NodeId, b: &NodeId) -> NodeId {
+    let mut distance = [0u8; 20];
+    for (d, (a, b)) in distance.iter_mut().zip(a.iter().zip(b)) {
+        *d = a ^ b;
+    }
+    distance
+}
+
+/// Parse a compact peer address: 4 or 16 IP bytes followed by a 2-byte port
+fn compact_addr(bytes: &[u8]) -> Option<SocketAddr> {
+    let (ip, port) = bytes.split_at_checked(bytes.len().checked_sub(2)?)?;
+    let port = u16::from_be_bytes([port[0], port[1]]);
+    match ip.len() {
+        4 => Some(SocketAddr::from((<[u8; 4]>::try_from(ip).ok()?, port))),
+        16 => Some(SocketAddr::from((<[u8; 16]>::try_from(ip).ok()?, port))),
+        _ => None,
+    }
+}
+
+/// Parse compact node info: a 20-byte ID followed by a compact address each
+fn compact_nodes(bytes: &[u8], ipv6: bool) -> impl Iterator<Item = Node> + '_ {
+    let len = if ipv6 { 38 } else { 26 };
+    bytes.chunks_exact(len).filter_map(|node| {
+        let (id, addr) = node.split_at(20);
+        Some(Node {
+            id: id.try_into().ok()?,
+            addr: compact_addr(addr)?,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compact_addr, distance, Dht, Node, NodeId, RoutingTable};
+    use crate::bencode::{self, Value};
+    use crate::InfoHash;
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use core::time::Duration;
+    use std::net::{SocketAddr, UdpSocket};
+    use std::sync::Mutex;
+    use std::thread::{self, JoinHandle};
+
+    const TARGET: NodeId = [0xf1; 20];
+
+    /// A simulated DHT node on loopback
+    struct FakeNode {
+        id: NodeId,
+        socket: UdpSocket,
+        /// The nodes this node tells others about
+        knows: Vec<Node>,
+        /// Peers stored for `TARGET`
+        peers: Vec<SocketAddr>,
+        /// Ports announced to this node with a valid token
+        announced: Arc<Mutex<Vec<u16>>>,
+    }
+
+    impl FakeNode {
+        fn new(id: NodeId) -> Self {
+            Self {
+                id,
+                socket: UdpSocket::bind("127.0.0.1:0").unwrap(),
+                knows: Vec::new(),
+                peers: Vec::new(),
+                announced: Arc::default(),
+            }
+        }
+
+        fn node(&self) -> Node {
+            Node {
+                id: self.id,
+                addr: self.socket.local_addr().unwrap(),
+            }
+        }
+
+        fn run(self, stop: Arc<AtomicBool>) -> JoinHandle<()> {
+            self.socket
+                .set_read_timeout(Some(Duration::from_millis(20)))
+                .unwrap();
+            thread::spawn(move || {
+                let mut buffer = [0u8; 2048];
+                while !stop.load(Ordering::Relaxed) {
+                    let Ok((len, from)) = self.socket.recv_from(&mut buffer) else {
+                        continue;
+                    };
+                    let response = self.respond(&buffer[..len]);
+                    self.socket.send_to(&response, from).unwrap();
+                }
+            })
+        }
+
+        fn respond(&self, packet: &[u8]) -> Vec<u8> {
+            let query = bencode::decode(packet).unwrap();
+            let args = query.get("a").unwrap();
+            assert_eq!(query.get("y"), Some(&Value::Bytes(b"q")));
+            assert_eq!(query.get("ro"), Some(&Value::Int(1)));
+            assert_eq!(
+                args.get("id").and_then(Value::as_bytes).map(<[u8]>::len),
+                Some(20)
+            );
+
+            let token = [b't', self.id[0]];
+            let mut nodes = Vec::new();
+            let values: Vec<Vec<u8>>;
+            let mut response = vec![(&b"id"[..], Value::Bytes(&self.id))];
+            match query.get("q").and_then(Value::as_str) {
+                Some("get_peers") => {
+                    response.push((b"token", Value::Bytes(&token)));
+                    if self.peers.is_empty() {
+                        for node in &self.knows {
+                            nodes.extend_from_slice(&node.id);
+                            nodes.extend_from_slice(&compact(node.addr));
+                        }
+                        response.push((b"nodes", Value::Bytes(&nodes)));
+                    } else {
+                        values = self.peers.iter().map(|&peer| compact(peer)).collect();
+                        let values = values.iter().map(|v| Value::Bytes(v)).collect();
+                        response.push((b"values", Value::List(values)));
+                    }
+                }
+                Some("announce_peer") => {
+                    assert_eq!(
+                        args.get("token").and_then(Value::as_bytes),
+                        Some(&token[..])
+                    );
+                    let port = args.get("port").and_then(Value::as_int).unwrap();
+                    self.announced.lock().unwrap().push(port as u16);
+                }
+                _ => unreachable!(),
+            }
+
+            let message = Value::Dict(vec![
+                (b"r", Value::Dict(response)),
+                (b"t", query.get("t").unwrap().clone()),
+                (b"y", Value::Bytes(b"r")),
+            ]);
+            let mut packet = Vec::new();
+            bencode::encode(&message, &mut packet);
+            packet
+        }
+    }
+
+    fn compact(addr: SocketAddr) -> Vec<u8> {
+        let SocketAddr::V4(addr) = addr else {
+            unreachable!()
+        };
+        let mut bytes = addr.ip().octets().to_vec();
+        bytes.extend_from_slice(&addr.port().to_be_bytes());
+        bytes
+    }
+
+    /// A running simulated DHT, stopped when dropped
+    struct SimulatedDht {
+        nodes: Vec<Node>,
+        announced: Vec<Arc<Mutex<Vec<u16>>>>,
+        stop: Arc<AtomicBool>,
+        handles: Vec<JoinHandle<()>>,
+    }
+
+    impl Drop for SimulatedDht {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+            for handle in self.handles.drain(..) {
+                let _ = handle.join();
+            }
+        }
+    }
+
+    /// 16 nodes in a chain, each knowing the next two, which are closer to `TARGET`. The last
+    /// node stores two peers.
+    fn simulated_dht() -> SimulatedDht {
+        let mut nodes: Vec<FakeNode> = (0..16u8)
+            .map(|i| {
+                let mut id = [i; 20];
+                id[0] = i * 16;
+                FakeNode::new(id)
+            })
+            .collect();
+        let infos: Vec<Node> = nodes.iter().map(FakeNode::node).collect();
+        for (i, node) in nodes.iter_mut().enumerate() {
+            node.knows = infos.iter().skip(i + 1).take(2).copied().collect();
+        }
+        nodes[15].peers = vec![
+            "10.0.0.1:6881".parse().unwrap(),
+            "10.0.0.2:51413".parse().unwrap(),
+        ];
+
+        let announced = nodes.iter().map(|n| n.announced.clone()).collect();
+        let stop = Arc::new(AtomicBool::new(false));
+        let handles = nodes.into_iter().map(|n| n.run(stop.clone())).collect();
+        SimulatedDht {
+            nodes: infos,
+            announced,
+            stop,
+            handles,
+        }
+    }
+
+    #[test]
+    fn get_peers_test() {
+        let simulated = simulated_dht();
+        let (nodes, announced) = (&simulated.nodes, &simulated.announced);
+        let mut dht = Dht::bind("127.0.0.1:0")
+            .unwrap()
+            .timeout(Duration::from_secs(2))
+            .bootstrap_nodes([nodes[0].addr]);
+
+        let lookup = dht.get_peers(&InfoHash::new(TARGET)).unwrap();
+        assert_eq!(
+            lookup.peers,
+            [
+                "10.0.0.1:6881".parse().unwrap(),
+                "10.0.0.2:51413".parse().unwrap()
+            ]
+        );
+
+        // The whole chain was walked, and the closest nodes are the end of the chain
+        assert_eq!(dht.routing_table().len(), 16);
+        assert_eq!(lookup.closest.len(), 8);
+        assert_eq!(lookup.closest[0].0, nodes[15]);
+        assert_eq!(lookup.closest[0].1, [b't', 0xf0]);
+        assert_eq!(lookup.closest[7].0, nodes[8]);
+
+        assert_eq!(dht.announce_peer(&lookup, 6881).unwrap(), 8);
+        assert_eq!(*announced[15].lock().unwrap(), [6881]);
+        assert!(announced[7].lock().unwrap().is_empty());
+
+        // A second lookup starts from the routing table
+        let lookup = dht
+            .bootstrap_nodes([])
+            .get_peers(&InfoHash::new(TARGET))
+            .unwrap();
+        assert_eq!(lookup.peers.len(), 2);
+    }
+
+    #[test]
+    fn unresponsive_node_test() {
+        let simulated = simulated_dht();
+        let nodes = &simulated.nodes;
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut dht = Dht::bind("127.0.0.1:0")
+            .unwrap()
+            .timeout(Duration::from_millis(200))
+            .bootstrap_nodes([silent.local_addr().unwrap(), nodes[13].addr]);
+
+        let lookup = dht.get_peers(&InfoHash::new(TARGET)).unwrap();
+        assert_eq!(lookup.peers.len(), 2);
+        assert_eq!(lookup.closest.len(), 3);
+        assert_eq!(dht.routing_table().len(), 3);
+    }
+
+    #[test]
+    fn routing_table_test() {
+        let addr: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        let mut table = RoutingTable::new([0; 20]);
+        assert!(!table.insert(Node { id: [0; 20], addr }));
+
+        // All IDs starting with a 1 bit share the same bucket
+        for i in 0..8u8 {
+            assert!(table.insert(Node {
+                id: [0x80 | i; 20],
+                addr
+            }));
+        }
+        assert!(!table.insert(Node {
+            id: [0xff; 20],
+            addr
+        }));
+        assert!(table.insert(Node {
+            id: [0x80; 20],
+            addr
+        }));
+        assert!(table.insert(Node {
+            id: [0x01; 20],
+            addr
+        }));
+        assert_eq!(table.len(), 9);
+
+        let closest: Vec<NodeId> = table.closest(&[0x83; 20], 3).iter().map(|n| n.id).collect();
+        assert_eq!(closest, [[0x83; 20], [0x82; 20], [0x81; 20]]);
+
+        table.remove(&[0x83; 20]);
+        assert_eq!(table.len(), 8);
+        assert_eq!(table.closest(&[0x83; 20], 1)[0].id, [0x82; 20]);
+    }
+
+    #[test]
+    fn compact_test() {
+        assert_eq!(
+            compact_addr(&[10, 0, 0, 1, 0x1a, 0xe1]),
+            Some("10.0.0.1:6881".parse().unwrap())
+        );
+        assert_eq!(compact_addr(&[10, 0, 0, 1, 0x1a]), None);
+        assert_eq!(compact_addr(&[]), None);
+        assert_eq!(distance(&[0xf0; 20], &[0x0f; 20]), [0xff; 20]);
+    }
+}
@@ -0,0 +1,466 @@
+//! eDonkey2000 `ed2k://|file|...|/` links, and their conversion to and from magnets
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::encoding::{
+    base32_decode, base32_encode, hex_decode, hex_encode, percent_decode, percent_encode,
+};
+use crate::md4::Md4;
+use crate::{Magnet, MagnetBuilder};
+
+/// Size of the parts a file is split into for its ed2k hash
+const PART_SIZE: u64 = 9_728_000;
+
+/// The ways parsing an `ed2k://` link can fail
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum Ed2kError {
+    /// The provided string is not a valid `ed2k://|file|...` link
+    InvalidLink,
+}
+
+impl fmt::Display for Ed2kError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ed2kError::InvalidLink => write!(f, "provided link is not a valid ed2k file link"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Ed2kError {}
+
+/// A parsed `ed2k://|file|name|size|hash|/` link
+///
+/// Besides the name, size and MD4 based ed2k hash, a link may carry the AICH root hash
+/// (`h=`), the hashes of its 9.28 MB parts (`p=`), HTTP sources (`s=`) and eDonkey sources
+/// (`/|sources,ip:port|/`). All of them are kept when the link is formatted again.
+///
+/// # Example
+///
+/// ```
+/// use magnet_url::{Ed2kLink, Magnet};
+///
+/// let link = Ed2kLink::parse("ed2k://|file|mediawiki-1.15.1.tar.gz|10826029|354B15E68FB8F36D7CD88FF94116CDC1|h=W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW|/").unwrap();
+/// assert_eq!(link.name, "mediawiki-1.15.1.tar.gz");
+///
+/// let magnet = link.to_magnet();
+/// assert_eq!(magnet.to_string(), "magnet:?xt=urn:ed2k:354b15e68fb8f36d7cd88ff94116cdc1&xt=urn:aich:W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW&dn=mediawiki-1.15.1.tar.gz&xl=10826029");
+/// assert_eq!(Ed2kLink::from_magnet(&magnet), Some(link));
+/// ```
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Ed2kLink {
+    /// The file name, percent-decoded
+    pub name: String,
+    /// The file size in bytes
+    pub size: u64,
+    /// The ed2k hash: the MD4 of the MD4 hashes of the file's parts
+    pub hash: [u8; 16],
+    /// The root of the AICH tree (a SHA-1 hash), from `h=`
+    pub aich: Option<[u8; 20]>,
+    /// The MD4 hash of each part, from `p=`
+    pub part_hashes: Vec<[u8; 16]>,
+    /// URLs the whole file can be downloaded from, from `s=`
+    pub http_sources: Vec<String>,
+    /// Addresses (`ip:port`) of eDonkey peers sharing the file, from the `sources` section
+    pub sources: Vec<String>,
+}
+
+impl Ed2kLink {
+    /// Parse an `ed2k://|file|...|/` link
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(Ed2kError::InvalidLink)` if the link is not a file link, or its size or
+    /// hashes are invalid
+    pub fn parse(link: &str) -> Result<Self, Ed2kError> {
+        parse(link.trim()).ok_or(Ed2kError::InvalidLink)
+    }
+
+    /// Convert the link to a magnet
+    ///
+    /// The name becomes `dn`, the size `xl`, the ed2k hash `xt=urn:ed2k` and the AICH root a
+    /// second `xt=urn:aich`. The first HTTP source is kept as the acceptable source (`as`), the
+    /// second as the exact source (`xs`) and the eDonkey sources as peers (`x.pe`). Part hashes
+    /// and further HTTP sources have no place in a magnet and are dropped.
+    pub fn to_magnet(&self) -> Magnet {
+        let mut builder = MagnetBuilder::new().add_exact_topic("ed2k", &hex_encode(&self.hash));
+        if let Some(aich) = &self.aich {
+            builder = builder.add_exact_topic("aich", &base32_encode(aich));
+        }
+        builder = builder
+            .display_name(&percent_encode(&self.name))
+            .length(self.size);
+        if let Some(source) = self.http_sources.first() {
+            builder = builder.acceptable_source(&percent_encode(source));
+        }
+        if let Some(source) = self.http_sources.get(1) {
+            builder = builder.source(&percent_encode(source));
+        }
+        for source in &self.sources {
+            builder = builder.add_peer(source);
+        }
+        builder.build()
+    }
+
+    /// Convert a magnet with an `urn:ed2k` (or `urn:ed2khash`) exact topic, a display name and a
+    /// length back to a link, or `None` if one of those is missing
+    ///
+    /// The acceptable source (`as`) and an `http://` or `https://` exact source (`xs`) become HTTP
+    /// sources, and the peers (`x.pe`) eDonkey sources.
+    pub fn from_magnet(magnet: &Magnet) -> Option<Self> {
+        let hash = magnet
+            .exact_topic("ed2k")
+            .or_else(|| magnet.exact_topic("ed2khash"))?;
+        let aich = match magnet.exact_topic("aich") {
+            Some(aich) => Some(base32_decode(aich)?),
+            None => None,
+        };
+        let decode = |value: &str| percent_decode(value).unwrap_or_else(|| value.to_string());
+
+        Some(Self {
+            name: decode(magnet.display_name()?),
+            size: magnet.length()?,
+            hash: hex_decode(hash)?,
+            aich,
+            part_hashes: Vec::new(),
+            http_sources: magnet
+                .acceptable_source()
+                .map(decode)
+                .into_iter()
+                .chain(magnet.source().map(decode).filter(|source| is_http(source)))
+                .collect(),
+            sources: magnet.peers().iter().map(|peer| decode(peer)).collect(),
+        })
+    }
+}
+
+impl fmt::Display for Ed2kLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ed2k://|file|{}|{}|{}|",
+            percent_encode(&self.name),
+            self.size,
+            hex_encode(&self.hash).to_ascii_uppercase()
+        )?;
+        if let Some(aich) = &self.aich {
+            write!(f, "h={}|", base32_encode(aich))?;
+        }
+        if !self.part_hashes.is_empty() {
+            let parts: Vec<String> = self
+                .part_hashes
+                .iter()
+                .map(|part| hex_encode(part).to_ascii_uppercase())
+                .collect();
+            write!(f, "p={}|", parts.join(":"))?;
+        }
+        for source in &self.http_sources {
+            write!(f, "s={}|", source)?;
+        }
+        f.write_str("/")?;
+        if !self.sources.is_empty() {
+            write!(f, "|sources,{}|/", self.sources.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+impl Magnet {
+    /// Create a magnet from an `ed2k://|file|...|/` link (see [`Ed2kLink::to_magnet`])
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(Ed2kError::InvalidLink)` if the link is not a valid file link
+    pub fn from_ed2k(link: &str) -> Result<Magnet, Ed2kError> {
+        Ed2kLink::parse(link).map(|link| link.to_magnet())
+    }
+}
+
+/// Streaming ed2k hash computation
+///
+/// The file is split into 9,728,000-byte parts: a file of a single part hashes to the MD4 of
+/// its content, a longer one to the MD4 of the concatenated MD4 hashes of its parts. As in eMule,
+/// a file of an exact multiple of the part size ends with an empty part, whose hash is included.
+///
+/// # Example
+///
+/// ```
+/// use magnet_url::Ed2kHasher;
+///
+/// let mut hasher = Ed2kHasher::new();
+/// hasher.update(b"a");
+/// assert_eq!(hasher.finish(), Ed2kHasher::digest(b"a"));
+/// ```
+#[derive(Clone)]
+pub struct Ed2kHasher {
+    part: Md4,
+    part_len: u64,
+    part_hashes: Vec<[u8; 16]>,
+}
+
+impl Ed2kHasher {
+    /// Create a new hasher
+    pub fn new() -> Self {
+        Self {
+            part: Md4::new(),
+            part_len: 0,
+            part_hashes: Vec::new(),
+        }
+    }
+
+    /// Compute the ed2k hash of a complete input in one go
+    pub fn digest(data: &[u8]) -> [u8; 16] {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finish()
+    }
+
+    /// Feed more data into the hasher
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let take = (PART_SIZE - self.part_len).min(data.len() as u64) as usize;
+            self.part.update(&data[..take]);
+            self.part_len += take as u64;
+            data = &data[take..];
+
+            if self.part_len == PART_SIZE {
+                let part = core::mem::replace(&mut self.part, Md4::new());
+                self.part_hashes.push(part.finish());
+                self.part_len = 0;
+            }
+        }
+    }
+
+    /// Finish hashing and return the ed2k hash
+    pub fn finish(self) -> [u8; 16] {
+        if self.part_hashes.is_empty() {
+            return self.part.finish();
+        }
+
+        // The last part is empty for exact multiples of the part size
+        let mut root = Md4::new();
+        for part in &self.part_hashes {
+            root.update(part);
+        }
+        root.update(&self.part.finish());
+        root.finish()
+    }
+}
+
+impl Default for Ed2kHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for Ed2kHasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn is_http(url: &str) -> bool {
+    url.split_once("://").is_some_and(|(scheme, _)| {
+        scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+    })
+}
+
+fn parse(link: &str) -> Option<Ed2kLink> {
+    let scheme = link.get(..7)?;
+    if !scheme.eq_ignore_ascii_case("ed2k://") {
+        return None;
+    }
+    let mut fields = link[7..].split('|');
+    if !fields.next()?.is_empty() || !fields.next()?.eq_ignore_ascii_case("file") {
+        return None;
+    }
+
+    let name = fields.next().filter(|name| !name.is_empty())?;
+    let name = percent_decode(name).unwrap_or_else(|| name.to_string());
+    let size = fields.next()?.parse().ok().filter(|&size| size > 0)?;
+    let hash = hex_decode(fields.next()?)?;
+    let mut link = Ed2kLink {
+        name,
+        size,
+        hash,
+        aich: None,
+        part_hashes: Vec::new(),
+        http_sources: Vec::new(),
+        sources: Vec::new(),
+    };
+
+    // Optional fields, until the `/` closing the file section
+    loop {
+        let field = fields.next()?;
+        if field == "/" {
+            break;
+        } else if let Some(aich) = field.strip_prefix("h=") {
+            link.aich = Some(base32_decode(aich)?);
+        } else if let Some(parts) = field.strip_prefix("p=") {
+            link.part_hashes = parts.split(':').map(hex_decode).collect::<Option<_>>()?;
+        } else if let Some(source) = field.strip_prefix("s=") {
+            link.http_sources.push(source.to_string());
+        }
+        // Other fields are from newer clients, and skipped
+    }
+
+    // An optional `|sources,ip:port,...|/` section
+    match fields.next() {
+        None | Some("") => {}
+        Some(sources) => {
+            let sources = sources.strip_prefix("sources,")?;
+            link.sources = sources
+                .split(',')
+                .filter(|source| !source.is_empty())
+                .map(ToString::to_string)
+                .collect();
+            if fields.next()? != "/" {
+                return None;
+            }
+        }
+    }
+    match fields.next() {
+        None | Some("") => Some(link),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const LINK: &str = "ed2k://|file|The%20Two%20Towers.avi|14997504|965C013E991EE246D63D45EA71954C4D|h=H52BRVWPBBTAED5NXQDH7RRFQGPNPDHC|p=264E6F6B587985D4E9CDD1E4E7A4CB11:B7F2B26A7BF15CC5FBB9E7A8C3CD9C2F|s=http://example.org/towers.avi|/|sources,10.0.0.1:4662,10.0.0.2:4662|/";
+
+    #[test]
+    fn parse_test() {
+        let link = Ed2kLink::parse(LINK).unwrap();
+        assert_eq!(link.name, "The Two Towers.avi");
+        assert_eq!(link.size, 14997504);
+        assert_eq!(hex_encode(&link.hash), "965c013e991ee246d63d45ea71954c4d");
+        assert_eq!(
+            base32_encode(&link.aich.unwrap()),
+            "H52BRVWPBBTAED5NXQDH7RRFQGPNPDHC"
+        );
+        assert_eq!(link.part_hashes.len(), 2);
+        assert_eq!(link.http_sources, vec!["http://example.org/towers.avi"]);
+        assert_eq!(link.sources, vec!["10.0.0.1:4662", "10.0.0.2:4662"]);
+        assert_eq!(link.to_string(), LINK);
+
+        let minimal = "ed2k://|file|a.txt|3|965C013E991EE246D63D45EA71954C4D|/";
+        let link = Ed2kLink::parse(minimal).unwrap();
+        assert_eq!(link.aich, None);
+        assert!(link.sources.is_empty());
+        assert_eq!(link.to_string(), minimal);
+    }
+
+    #[test]
+    fn invalid_test() {
+        for link in [
+            "",
+            "magnet:?xt=urn:ed2k:965c013e991ee246d63d45ea71954c4d",
+            "ed2k://|server|10.0.0.1|4661|/",
+            "ed2k://|file||3|965C013E991EE246D63D45EA71954C4D|/",
+            "ed2k://|file|a.txt|big|965C013E991EE246D63D45EA71954C4D|/",
+            "ed2k://|file|a.txt|3|965C013E|/",
+            "ed2k://|file|a.txt|3|965C013E991EE246D63D45EA71954C4D|",
+            "ed2k://|file|a.txt|3|965C013E991EE246D63D45EA71954C4D|h=short|/",
+            "ed2k://|file|a.txt|3|965C013E991EE246D63D45EA71954C4D|/|peers,1.2.3.4:1|/",
+        ] {
+            assert_eq!(
+                Ed2kLink::parse(link),
+                Err(Ed2kError::InvalidLink),
+                "{}",
+                link
+            );
+        }
+    }
+
+    #[test]
+    fn hasher_test() {
+        let empty = Md4::digest(b"");
+        assert_eq!(Ed2kHasher::digest(b""), empty);
+        assert_eq!(Ed2kHasher::digest(b"abc"), Md4::digest(b"abc"));
+
+        let part = PART_SIZE as usize;
+        let data: Vec<u8> = (0..part + 1).map(|i| (i % 251) as u8).collect();
+        let first = Md4::digest(&data[..part]);
+
+        // An exact multiple of the part size ends with the hash of an empty part
+        let mut parts = Vec::new();
+        parts.extend_from_slice(&first);
+        parts.extend_from_slice(&empty);
+        assert_eq!(Ed2kHasher::digest(&data[..part]), Md4::digest(&parts));
+
+        let mut parts = Vec::new();
+        parts.extend_from_slice(&first);
+        parts.extend_from_slice(&Md4::digest(&data[part..]));
+        let mut hasher = Ed2kHasher::new();
+        for chunk in data.chunks(65536) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finish(), Md4::digest(&parts));
+    }
+
+    #[test]
+    fn magnet_test() {
+        let magnet = Magnet::from_ed2k(LINK).unwrap();
+        assert_eq!(
+            magnet.exact_topic("ed2k"),
+            Some("965c013e991ee246d63d45ea71954c4d")
+        );
+        assert_eq!(
+            magnet.exact_topic("aich"),
+            Some("H52BRVWPBBTAED5NXQDH7RRFQGPNPDHC")
+        );
+        assert_eq!(magnet.display_name(), Some("The%20Two%20Towers.avi"));
+        assert_eq!(magnet.length(), Some(14997504));
+        assert_eq!(magnet.peers().len(), 2);
+
+        let link = Ed2kLink::from_magnet(&magnet).unwrap();
+        let mut expected = Ed2kLink::parse(LINK).unwrap();
+        expected.part_hashes.clear();
+        assert_eq!(link, expected);
+
+        let magnet =
+            Magnet::new("magnet:?xt=urn:ed2khash:965c013e991ee246d63d45ea71954c4d&dn=a.txt&xl=3")
+                .unwrap();
+        assert_eq!(
+            Ed2kLink::from_magnet(&magnet).unwrap().to_string(),
+            "ed2k://|file|a.txt|3|965C013E991EE246D63D45EA71954C4D|/"
+        );
+        let magnet =
+            Magnet::new("magnet:?xt=urn:ed2k:965c013e991ee246d63d45ea71954c4d&dn=a.txt").unwrap();
+        assert_eq!(Ed2kLink::from_magnet(&magnet), None);
+    }
+
+    #[test]
+    fn http_sources_test() {
+        let link = "ed2k://|file|a.txt|3|965C013E991EE246D63D45EA71954C4D|s=http://a.example/a.txt|s=https://b.example/a.txt|s=http://c.example/a.txt|/";
+        let magnet = Magnet::from_ed2k(link).unwrap();
+        assert_eq!(magnet.acceptable_source(), Some("http://a.example/a.txt"));
+        assert_eq!(magnet.source(), Some("https://b.example/a.txt"));
+        assert_eq!(
+            Ed2kLink::from_magnet(&magnet).unwrap().http_sources,
+            vec!["http://a.example/a.txt", "https://b.example/a.txt"]
+        );
+
+        // Exact sources that are not HTTP URLs are not ed2k sources
+        let magnet = Magnet::new(
+            "magnet:?xt=urn:ed2k:965c013e991ee246d63d45ea71954c4d&dn=a.txt&xl=3&xs=urn:btpk:abc",
+        )
+        .unwrap();
+        assert!(Ed2kLink::from_magnet(&magnet)
+            .unwrap()
+            .http_sources
+            .is_empty());
+    }
+}
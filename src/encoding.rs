@@ -0,0 +1,399 @@
+//! Small text encodings shared by the rest of the crate (hex, base32, base-x and
+//! percent-encoding)
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Encode bytes as lowercase hexadecimal
+pub(crate) fn hex_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        out.push(HEX_DIGITS[(b >> 4) as usize] as char);
+        out.push(HEX_DIGITS[(b & 0x0f) as usize] as char);
+    }
+    out
+}
+
+/// Decode hexadecimal text (in either case) into exactly `N` bytes
+pub(crate) fn hex_decode<const N: usize>(text: &str) -> Option<[u8; N]> {
+    let text = text.as_bytes();
+    if text.len() != N * 2 {
+        return None;
+    }
+
+    let mut out = [0u8; N];
+    for (byte, pair) in out.iter_mut().zip(text.chunks_exact(2)) {
+        let high = (pair[0] as char).to_digit(16)?;
+        let low = (pair[1] as char).to_digit(16)?;
+        *byte = (high << 4 | low) as u8;
+    }
+    Some(out)
+}
+
+/// Decode hexadecimal text (in either case) of any even length
+pub(crate) fn hex_decode_vec(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    text.chunks_exact(2)
+        .map(|pair| {
+            let high = (pair[0] as char).to_digit(16)?;
+            let low = (pair[1] as char).to_digit(16)?;
+            Some((high << 4 | low) as u8)
+        })
+        .collect()
+}
+
+/// Encode bytes as unpadded uppercase RFC 4648 base32
+pub(crate) fn base32_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut out = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &b in bytes {
+        buffer = buffer << 8 | u32::from(b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+    out
+}
+
+/// Decode unpadded RFC 4648 base32 text (in either case) into exactly `N` bytes
+pub(crate) fn base32_decode<const N: usize>(text: &str) -> Option<[u8; N]> {
+    if text.len() != (N * 8).div_ceil(5) {
+        return None;
+    }
+    base32_decode_vec(text)?.try_into().ok()
+}
+
+/// Decode unpadded RFC 4648 base32 text (in either case) of any length
+pub(crate) fn base32_decode_vec(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 5 / 8);
+    let mut buffer = 0u64;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match c.to_ascii_uppercase() {
+            c @ b'A'..=b'Z' => c - b'A',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = buffer << 5 | u64::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+
+    // Leftover bits must be zero padding, shorter than a character
+    if bits >= 5 || buffer & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+/// Encode bytes in the base of `alphabet` (like base58 or base36), as a big-endian number with
+/// each leading zero byte written as the alphabet's first character
+pub(crate) fn base_x_encode(bytes: &[u8], alphabet: &[u8]) -> String {
+    let base = alphabet.len() as u32;
+    let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+
+    // Little-endian digits of the number, by repeated long division
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 2);
+    for &byte in &bytes[zeros..] {
+        let mut carry = u32::from(byte);
+        for digit in &mut digits {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % base) as u8;
+            carry /= base;
+        }
+        while carry > 0 {
+            digits.push((carry % base) as u8);
+            carry /= base;
+        }
+    }
+
+    let mut out = String::with_capacity(zeros + digits.len());
+    out.extend(core::iter::repeat_n(alphabet[0] as char, zeros));
+    out.extend(digits.iter().rev().map(|&d| alphabet[d as usize] as char));
+    out
+}
+
+/// Decode text written with [`base_x_encode`] in the base of `alphabet`
+pub(crate) fn base_x_decode(text: &str, alphabet: &[u8]) -> Option<Vec<u8>> {
+    let base = alphabet.len() as u32;
+    let zeros = text.bytes().take_while(|&c| c == alphabet[0]).count();
+
+    // Little-endian bytes of the number
+    let mut bytes: Vec<u8> = Vec::with_capacity(text.len());
+    for c in text.bytes().skip(zeros) {
+        let mut carry = alphabet.iter().position(|&a| a == c)? as u32;
+        for byte in &mut bytes {
+            carry += u32::from(*byte) * base;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let mut out = alloc::vec![0u8; zeros];
+    out.extend(bytes.iter().rev());
+    Some(out)
+}
+
+/// Decode `%XX` escapes in a magnet parameter value
+///
+/// Invalid escapes are kept as they are, and `+` is left alone (magnet values are not form data).
+/// Returns `None` if the decoded bytes are not valid UTF-8.
+pub(crate) fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let digits = bytes.get(i + 1..i + 3).and_then(|d| core::str::from_utf8(d).ok());
+            if let Some([byte]) = digits.and_then(hex_decode::<1>) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Percent-encode a value so it can be embedded in a magnet URL parameter
+///
+/// Unreserved characters and the URL delimiters `:`, `/`, `?`, `@`, `!`, `$`, `'`, `(`, `)`,
+/// `*`, `,` and `;` are kept as they are, so tracker and web seed URLs stay readable. Anything
+/// that would end or confuse a magnet parameter (`&`, `=`, `%`, `#`, `+`, spaces, ...) is escaped.
+pub(crate) fn percent_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for &b in value.as_bytes() {
+        match b {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b':'
+            | b'/'
+            | b'?'
+            | b'@'
+            | b'!'
+            | b'$'
+            | b'\''
+            | b'('
+            | b')'
+            | b'*'
+            | b','
+            | b';' => out.push(b as char),
+            _ => {
+                out.push('%');
+                out.push(HEX_DIGITS[(b >> 4) as usize].to_ascii_uppercase() as char);
+                out.push(HEX_DIGITS[(b & 0x0f) as usize].to_ascii_uppercase() as char);
+            }
+        }
+    }
+    out
+}
+
+/// Percent-encode raw bytes for a URL query, keeping only the RFC 3986 unreserved characters
+pub(crate) fn percent_encode_bytes(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 3);
+    for &b in bytes {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push('%');
+            out.push(HEX_DIGITS[(b >> 4) as usize].to_ascii_uppercase() as char);
+            out.push(HEX_DIGITS[(b & 0x0f) as usize].to_ascii_uppercase() as char);
+        }
+    }
+    out
+}
+
+/// The longest varint that holds a u64
+pub(crate) const MAX_VARINT_LEN: usize = 10;
+
+/// Read an unsigned LEB128 varint of at most `max_len` bytes from the front of `bytes`,
+/// advancing past it
+///
+/// Any u64 fits in [`MAX_VARINT_LEN`] bytes, but formats may allow fewer: multiformats (as in
+/// CIDs) limit varints to 9 bytes.
+pub(crate) fn read_varint(bytes: &mut &[u8], max_len: usize) -> Option<u64> {
+    let mut value = 0u64;
+    for (i, &byte) in bytes.iter().enumerate().take(max_len.min(MAX_VARINT_LEN)) {
+        // The tenth byte only has room for the top bit of a u64
+        if i == 9 && byte > 1 {
+            return None;
+        }
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Append `value` as an unsigned LEB128 varint: 7 bits per byte, least significant first
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        base32_decode, base32_decode_vec, base32_encode, base_x_decode, base_x_encode, hex_decode,
+        hex_decode_vec, hex_encode, percent_decode, percent_encode, percent_encode_bytes,
+        read_varint, write_varint, MAX_VARINT_LEN,
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    fn hex_encode_test() {
+        assert_eq!(hex_encode(&[]), "");
+        assert_eq!(hex_encode(&[0x00, 0x0f, 0xa5, 0xff]), "000fa5ff");
+    }
+
+    #[test]
+    fn hex_decode_test() {
+        assert_eq!(hex_decode::<4>("000fA5ff"), Some([0x00, 0x0f, 0xa5, 0xff]));
+        assert_eq!(hex_decode::<4>("000fa5f"), None);
+        assert_eq!(hex_decode::<2>("zz00"), None);
+    }
+
+    #[test]
+    fn base32_encode_test() {
+        assert_eq!(base32_encode(b""), "");
+        assert_eq!(base32_encode(b"f"), "MY");
+        assert_eq!(base32_encode(b"foo"), "MZXW6");
+        assert_eq!(base32_encode(b"fooba"), "MZXW6YTB");
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        let bytes = [0xa5; 20];
+        assert_eq!(base32_decode::<20>(&base32_encode(&bytes)), Some(bytes));
+    }
+
+    #[test]
+    fn base32_decode_test() {
+        assert_eq!(base32_decode::<5>("MZXW6YTB"), Some(*b"fooba"));
+        assert_eq!(base32_decode::<5>("mzxw6ytb"), Some(*b"fooba"));
+        assert_eq!(base32_decode::<3>("MZXW6"), Some(*b"foo"));
+        assert_eq!(base32_decode::<3>("MZXW7"), None);
+        assert_eq!(base32_decode::<5>("MZXW6YT1"), None);
+        assert_eq!(base32_decode::<5>("MZXW6YTBA"), None);
+    }
+
+    #[test]
+    fn hex_decode_vec_test() {
+        assert_eq!(hex_decode_vec("00fF10").as_deref(), Some(&[0, 255, 16][..]));
+        assert_eq!(hex_decode_vec("").as_deref(), Some(&[][..]));
+        assert_eq!(hex_decode_vec("abc"), None);
+        assert_eq!(hex_decode_vec("zz"), None);
+    }
+
+    #[test]
+    fn base32_decode_vec_test() {
+        assert_eq!(base32_decode_vec("").as_deref(), Some(&b""[..]));
+        assert_eq!(base32_decode_vec("MY").as_deref(), Some(&b"f"[..]));
+        assert_eq!(base32_decode_vec("mzxw6yq").as_deref(), Some(&b"foob"[..]));
+        assert_eq!(base32_decode_vec("MZXW6YTBOI").as_deref(), Some(&b"foobar"[..]));
+        assert_eq!(base32_decode_vec("MZ"), None);
+        assert_eq!(base32_decode_vec("MZX"), None);
+        assert_eq!(base32_decode_vec("MZ1"), None);
+    }
+
+    #[test]
+    fn base_x_test() {
+        const BASE58: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+        assert_eq!(base_x_encode(b"hello world", BASE58), "StV1DL6CwTryKyV");
+        assert_eq!(base_x_encode(&[0, 0, 1], BASE58), "112");
+        assert_eq!(base_x_encode(&[], BASE58), "");
+        assert_eq!(
+            base_x_decode("StV1DL6CwTryKyV", BASE58).as_deref(),
+            Some(&b"hello world"[..])
+        );
+        assert_eq!(base_x_decode("112", BASE58).as_deref(), Some(&[0, 0, 1][..]));
+        assert_eq!(base_x_decode("0OIl", BASE58), None);
+
+        const BASE36: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+        assert_eq!(base_x_encode(&[1, 0], BASE36), "74");
+        assert_eq!(base_x_decode("74", BASE36).as_deref(), Some(&[1, 0][..]));
+    }
+
+    #[test]
+    fn percent_decode_test() {
+        assert_eq!(
+            percent_decode("udp%3A%2F%2Ftracker.example.com%3a6969").as_deref(),
+            Some("udp://tracker.example.com:6969")
+        );
+        assert_eq!(percent_decode("a+b%2").as_deref(), Some("a+b%2"));
+        assert_eq!(percent_decode("100%zz%").as_deref(), Some("100%zz%"));
+        assert_eq!(percent_decode("%C3%BC").as_deref(), Some("ü"));
+        assert_eq!(percent_decode("%FF"), None);
+    }
+
+    #[test]
+    fn percent_encode_test() {
+        assert_eq!(percent_encode("Sintel"), "Sintel");
+        assert_eq!(percent_encode("My Torrent & more"), "My%20Torrent%20%26%20more");
+        assert_eq!(
+            percent_encode("udp://tracker.example.com:6969/announce"),
+            "udp://tracker.example.com:6969/announce"
+        );
+        assert_eq!(percent_encode("a=b+c%"), "a%3Db%2Bc%25");
+        assert_eq!(percent_encode("ü"), "%C3%BC");
+    }
+
+    #[test]
+    fn percent_encode_bytes_test() {
+        assert_eq!(percent_encode_bytes(b"aZ09-._~"), "aZ09-._~");
+        assert_eq!(percent_encode_bytes(&[0x00, 0x12, 0xff, b' ', b'/']), "%00%12%FF%20%2F");
+    }
+
+    #[test]
+    fn varint_test() {
+        let mut out = Vec::new();
+        write_varint(&mut out, 300);
+        assert_eq!(out, [0xac, 0x02]);
+        write_varint(&mut out, u64::MAX);
+        assert_eq!(out.len(), 12);
+
+        let mut bytes = &out[..];
+        assert_eq!(read_varint(&mut bytes, MAX_VARINT_LEN), Some(300));
+        assert_eq!(read_varint(&mut bytes, MAX_VARINT_LEN), Some(u64::MAX));
+        assert!(bytes.is_empty());
+
+        assert_eq!(read_varint(&mut &[0x80][..], MAX_VARINT_LEN), None);
+        // Too long, and too large for a u64
+        assert_eq!(read_varint(&mut &[0x80; 11][..], MAX_VARINT_LEN), None);
+        let overflow = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02];
+        assert_eq!(read_varint(&mut &overflow[..], MAX_VARINT_LEN), None);
+
+        // u64::MAX takes 10 bytes, more than the limit given
+        assert_eq!(read_varint(&mut &out[2..], 9), None);
+        let nine = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+        assert_eq!(read_varint(&mut &nine[..], 9), Some(u64::MAX >> 1));
+    }
+}
@@ -0,0 +1,150 @@
+//! Hashing files on disk into the exact topics of a magnet, in a single pass over the file
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::encoding::{hex_encode, percent_encode};
+use crate::sha1::Sha1;
+use crate::{Ed2kHasher, MagnetBuilder, Sha1Hash, TigerTree};
+
+/// The hashes [`MagnetBuilder::hash_file`] can compute, each added as an exact topic
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum FileHash {
+    /// The SHA-1 of the file, as `urn:sha1` in base32
+    Sha1,
+    /// The Tiger Tree Hash of the file, as `urn:tree:tiger` in base32
+    TigerTree,
+    /// The ed2k hash of the file, as `urn:ed2k` in hex
+    Ed2k,
+}
+
+enum Hasher {
+    Sha1(Sha1),
+    TigerTree(TigerTree),
+    Ed2k(Ed2kHasher),
+}
+
+impl Hasher {
+    fn new(hash: FileHash) -> Self {
+        match hash {
+            FileHash::Sha1 => Hasher::Sha1(Sha1::new()),
+            FileHash::TigerTree => Hasher::TigerTree(TigerTree::new()),
+            FileHash::Ed2k => Hasher::Ed2k(Ed2kHasher::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::TigerTree(hasher) => hasher.update(data),
+            Hasher::Ed2k(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Finish hashing, returning the (hash type, hash) of the exact topic
+    fn finish(self) -> (&'static str, String) {
+        match self {
+            Hasher::Sha1(hasher) => ("sha1", Sha1Hash::new(hasher.finish()).to_string()),
+            Hasher::TigerTree(hasher) => ("tree:tiger", hasher.finish().to_string()),
+            Hasher::Ed2k(hasher) => ("ed2k", hex_encode(&hasher.finish())),
+        }
+    }
+}
+
+impl MagnetBuilder {
+    /// Read the file at `path` once, computing each of `hashes` as an exact topic (in the given
+    /// order), and fill in its length (`xl`) and file name (`dn`)
+    ///
+    /// The exact topics are added with [`add_exact_topic`](Self::add_exact_topic), so they follow
+    /// any exact topic set before.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of opening or reading the file
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use magnet_url::{FileHash, MagnetBuilder};
+    ///
+    /// let magnet = MagnetBuilder::new()
+    ///     .hash_file("video.mkv", &[FileHash::TigerTree, FileHash::Ed2k])
+    ///     .unwrap()
+    ///     .build();
+    /// assert_eq!(magnet.exact_topics().count(), 2);
+    /// ```
+    pub fn hash_file(self, path: impl AsRef<Path>, hashes: &[FileHash]) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let mut hashers: Vec<Hasher> = hashes.iter().map(|&hash| Hasher::new(hash)).collect();
+
+        let mut buffer = [0u8; 64 * 1024];
+        let mut length = 0u64;
+        loop {
+            let read = match file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            for hasher in &mut hashers {
+                hasher.update(&buffer[..read]);
+            }
+            length += read as u64;
+        }
+
+        let mut builder = self;
+        for hasher in hashers {
+            let (hash_type, hash) = hasher.finish();
+            builder = builder.add_exact_topic(hash_type, &hash);
+        }
+        builder = builder.length(length);
+        if let Some(name) = path.file_name() {
+            builder = builder.display_name(&percent_encode(&name.to_string_lossy()));
+        }
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ed2k::Ed2kLink;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn hash_file_test() {
+        let dir = TempDir::new("file-hash");
+        let path = dir.join("data.bin");
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        let magnet = MagnetBuilder::new()
+            .hash_file(
+                &path,
+                &[FileHash::Sha1, FileHash::TigerTree, FileHash::Ed2k],
+            )
+            .unwrap()
+            .build();
+        assert_eq!(magnet.hash_type(), Some("sha1"));
+        assert_eq!(magnet.sha1_hash(), Some(Sha1Hash::new(Sha1::digest(&data))));
+        assert_eq!(magnet.tiger_tree_hash(), Some(TigerTree::digest(&data)));
+        assert_eq!(magnet.display_name(), Some("data.bin"));
+        assert_eq!(magnet.length(), Some(200_000));
+
+        let link = Ed2kLink::from_magnet(&magnet).unwrap();
+        assert_eq!(link.hash, Ed2kHasher::digest(&data));
+        assert_eq!(link.size, 200_000);
+
+        let magnet = MagnetBuilder::new().hash_file(&path, &[]).unwrap().build();
+        assert_eq!(magnet.hash(), None);
+        assert_eq!(magnet.length(), Some(200_000));
+
+        assert!(MagnetBuilder::new()
+            .hash_file(dir.join("missing"), &[FileHash::Ed2k])
+            .is_err());
+    }
+}
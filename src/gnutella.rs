@@ -0,0 +1,217 @@
+//! The hashes identifying files in Gnutella magnets: `urn:sha1`, `urn:tree:tiger` and
+//! `urn:bitprint`
+
+use core::fmt;
+
+use crate::encoding::{base32_decode, base32_encode, hex_decode};
+use crate::Magnet;
+
+/// A 20-byte SHA-1 hash of a whole file, from an `urn:sha1` exact topic
+///
+/// Displays as 32 uppercase base32 characters.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Sha1Hash([u8; 20]);
+
+impl Sha1Hash {
+    /// Wrap the raw bytes of a SHA-1 hash
+    pub const fn new(bytes: [u8; 20]) -> Self {
+        Sha1Hash(bytes)
+    }
+
+    /// Parse a SHA-1 hash written as 32 base32 characters, or as 40 hex characters as some
+    /// clients do
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.len() {
+            32 => base32_decode(text).map(Sha1Hash),
+            40 => hex_decode(text).map(Sha1Hash),
+            _ => None,
+        }
+    }
+
+    /// Get the raw bytes of the hash
+    pub const fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Sha1Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&base32_encode(&self.0))
+    }
+}
+
+/// A 24-byte Tiger Tree Hash (TTH): the root of a Merkle tree of Tiger hashes over the file's
+/// 1024-byte blocks, from an `urn:tree:tiger` exact topic
+///
+/// Displays as 39 uppercase base32 characters.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TigerTreeHash([u8; 24]);
+
+impl TigerTreeHash {
+    /// Wrap the raw bytes of a Tiger tree root
+    pub const fn new(bytes: [u8; 24]) -> Self {
+        TigerTreeHash(bytes)
+    }
+
+    /// Parse a Tiger tree root written as 39 base32 characters
+    pub fn parse(text: &str) -> Option<Self> {
+        base32_decode(text).map(TigerTreeHash)
+    }
+
+    /// Get the raw bytes of the hash
+    pub const fn as_bytes(&self) -> &[u8; 24] {
+        &self.0
+    }
+}
+
+impl fmt::Display for TigerTreeHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&base32_encode(&self.0))
+    }
+}
+
+/// A bitprint: the SHA-1 and Tiger tree hashes of the same file, written as
+/// `<sha1>.<tth>` in an `urn:bitprint` exact topic
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bitprint {
+    /// The SHA-1 of the file
+    pub sha1: Sha1Hash,
+    /// The Tiger tree root of the file
+    pub tiger_tree: TigerTreeHash,
+}
+
+impl Bitprint {
+    /// Parse a bitprint written as 32 base32 characters, a `.` and 39 base32 characters
+    pub fn parse(text: &str) -> Option<Self> {
+        let (sha1, tiger_tree) = text.split_once('.')?;
+        Some(Bitprint {
+            sha1: base32_decode(sha1).map(Sha1Hash)?,
+            tiger_tree: TigerTreeHash::parse(tiger_tree)?,
+        })
+    }
+}
+
+impl fmt::Display for Bitprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.sha1, self.tiger_tree)
+    }
+}
+
+impl Magnet {
+    /// Get the SHA-1 of the file, from a valid `urn:sha1` or `urn:bitprint` exact topic
+    pub fn sha1_hash(&self) -> Option<Sha1Hash> {
+        self.exact_topic("sha1")
+            .and_then(Sha1Hash::parse)
+            .or_else(|| self.bitprint_topic().map(|bitprint| bitprint.sha1))
+    }
+
+    /// Get the Tiger tree root of the file, from a valid `urn:tree:tiger` or `urn:bitprint`
+    /// exact topic
+    ///
+    /// The `urn:tree:tiger/:` and `urn:tree:tiger/1024:` spellings of older clients are accepted.
+    pub fn tiger_tree_hash(&self) -> Option<TigerTreeHash> {
+        self.exact_topics()
+            .find(|(hash_type, _)| {
+                let hash_type = hash_type.to_ascii_lowercase();
+                matches!(
+                    hash_type.as_str(),
+                    "tree:tiger" | "tree:tiger/" | "tree:tiger/1024"
+                )
+            })
+            .and_then(|(_, hash)| TigerTreeHash::parse(hash))
+            .or_else(|| self.bitprint_topic().map(|bitprint| bitprint.tiger_tree))
+    }
+
+    /// Get the bitprint of the file, from a valid `urn:bitprint` exact topic or else from
+    /// separate `urn:sha1` and `urn:tree:tiger` ones
+    pub fn bitprint(&self) -> Option<Bitprint> {
+        self.bitprint_topic().or_else(|| {
+            Some(Bitprint {
+                sha1: self.sha1_hash()?,
+                tiger_tree: self.tiger_tree_hash()?,
+            })
+        })
+    }
+
+    fn bitprint_topic(&self) -> Option<Bitprint> {
+        self.exact_topic("bitprint").and_then(Bitprint::parse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    // The hashes of an empty file
+    const SHA1: &str = "3I42H3S6NNFQ2MSVX7XZKYAYSCX5QBYJ";
+    const TTH: &str = "LWPNACQDBZRYXW3VHJVCJ64QBZNGHOHHHZWCLNQ";
+
+    #[test]
+    fn parse_test() {
+        let sha1 = Sha1Hash::parse(SHA1).unwrap();
+        assert_eq!(sha1.as_bytes()[..3], [0xda, 0x39, 0xa3]);
+        assert_eq!(sha1.to_string(), SHA1);
+        assert_eq!(
+            Sha1Hash::parse("da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            Some(sha1)
+        );
+        assert_eq!(Sha1Hash::parse(&SHA1[1..]), None);
+        assert_eq!(Sha1Hash::parse(TTH), None);
+
+        let tth = TigerTreeHash::parse(&TTH.to_lowercase()).unwrap();
+        assert_eq!(tth.to_string(), TTH);
+        assert_eq!(TigerTreeHash::parse(SHA1), None);
+
+        let bitprint = Bitprint::parse(&alloc::format!("{}.{}", SHA1, TTH)).unwrap();
+        assert_eq!(bitprint.sha1, sha1);
+        assert_eq!(bitprint.tiger_tree, tth);
+        assert_eq!(bitprint.to_string(), alloc::format!("{}.{}", SHA1, TTH));
+        assert_eq!(Bitprint::parse(SHA1), None);
+        assert_eq!(Bitprint::parse(&alloc::format!("{}.{}", TTH, SHA1)), None);
+    }
+
+    #[test]
+    fn magnet_test() {
+        let magnet = Magnet::new(&alloc::format!(
+            "magnet:?xt=urn:tree:tiger:{}&xt=urn:sha1:{}&dn=empty",
+            TTH,
+            SHA1
+        ))
+        .unwrap();
+        assert_eq!(magnet.hash_type(), Some("tree:tiger"));
+        assert_eq!(magnet.hash(), Some(TTH));
+        assert_eq!(magnet.exact_topic("tree:tiger"), Some(TTH));
+        assert_eq!(magnet.sha1_hash().unwrap().to_string(), SHA1);
+        assert_eq!(magnet.tiger_tree_hash().unwrap().to_string(), TTH);
+        assert_eq!(
+            magnet.bitprint().unwrap().to_string(),
+            alloc::format!("{}.{}", SHA1, TTH)
+        );
+        assert_eq!(
+            magnet.to_string(),
+            alloc::format!(
+                "magnet:?xt=urn:tree:tiger:{}&xt=urn:sha1:{}&dn=empty",
+                TTH,
+                SHA1
+            )
+        );
+
+        let magnet =
+            Magnet::new(&alloc::format!("magnet:?xt=urn:bitprint:{}.{}", SHA1, TTH)).unwrap();
+        assert_eq!(magnet.hash_type(), Some("bitprint"));
+        assert_eq!(magnet.sha1_hash().unwrap().to_string(), SHA1);
+        assert_eq!(magnet.tiger_tree_hash().unwrap().to_string(), TTH);
+
+        let magnet = Magnet::new(&alloc::format!("magnet:?xt=urn:tree:tiger/:{}", TTH)).unwrap();
+        assert_eq!(magnet.tiger_tree_hash().unwrap().to_string(), TTH);
+        assert_eq!(magnet.sha1_hash(), None);
+        assert_eq!(magnet.bitprint(), None);
+
+        // Wrong lengths are not hashes
+        let magnet =
+            Magnet::new("magnet:?xt=urn:sha1:3I42H3S6&xt=urn:tree:tiger:LWPNACQD").unwrap();
+        assert_eq!(magnet.sha1_hash(), None);
+        assert_eq!(magnet.tiger_tree_hash(), None);
+    }
+}
@@ -0,0 +1,530 @@
+//! Tracker health tracking, to try the trackers that work first
+//!
+//! [`TrackerHealth`] keeps the history of every tracker it has seen: how often it answered, how
+//! fast, and when. Trackers are scored from that history, which is used to reorder or prune the
+//! tracker list of a [`Magnet`]. The history can be saved to a file and loaded on the next run.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::time::Duration;
+use std::io::{self, BufRead, Write};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::encoding::percent_decode;
+use crate::Magnet;
+
+/// A request to a tracker, like an announce or a scrape, whose outcome can be recorded by
+/// [`TrackerHealth::request`]
+///
+/// It is implemented for closures taking the tracker URL, so any tracker client can be used.
+pub trait TrackerRequest {
+    /// The tracker's answer
+    type Output;
+    /// Why the request failed
+    type Error;
+
+    /// Send the request to the tracker at `url`
+    fn send(&mut self, url: &str) -> Result<Self::Output, Self::Error>;
+}
+
+impl<F, T, E> TrackerRequest for F
+where
+    F: FnMut(&str) -> Result<T, E>,
+{
+    type Output = T;
+    type Error = E;
+
+    fn send(&mut self, url: &str) -> Result<T, E> {
+        self(url)
+    }
+}
+
+/// The error of every tracker that was tried, with its URL
+pub type TrackerErrors<E> = Vec<(String, E)>;
+
+/// The request history of one tracker
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackerStats {
+    /// Number of successful requests
+    pub successes: u32,
+    /// Number of failed requests
+    pub failures: u32,
+    /// Number of failures since the last success
+    pub consecutive_failures: u32,
+    /// Moving average of the response time of successful requests
+    pub latency: Option<Duration>,
+    /// When the tracker last answered
+    pub last_success: Option<SystemTime>,
+    /// When a request to the tracker last failed
+    pub last_failure: Option<SystemTime>,
+}
+
+impl TrackerStats {
+    /// Get a score between 0 and 1, higher for trackers that are more likely to answer quickly
+    ///
+    /// Trackers without history score 0.5, which puts them after the trackers known to work and
+    /// before those that keep failing.
+    pub fn score(&self) -> f64 {
+        // Success rate with one success and one failure added, so a single request doesn't
+        // decide everything
+        let attempts = f64::from(self.successes) + f64::from(self.failures);
+        let mut score = (f64::from(self.successes) + 1.0) / (attempts + 2.0);
+        // Every failure in a row halves the score, as the tracker is probably gone
+        score *= 0.5f64.powi(self.consecutive_failures.min(64) as i32);
+        if let Some(latency) = self.latency {
+            score /= 1.0 + latency.as_secs_f64() / 10.0;
+        }
+        score
+    }
+
+    fn record_success(&mut self, latency: Duration, now: SystemTime) {
+        self.successes = self.successes.saturating_add(1);
+        self.consecutive_failures = 0;
+        self.latency = Some(match self.latency {
+            Some(average) => (average * 3 + latency) / 4,
+            None => latency,
+        });
+        self.last_success = Some(now);
+    }
+
+    fn record_failure(&mut self, now: SystemTime) {
+        self.failures = self.failures.saturating_add(1);
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        self.last_failure = Some(now);
+    }
+}
+
+/// A registry of tracker health, keyed by percent-decoded tracker URL
+///
+/// # Example
+///
+/// ```no_run
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use magnet_url::Magnet;
+/// use magnet_url::health::TrackerHealth;
+///
+/// let mut health = match File::open("trackers.txt") {
+///     Ok(file) => TrackerHealth::load(BufReader::new(file)).unwrap(),
+///     Err(_) => TrackerHealth::new(),
+/// };
+///
+/// let mut magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&tr=udp%3A%2F%2Fexplodie.org%3A6969&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337").unwrap();
+/// health.prune_trackers(&mut magnet, 0.01);
+/// health.sort_trackers(&mut magnet);
+///
+/// let result = health.request_in_order(&magnet, |url: &str| -> Result<(), String> {
+///     // Announce with any tracker client here
+///     Err(format!("{} did not answer", url))
+/// });
+/// println!("{:?}", result);
+///
+/// health.save(File::create("trackers.txt").unwrap()).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackerHealth {
+    trackers: BTreeMap<String, TrackerStats>,
+}
+
+impl TrackerHealth {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the history of a tracker, if it has one
+    pub fn stats(&self, url: &str) -> Option<&TrackerStats> {
+        self.trackers.get(&key(url))
+    }
+
+    /// Get a tracker's score (see [`TrackerStats::score`])
+    pub fn score(&self, url: &str) -> f64 {
+        self.stats(url).cloned().unwrap_or_default().score()
+    }
+
+    /// Iterate over the trackers with a history, by URL
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TrackerStats)> {
+        self.trackers
+            .iter()
+            .map(|(url, stats)| (url.as_str(), stats))
+    }
+
+    /// Get the number of trackers with a history
+    pub fn len(&self) -> usize {
+        self.trackers.len()
+    }
+
+    /// Check whether no tracker has a history
+    pub fn is_empty(&self) -> bool {
+        self.trackers.is_empty()
+    }
+
+    /// Forget a tracker's history
+    pub fn remove(&mut self, url: &str) -> Option<TrackerStats> {
+        self.trackers.remove(&key(url))
+    }
+
+    /// Record that a tracker answered after `latency`
+    pub fn record_success(&mut self, url: &str, latency: Duration) {
+        self.record_success_at(url, latency, SystemTime::now());
+    }
+
+    /// Record that a request to a tracker failed
+    pub fn record_failure(&mut self, url: &str) {
+        self.record_failure_at(url, SystemTime::now());
+    }
+
+    fn record_success_at(&mut self, url: &str, latency: Duration, now: SystemTime) {
+        let stats = self.trackers.entry(key(url)).or_default();
+        stats.record_success(latency, now);
+    }
+
+    fn record_failure_at(&mut self, url: &str, now: SystemTime) {
+        self.trackers
+            .entry(key(url))
+            .or_default()
+            .record_failure(now);
+    }
+
+    /// Send a request to a tracker, and record whether it succeeded and how long it took
+    pub fn request<R: TrackerRequest>(
+        &mut self,
+        url: &str,
+        mut request: R,
+    ) -> Result<R::Output, R::Error> {
+        self.send(url, &mut request)
+    }
+
+    fn send<R: TrackerRequest>(
+        &mut self,
+        url: &str,
+        request: &mut R,
+    ) -> Result<R::Output, R::Error> {
+        let start = Instant::now();
+        let result = request.send(url);
+        match result {
+            Ok(_) => self.record_success(url, start.elapsed()),
+            Err(_) => self.record_failure(url),
+        }
+        result
+    }
+
+    /// Send a request to the magnet's trackers, best score first, until one succeeds
+    ///
+    /// Returns the URL (as it appears in the magnet) and output of the first success, or every
+    /// error if all trackers failed.
+    pub fn request_in_order<R: TrackerRequest>(
+        &mut self,
+        magnet: &Magnet,
+        mut request: R,
+    ) -> Result<(String, R::Output), TrackerErrors<R::Error>> {
+        let mut errors = Vec::new();
+        for url in self.sorted(magnet.trackers()) {
+            match self.send(&url, &mut request) {
+                Ok(output) => return Ok((url, output)),
+                Err(err) => errors.push((url, err)),
+            }
+        }
+        Err(errors)
+    }
+
+    /// Reorder the magnet's trackers from the best score to the worst
+    ///
+    /// Trackers with the same score keep their order.
+    pub fn sort_trackers(&self, magnet: &mut Magnet) {
+        magnet.trackers = self.sorted(&magnet.trackers);
+    }
+
+    /// Remove the magnet's trackers that score below `min_score`
+    pub fn prune_trackers(&self, magnet: &mut Magnet, min_score: f64) {
+        magnet
+            .trackers
+            .retain(|tracker| self.score(tracker) >= min_score);
+    }
+
+    fn sorted(&self, trackers: &[String]) -> Vec<String> {
+        let mut scored: Vec<(f64, &String)> = trackers
+            .iter()
+            .map(|tracker| (self.score(tracker), tracker))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored
+            .into_iter()
+            .map(|(_, tracker)| tracker.clone())
+            .collect()
+    }
+
+    /// Write the registry as text, one tracker per line
+    ///
+    /// Each line holds the successes, failures, consecutive failures, latency in milliseconds,
+    /// and the times of the last success and failure in seconds since the Unix epoch (`-` when
+    /// unknown), followed by the URL. Trackers whose URL contains a line break are skipped.
+    pub fn save(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "# magnet-url tracker health v1")?;
+        for (url, stats) in &self.trackers {
+            if url.contains(['\n', '\r']) {
+                continue;
+            }
+            writeln!(
+                writer,
+                "{} {} {} {} {} {} {}",
+                stats.successes,
+                stats.failures,
+                stats.consecutive_failures,
+                optional(stats.latency.map(|latency| latency.as_millis())),
+                optional(stats.last_success.map(unix_seconds)),
+                optional(stats.last_failure.map(unix_seconds)),
+                url,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Read a registry written by [`save`](TrackerHealth::save)
+    ///
+    /// Blank lines and lines starting with `#` are skipped. Fails with `InvalidData` if a line
+    /// is malformed.
+    pub fn load(reader: impl BufRead) -> io::Result<Self> {
+        let mut health = Self::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (url, stats) = parse_line(&line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    alloc::format!("invalid tracker health line: {}", line),
+                )
+            })?;
+            health.trackers.insert(url, stats);
+        }
+        Ok(health)
+    }
+}
+
+/// Trackers are compared percent-decoded, as magnets may encode the same URL differently
+fn key(url: &str) -> String {
+    percent_decode(url).unwrap_or_else(|| url.to_string())
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+fn optional(value: Option<impl ToString>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| value.to_string())
+}
+
+fn parse_line(line: &str) -> Option<(String, TrackerStats)> {
+    let mut fields = line.splitn(7, ' ');
+    let mut number = || fields.next()?.parse::<u32>().ok();
+    let (successes, failures, consecutive_failures) = (number()?, number()?, number()?);
+    let mut optional = || match fields.next()? {
+        "-" => Some(None),
+        field => field.parse::<u64>().ok().map(Some),
+    };
+    let latency = optional()?.map(Duration::from_millis);
+    let mut time = || match optional()? {
+        Some(seconds) => UNIX_EPOCH
+            .checked_add(Duration::from_secs(seconds))
+            .map(Some),
+        None => Some(None),
+    };
+    let last_success = time()?;
+    let last_failure = time()?;
+    let url = fields.next().filter(|url| !url.is_empty())?;
+
+    let stats = TrackerStats {
+        successes,
+        failures,
+        consecutive_failures,
+        latency,
+        last_success,
+        last_failure,
+    };
+    Some((url.to_string(), stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TrackerHealth, TrackerStats};
+    use crate::Magnet;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::io;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const FAST: &str = "udp://fast.example.com:6969";
+    const SLOW: &str = "udp://slow.example.com:6969";
+    const DEAD: &str = "udp://dead.example.com:6969";
+    const NEW: &str = "udp://new.example.com:6969";
+    const SLOW_ENCODED: &str = "udp%3A%2F%2Fslow.example.com%3A6969";
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    /// A registry where FAST answers quickly, SLOW answers slowly and DEAD keeps failing
+    fn health() -> TrackerHealth {
+        let mut health = TrackerHealth::new();
+        for i in 0..3 {
+            health.record_success_at(FAST, Duration::from_millis(40), at(100 + i));
+            health.record_success_at(SLOW, Duration::from_secs(5), at(100 + i));
+            health.record_failure_at(DEAD, at(100 + i));
+        }
+        health
+    }
+
+    /// A magnet with the trackers in the wrong order, one of them percent-encoded
+    fn magnet() -> Magnet {
+        Magnet::new(&alloc::format!(
+            "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&tr={}&tr={}&tr={}&tr={}",
+            DEAD,
+            NEW,
+            SLOW_ENCODED,
+            FAST
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn score_test() {
+        assert_eq!(TrackerStats::default().score(), 0.5);
+
+        let health = health();
+        let stats = health.stats(FAST).unwrap();
+        assert_eq!((stats.successes, stats.failures), (3, 0));
+        assert_eq!(stats.latency, Some(Duration::from_millis(40)));
+        assert_eq!(stats.last_success, Some(at(102)));
+        assert_eq!(health.stats(DEAD).unwrap().consecutive_failures, 3);
+
+        assert!(health.score(FAST) > health.score(SLOW));
+        assert!(health.score(SLOW) > health.score(NEW));
+        assert!(health.score(NEW) > health.score(DEAD));
+        // Encoded URLs share the history of the decoded URL
+        assert_eq!(
+            health.score("udp%3A%2F%2Ffast.example.com%3A6969"),
+            health.score(FAST)
+        );
+
+        // A success resets the failure streak and averages the latency
+        let mut health = health;
+        health.record_success_at(DEAD, Duration::from_millis(200), at(200));
+        health.record_success_at(DEAD, Duration::from_millis(600), at(201));
+        let stats = health.stats(DEAD).unwrap();
+        assert_eq!(stats.consecutive_failures, 0);
+        assert_eq!(stats.failures, 3);
+        assert_eq!(stats.latency, Some(Duration::from_millis(300)));
+        assert!(health.score(DEAD) > health.score(NEW) / 2.0);
+    }
+
+    #[test]
+    fn sort_and_prune_test() {
+        let health = health();
+        let mut magnet = magnet();
+        health.sort_trackers(&mut magnet);
+        assert_eq!(magnet.trackers(), [FAST, SLOW_ENCODED, NEW, DEAD]);
+
+        let mut magnet = self::magnet();
+        health.prune_trackers(&mut magnet, 0.1);
+        assert_eq!(magnet.trackers().len(), 3);
+        assert!(!magnet.trackers().iter().any(|t| t == DEAD));
+        // Unlike sorting, pruning keeps the order
+        assert_eq!(magnet.trackers()[0], NEW);
+    }
+
+    #[test]
+    fn shorten_keeps_healthiest_test() {
+        let health = health();
+        let mut magnet = magnet();
+        health.sort_trackers(&mut magnet);
+        let budget = "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10".len()
+            + "&tr=".len() * 2
+            + FAST.len()
+            + SLOW_ENCODED.len();
+        let report = magnet.shorten(&crate::Shortener::new(budget));
+        assert!(report.fits);
+        assert_eq!(magnet.trackers(), [FAST, SLOW_ENCODED]);
+        assert_eq!(
+            report.dropped,
+            [
+                ("tr".to_string(), NEW.to_string()),
+                ("tr".to_string(), DEAD.to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn request_test() {
+        let mut health = TrackerHealth::new();
+        assert_eq!(health.request(FAST, |_: &str| Ok::<_, ()>(42)), Ok(42));
+        assert_eq!(
+            health.request(DEAD, |_: &str| Err::<(), _>("timeout")),
+            Err("timeout")
+        );
+        assert_eq!(health.stats(FAST).unwrap().successes, 1);
+        assert!(health.stats(FAST).unwrap().latency.is_some());
+        assert_eq!(health.stats(DEAD).unwrap().failures, 1);
+
+        // Trackers are tried best first until one answers
+        let mut health = self::health();
+        let mut tried = Vec::new();
+        let result = health.request_in_order(&magnet(), |url: &str| {
+            tried.push(url.to_string());
+            if url.contains("slow") {
+                Ok(url.len())
+            } else {
+                Err(io::Error::from(io::ErrorKind::TimedOut))
+            }
+        });
+        let (url, _) = result.unwrap();
+        assert!(url.contains("slow"));
+        assert_eq!(tried.len(), 2);
+        assert_eq!(tried[0], FAST);
+        assert_eq!(health.stats(FAST).unwrap().consecutive_failures, 1);
+        assert_eq!(health.stats(SLOW).unwrap().successes, 4);
+
+        let errors = health
+            .request_in_order(&magnet(), |_: &str| Err::<(), _>(()))
+            .unwrap_err();
+        assert_eq!(errors.len(), 4);
+        assert_eq!(health.stats(NEW).unwrap().failures, 1);
+    }
+
+    #[test]
+    fn save_load_test() {
+        let mut health = health();
+        health.record_success_at(
+            "http://tracker.example.com/announce?key=a b",
+            Duration::from_millis(7),
+            at(5),
+        );
+        health.record_failure_at("udp://broken\nline:1", at(5));
+
+        let mut saved = Vec::new();
+        health.save(&mut saved).unwrap();
+        let text = String::from_utf8(saved.clone()).unwrap();
+        assert!(text.contains("\n3 0 0 40 102 - udp://fast.example.com:6969\n"));
+        assert!(text.contains("\n0 3 3 - - 102 udp://dead.example.com:6969\n"));
+
+        let loaded = TrackerHealth::load(&saved[..]).unwrap();
+        health.remove("udp://broken\nline:1");
+        assert_eq!(loaded, health);
+        assert_eq!(loaded.len(), 4);
+
+        let err = TrackerHealth::load(&b"1 2 3 - - -\n"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = TrackerHealth::load(&b"1 2 x - - - udp://a:1\n"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err =
+            TrackerHealth::load(&b"1 0 0 - 18446744073709551615 - udp://t:1\n"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(TrackerHealth::load(&b"# comment\n\n"[..])
+            .unwrap()
+            .is_empty());
+    }
+}
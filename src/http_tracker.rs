@@ -0,0 +1,741 @@
+//! HTTP tracker client, enabled by the `http-tracker` feature
+//!
+//! Announce and scrape URLs are built as described in
+//! [BEP 3](https://www.bittorrent.org/beps/bep_0003.html) and
+//! [BEP 48](https://www.bittorrent.org/beps/bep_0048.html), and bencoded responses are parsed
+//! with both compact ([BEP 23](https://www.bittorrent.org/beps/bep_0023.html)) and dictionary
+//! peer lists, plus IPv6 `peers6` ([BEP 7](https://www.bittorrent.org/beps/bep_0007.html)).
+//!
+//! Plain `http://` trackers are queried with a small built-in HTTP/1.1 client. The crate has no
+//! TLS implementation, so `https://` trackers need the `_with` methods, which take a function
+//! that fetches a URL with the HTTP client of your choice.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write as _;
+use core::time::Duration;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+
+use crate::bencode::{self, Value};
+use crate::encoding::{percent_decode, percent_encode_bytes};
+use crate::tracker::parse_peers;
+use crate::{InfoHash, Magnet};
+
+pub use crate::tracker::{AnnounceEvent, AnnounceRequest, AnnounceResponse, ScrapeStats};
+
+/// The largest response the built-in HTTP client reads, headers included, so a hostile tracker
+/// can't make it buffer without bound. Even large peer lists are far smaller.
+const MAX_RESPONSE_SIZE: u64 = 1 << 20;
+
+/// The ways an HTTP tracker request can fail
+#[derive(Debug)]
+pub enum HttpTrackerError {
+    /// The tracker URL is not an `http://` or `https://` URL
+    InvalidUrl,
+    /// The tracker uses HTTPS, which needs a fetch function (see `announce_with`)
+    HttpsUnsupported,
+    /// The tracker's announce URL has no scrape equivalent
+    ScrapeUnsupported,
+    /// Connecting to the tracker or reading its response failed
+    Io(io::Error),
+    /// The tracker answered with a status other than 200
+    Status(u16),
+    /// The tracker answered with a failure reason
+    Tracker(String),
+    /// The tracker's response is not valid HTTP or bencode
+    InvalidResponse,
+    /// The tracker's response is larger than the built-in HTTP client accepts
+    ResponseTooLarge,
+}
+
+impl fmt::Display for HttpTrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpTrackerError::InvalidUrl => write!(f, "not a valid HTTP tracker URL"),
+            HttpTrackerError::HttpsUnsupported => write!(f, "HTTPS trackers need a fetch function"),
+            HttpTrackerError::ScrapeUnsupported => write!(f, "tracker does not support scraping"),
+            HttpTrackerError::Io(err) => write!(f, "tracker I/O error: {}", err),
+            HttpTrackerError::Status(status) => {
+                write!(f, "tracker answered with HTTP status {}", status)
+            }
+            HttpTrackerError::Tracker(message) => write!(f, "tracker error: {}", message),
+            HttpTrackerError::InvalidResponse => write!(f, "tracker sent an invalid response"),
+            HttpTrackerError::ResponseTooLarge => write!(f, "tracker response is too large"),
+        }
+    }
+}
+
+impl std::error::Error for HttpTrackerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HttpTrackerError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for HttpTrackerError {
+    fn from(err: io::Error) -> Self {
+        HttpTrackerError::Io(err)
+    }
+}
+
+/// A client for a single HTTP or HTTPS tracker
+///
+/// # Example
+///
+/// ```no_run
+/// use magnet_url::Magnet;
+/// use magnet_url::http_tracker::{AnnounceRequest, HttpTracker};
+///
+/// let magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&tr=http%3A%2F%2Ftracker.example.com%2Fannounce").unwrap();
+/// let request = AnnounceRequest::from_magnet(&magnet, 6881).unwrap();
+///
+/// for url in magnet.http_trackers() {
+///     let tracker = HttpTracker::with_decoded_url(&url).unwrap();
+///     match tracker.announce(&request) {
+///         Ok(response) => println!("{}: {:?}", url, response.peers),
+///         Err(err) => eprintln!("{}: {}", url, err),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpTracker {
+    url: String,
+    timeout: Duration,
+}
+
+impl HttpTracker {
+    /// Create a client for an `http://` or `https://` announce URL, which may still be
+    /// percent-encoded as it appears in a magnet
+    pub fn new(url: &str) -> Result<Self, HttpTrackerError> {
+        let url = percent_decode(url).ok_or(HttpTrackerError::InvalidUrl)?;
+        Self::with_decoded_url(&url)
+    }
+
+    /// Create a client for an `http://` or `https://` announce URL that is already
+    /// percent-decoded, like the ones returned by [`Magnet::http_trackers`]
+    pub fn with_decoded_url(url: &str) -> Result<Self, HttpTrackerError> {
+        if split_http_url(url).is_none() {
+            return Err(HttpTrackerError::InvalidUrl);
+        }
+        Ok(Self {
+            url: url.to_string(),
+            timeout: Duration::from_secs(15),
+        })
+    }
+
+    /// Set the connect and read timeout of the built-in HTTP client
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get the full announce URL for a request
+    pub fn announce_url(&self, request: &AnnounceRequest) -> String {
+        let mut url = self.url.clone();
+        url.push(if url.contains('?') { '&' } else { '?' });
+        let _ = write!(
+            url,
+            "info_hash={}&peer_id={}&port={}&uploaded={}&downloaded={}&left={}&compact=1&key={:08x}",
+            percent_encode_bytes(request.info_hash.as_bytes()),
+            percent_encode_bytes(&request.peer_id),
+            request.port,
+            request.uploaded,
+            request.downloaded,
+            request.left,
+            request.key,
+        );
+
+        let event = match request.event {
+            AnnounceEvent::None => None,
+            AnnounceEvent::Completed => Some("completed"),
+            AnnounceEvent::Started => Some("started"),
+            AnnounceEvent::Stopped => Some("stopped"),
+        };
+        if let Some(event) = event {
+            let _ = write!(url, "&event={}", event);
+        }
+        if let Some(num_want) = request.num_want {
+            let _ = write!(url, "&numwant={}", num_want);
+        }
+        url
+    }
+
+    /// Get the scrape URL for some info hashes
+    ///
+    /// Following the usual convention, this replaces `announce` at the start of the last path
+    /// segment with `scrape`. Returns `None` if the announce URL does not have that form.
+    pub fn scrape_url(&self, info_hashes: &[InfoHash]) -> Option<String> {
+        let (base, query) = match self.url.split_once('?') {
+            Some((base, query)) => (base, Some(query)),
+            None => (self.url.as_str(), None),
+        };
+        let (_, path) = split_http_url(base)?;
+        let segment = path.rsplit('/').next()?;
+        let rest = segment.strip_prefix("announce")?;
+
+        let mut url = format!("{}scrape{}", &base[..base.len() - segment.len()], rest);
+        let mut separator = '?';
+        if let Some(query) = query {
+            let _ = write!(url, "?{}", query);
+            separator = '&';
+        }
+        for info_hash in info_hashes {
+            let _ = write!(
+                url,
+                "{}info_hash={}",
+                separator,
+                percent_encode_bytes(info_hash.as_bytes())
+            );
+            separator = '&';
+        }
+        Some(url)
+    }
+
+    /// Announce to the tracker and get a list of peers
+    pub fn announce(
+        &self,
+        request: &AnnounceRequest,
+    ) -> Result<AnnounceResponse, HttpTrackerError> {
+        self.announce_with(request, |url| self.get(url))
+    }
+
+    /// Announce using `fetch` to download the announce URL's response body
+    pub fn announce_with<F>(
+        &self,
+        request: &AnnounceRequest,
+        fetch: F,
+    ) -> Result<AnnounceResponse, HttpTrackerError>
+    where
+        F: FnOnce(&str) -> Result<Vec<u8>, HttpTrackerError>,
+    {
+        parse_announce(&fetch(&self.announce_url(request))?)
+    }
+
+    /// Get the tracker's statistics for each of `info_hashes`, in the same order
+    ///
+    /// Torrents the tracker does not know about get zeroed statistics.
+    pub fn scrape(&self, info_hashes: &[InfoHash]) -> Result<Vec<ScrapeStats>, HttpTrackerError> {
+        self.scrape_with(info_hashes, |url| self.get(url))
+    }
+
+    /// Scrape using `fetch` to download the scrape URL's response body
+    pub fn scrape_with<F>(
+        &self,
+        info_hashes: &[InfoHash],
+        fetch: F,
+    ) -> Result<Vec<ScrapeStats>, HttpTrackerError>
+    where
+        F: FnOnce(&str) -> Result<Vec<u8>, HttpTrackerError>,
+    {
+        let url = self
+            .scrape_url(info_hashes)
+            .ok_or(HttpTrackerError::ScrapeUnsupported)?;
+        parse_scrape(&fetch(&url)?, info_hashes)
+    }
+
+    /// Fetch a URL with the built-in HTTP/1.1 client
+    fn get(&self, url: &str) -> Result<Vec<u8>, HttpTrackerError> {
+        let (authority, path) = split_http_url(url).ok_or(HttpTrackerError::InvalidUrl)?;
+        let plain = url
+            .split_once("://")
+            .is_some_and(|(scheme, _)| scheme.eq_ignore_ascii_case("http"));
+        if !plain {
+            return Err(HttpTrackerError::HttpsUnsupported);
+        }
+        let path = match path.chars().next() {
+            Some('/') => path.to_string(),
+            _ => format!("/{}", path),
+        };
+
+        // Add the default port unless the authority (possibly an IPv6 literal) has one
+        let has_port = authority
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| !port.contains(']'));
+        let addrs = if has_port {
+            authority.to_socket_addrs()?
+        } else {
+            format!("{}:80", authority).to_socket_addrs()?
+        };
+        let mut stream = connect(addrs, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: magnet-url/{}\r\nAccept-Encoding: identity\r\nConnection: close\r\n\r\n",
+            path,
+            authority,
+            env!("CARGO_PKG_VERSION"),
+        );
+        stream.write_all(request.as_bytes())?;
+
+        let mut response = Vec::new();
+        stream
+            .take(MAX_RESPONSE_SIZE + 1)
+            .read_to_end(&mut response)?;
+        if response.len() as u64 > MAX_RESPONSE_SIZE {
+            return Err(HttpTrackerError::ResponseTooLarge);
+        }
+        parse_http_response(&response)
+    }
+}
+
+impl Magnet {
+    /// Get the magnet's `http://` and `https://` tracker URLs, percent-decoded
+    pub fn http_trackers(&self) -> Vec<String> {
+        self.trackers()
+            .iter()
+            .filter_map(|tracker| percent_decode(tracker))
+            .filter(|tracker| split_http_url(tracker).is_some())
+            .collect()
+    }
+}
+
+/// Split an `http://` or `https://` URL into its authority and its path (with the query)
+fn split_http_url(url: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(end);
+    (!authority.is_empty()).then_some((authority, path))
+}
+
+/// Connect to the first of `addrs` that accepts a connection
+fn connect(addrs: impl Iterator<Item = SocketAddr>, timeout: Duration) -> io::Result<TcpStream> {
+    let mut last_err = io::Error::new(io::ErrorKind::NotFound, "tracker host has no addresses");
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
+
+/// Get the body of a complete HTTP/1.1 response, checking its status
+fn parse_http_response(response: &[u8]) -> Result<Vec<u8>, HttpTrackerError> {
+    let head_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or(HttpTrackerError::InvalidResponse)?;
+    let head = core::str::from_utf8(&response[..head_end])
+        .map_err(|_| HttpTrackerError::InvalidResponse)?;
+    let body = &response[head_end + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or(HttpTrackerError::InvalidResponse)?;
+    if status != 200 {
+        return Err(HttpTrackerError::Status(status));
+    }
+
+    let mut chunked = false;
+    let mut content_length = None;
+    for line in lines {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or(HttpTrackerError::InvalidResponse)?;
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_length = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| HttpTrackerError::InvalidResponse)?,
+            );
+        }
+    }
+
+    if chunked {
+        return dechunk(body).ok_or(HttpTrackerError::InvalidResponse);
+    }
+    match content_length {
+        Some(length) => body
+            .get(..length)
+            .map(<[u8]>::to_vec)
+            .ok_or(HttpTrackerError::InvalidResponse),
+        None => Ok(body.to_vec()),
+    }
+}
+
+/// Decode a `Transfer-Encoding: chunked` body
+fn dechunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|w| w == b"\r\n")?;
+        let size = core::str::from_utf8(&body[..line_end]).ok()?;
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(out);
+        }
+        out.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+/// Parse a bencoded announce response
+fn parse_announce(body: &[u8]) -> Result<AnnounceResponse, HttpTrackerError> {
+    let response = bencode::decode(body).map_err(|_| HttpTrackerError::InvalidResponse)?;
+    check_failure(&response)?;
+
+    let interval = response
+        .get("interval")
+        .and_then(Value::as_int)
+        .and_then(|i| u64::try_from(i).ok())
+        .ok_or(HttpTrackerError::InvalidResponse)?;
+    let count = |key| {
+        response
+            .get(key)
+            .and_then(Value::as_int)
+            .map_or(0, |n| u32::try_from(n).unwrap_or(0))
+    };
+
+    let mut peers = match response.get("peers") {
+        None => Vec::new(),
+        Some(Value::Bytes(compact)) => {
+            parse_peers(compact, false).ok_or(HttpTrackerError::InvalidResponse)?
+        }
+        Some(Value::List(list)) => list.iter().filter_map(dict_peer).collect(),
+        Some(_) => return Err(HttpTrackerError::InvalidResponse),
+    };
+    if let Some(compact) = response.get("peers6") {
+        let compact = compact
+            .as_bytes()
+            .ok_or(HttpTrackerError::InvalidResponse)?;
+        peers.extend(parse_peers(compact, true).ok_or(HttpTrackerError::InvalidResponse)?);
+    }
+
+    Ok(AnnounceResponse {
+        interval: Duration::from_secs(interval),
+        leechers: count("incomplete"),
+        seeders: count("complete"),
+        peers,
+    })
+}
+
+/// Parse a peer of a non-compact peer list, skipping peers given by host name
+fn dict_peer(peer: &Value<'_>) -> Option<SocketAddr> {
+    let ip: IpAddr = peer.get("ip")?.as_str()?.parse().ok()?;
+    let port = u16::try_from(peer.get("port")?.as_int()?).ok()?;
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Parse a bencoded scrape response, in the order of `info_hashes`
+fn parse_scrape(
+    body: &[u8],
+    info_hashes: &[InfoHash],
+) -> Result<Vec<ScrapeStats>, HttpTrackerError> {
+    let response = bencode::decode(body).map_err(|_| HttpTrackerError::InvalidResponse)?;
+    check_failure(&response)?;
+
+    let files = response
+        .get("files")
+        .and_then(Value::as_dict)
+        .ok_or(HttpTrackerError::InvalidResponse)?;
+    let stats = info_hashes
+        .iter()
+        .map(|info_hash| {
+            let Some((_, file)) = files.iter().find(|(key, _)| *key == info_hash.as_bytes()) else {
+                return ScrapeStats::default();
+            };
+            let count = |key| {
+                file.get(key)
+                    .and_then(Value::as_int)
+                    .map_or(0, |n| u32::try_from(n).unwrap_or(0))
+            };
+            ScrapeStats {
+                seeders: count("complete"),
+                completed: count("downloaded"),
+                leechers: count("incomplete"),
+            }
+        })
+        .collect();
+    Ok(stats)
+}
+
+fn check_failure(response: &Value<'_>) -> Result<(), HttpTrackerError> {
+    match response.get("failure reason").and_then(Value::as_bytes) {
+        Some(reason) => Err(HttpTrackerError::Tracker(
+            String::from_utf8_lossy(reason).to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnnounceEvent, AnnounceRequest, HttpTracker, HttpTrackerError, ScrapeStats};
+    use crate::{InfoHash, Magnet};
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    const SINTEL: &str = "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&tr=http%3A%2F%2Ftracker.example.com%2Fannounce&tr=udp%3A%2F%2Fexplodie.org%3A6969&tr=https%3A%2F%2Ftracker.example.org%3A443%2Fannounce";
+
+    /// A stand-in HTTP server answering one request with `response`, returning its URL and the
+    /// request line it received
+    fn server(response: Vec<u8>) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/announce", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut line = String::new();
+            while line != "\r\n" {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+            }
+            // The client may stop reading early, and close the connection
+            let _ = reader.get_mut().write_all(&response);
+            request_line
+        });
+        (url, handle)
+    }
+
+    fn ok(body: &[u8]) -> Vec<u8> {
+        let mut response =
+            format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    fn request() -> AnnounceRequest {
+        AnnounceRequest::new(InfoHash::new(*b"\x12\x34abcdefghij\xff\x00 /~.-_"), 6881)
+            .peer_id(*b"-MU3000-123456789012")
+            .key(0xabc)
+    }
+
+    #[test]
+    fn announce_url_test() {
+        let tracker = HttpTracker::new("http%3A%2F%2Ftracker.example.com%2Fannounce").unwrap();
+        assert_eq!(
+            tracker.announce_url(&request().left(100).event(AnnounceEvent::Started).num_want(30)),
+            "http://tracker.example.com/announce?info_hash=%124abcdefghij%FF%00%20%2F~.-_&peer_id=-MU3000-123456789012&port=6881&uploaded=0&downloaded=0&left=100&compact=1&key=00000abc&event=started&numwant=30"
+        );
+
+        let tracker =
+            HttpTracker::new("https://tracker.example.com/announce.php?passkey=abc").unwrap();
+        assert!(tracker
+            .announce_url(&request())
+            .starts_with("https://tracker.example.com/announce.php?passkey=abc&info_hash="));
+
+        // Escapes in an already decoded URL are kept
+        let tracker = HttpTracker::new("http%3A%2F%2Fexample.com%2Fannounce%3Fpasskey%3Da%252Fb")
+            .unwrap();
+        assert_eq!(
+            tracker,
+            HttpTracker::with_decoded_url("http://example.com/announce?passkey=a%2Fb").unwrap()
+        );
+        assert!(tracker
+            .announce_url(&request())
+            .starts_with("http://example.com/announce?passkey=a%2Fb&info_hash="));
+    }
+
+    #[test]
+    fn scrape_url_test() {
+        let hashes = [InfoHash::new([0xff; 20]), InfoHash::new([b'a'; 20])];
+        let scrape_url = |url| HttpTracker::new(url).unwrap().scrape_url(&hashes[..1]);
+        let encoded = "%FF".repeat(20);
+        assert_eq!(
+            scrape_url("http://example.com/announce"),
+            Some(format!("http://example.com/scrape?info_hash={}", encoded))
+        );
+        assert_eq!(
+            scrape_url("http://example.com/x/announce.php?passkey=1"),
+            Some(format!(
+                "http://example.com/x/scrape.php?passkey=1&info_hash={}",
+                encoded
+            ))
+        );
+        assert_eq!(scrape_url("http://example.com/a"), None);
+        assert_eq!(scrape_url("http://example.com/announce/x"), None);
+        assert_eq!(scrape_url("http://example.com"), None);
+
+        let tracker = HttpTracker::new("http://example.com/announce").unwrap();
+        assert_eq!(
+            tracker.scrape_url(&hashes).unwrap(),
+            format!(
+                "http://example.com/scrape?info_hash={}&info_hash={}",
+                encoded,
+                "a".repeat(20)
+            )
+        );
+    }
+
+    #[test]
+    fn announce_test() {
+        let mut body = b"d8:completei5e10:incompletei3e8:intervali1800e5:peers12:".to_vec();
+        body.extend_from_slice(&[10, 0, 0, 1, 0x1a, 0xe1, 192, 168, 1, 2, 0, 80]);
+        body.extend_from_slice(b"6:peers618:");
+        body.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0x1a, 0xe1]);
+        body.push(b'e');
+
+        let (url, handle) = server(ok(&body));
+        let tracker = HttpTracker::new(&url)
+            .unwrap()
+            .timeout(Duration::from_secs(5));
+        let response = tracker.announce(&request()).unwrap();
+        assert_eq!(response.interval, Duration::from_secs(1800));
+        assert_eq!((response.seeders, response.leechers), (5, 3));
+        assert_eq!(
+            response.peers,
+            [
+                "10.0.0.1:6881".parse().unwrap(),
+                "192.168.1.2:80".parse().unwrap(),
+                "[::1]:6881".parse().unwrap()
+            ]
+        );
+
+        let request_line = handle.join().unwrap();
+        assert!(request_line
+            .starts_with("GET /announce?info_hash=%124abcdefghij%FF%00%20%2F~.-_&peer_id="));
+        assert!(request_line.ends_with(" HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn response_too_large_test() {
+        let body = alloc::vec![b'x'; super::MAX_RESPONSE_SIZE as usize];
+        let (url, handle) = server(ok(&body));
+        let tracker = HttpTracker::new(&url).unwrap();
+        assert!(matches!(
+            tracker.announce(&request()),
+            Err(HttpTrackerError::ResponseTooLarge)
+        ));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn dictionary_peers_test() {
+        let body = b"d8:intervali60e5:peersld2:ip8:10.0.0.27:peer id20:-XX0000-0000000000004:porti51413eed2:ip11:example.com4:porti1eed2:ip3:::14:porti2eeee";
+        let tracker = HttpTracker::new("https://tracker.example.com/announce").unwrap();
+        let response = tracker
+            .announce_with(&request(), |url| {
+                assert!(url.starts_with("https://"));
+                Ok(body.to_vec())
+            })
+            .unwrap();
+        assert_eq!((response.seeders, response.leechers), (0, 0));
+        assert_eq!(
+            response.peers,
+            [
+                "10.0.0.2:51413".parse().unwrap(),
+                "[::1]:2".parse().unwrap()
+            ]
+        );
+
+        // Without a fetch function, HTTPS is not available
+        assert!(matches!(
+            tracker.announce(&request()),
+            Err(HttpTrackerError::HttpsUnsupported)
+        ));
+    }
+
+    #[test]
+    fn scrape_test() {
+        let known = InfoHash::new([b'a'; 20]);
+        let unknown = InfoHash::new([b'b'; 20]);
+        let body = format!(
+            "d5:filesd20:{}d8:completei4e10:downloadedi40e10:incompletei2eeee",
+            "a".repeat(20)
+        );
+        let chunked = format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+            10,
+            &body[..10],
+            body.len() - 10,
+            &body[10..]
+        );
+
+        let (url, handle) = server(chunked.into_bytes());
+        let stats = HttpTracker::new(&url)
+            .unwrap()
+            .scrape(&[known, unknown])
+            .unwrap();
+        assert_eq!(
+            stats,
+            [
+                ScrapeStats {
+                    seeders: 4,
+                    completed: 40,
+                    leechers: 2
+                },
+                ScrapeStats::default()
+            ]
+        );
+        assert!(handle
+            .join()
+            .unwrap()
+            .starts_with("GET /scrape?info_hash=aaaa"));
+
+        let tracker = HttpTracker::new("http://example.com/tracker").unwrap();
+        assert!(matches!(
+            tracker.scrape(&[known]),
+            Err(HttpTrackerError::ScrapeUnsupported)
+        ));
+    }
+
+    #[test]
+    fn error_test() {
+        let (url, handle) = server(ok(b"d14:failure reason17:torrent not founde"));
+        let err = HttpTracker::new(&url)
+            .unwrap()
+            .announce(&request())
+            .unwrap_err();
+        assert!(matches!(&err, HttpTrackerError::Tracker(m) if m == "torrent not found"));
+        handle.join().unwrap();
+
+        let (url, handle) = server(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_vec());
+        let err = HttpTracker::new(&url)
+            .unwrap()
+            .announce(&request())
+            .unwrap_err();
+        assert!(matches!(err, HttpTrackerError::Status(404)));
+        handle.join().unwrap();
+
+        let (url, handle) = server(ok(b"not bencode"));
+        let err = HttpTracker::new(&url)
+            .unwrap()
+            .announce(&request())
+            .unwrap_err();
+        assert!(matches!(err, HttpTrackerError::InvalidResponse));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn http_trackers_test() {
+        let magnet = Magnet::new(SINTEL).unwrap();
+        assert_eq!(
+            magnet.http_trackers(),
+            [
+                "http://tracker.example.com/announce",
+                "https://tracker.example.org:443/announce"
+            ]
+        );
+        assert!(matches!(
+            HttpTracker::new("udp://example.com:80"),
+            Err(HttpTrackerError::InvalidUrl)
+        ));
+        assert!(matches!(
+            HttpTracker::new("http:///announce"),
+            Err(HttpTrackerError::InvalidUrl)
+        ));
+    }
+}
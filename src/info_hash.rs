@@ -0,0 +1,92 @@
+//! The BitTorrent v1 info hash identified by a magnet's `urn:btih` exact topic
+
+use core::fmt;
+
+use crate::encoding::{base32_decode, hex_decode, hex_encode};
+use crate::Magnet;
+
+/// A 20-byte BitTorrent info hash (the SHA-1 of a torrent's `info` dictionary)
+///
+/// Displays as 40 lowercase hex characters.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InfoHash([u8; 20]);
+
+impl InfoHash {
+    /// Wrap the raw bytes of an info hash
+    pub const fn new(bytes: [u8; 20]) -> Self {
+        InfoHash(bytes)
+    }
+
+    /// Parse an info hash written as 40 hex characters or 32 base32 characters, as allowed in
+    /// `urn:btih` exact topics
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.len() {
+            40 => hex_decode(text).map(InfoHash),
+            32 => base32_decode(text).map(InfoHash),
+            _ => None,
+        }
+    }
+
+    /// Get the raw bytes of the info hash
+    pub const fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+}
+
+impl fmt::Display for InfoHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex_encode(&self.0))
+    }
+}
+
+impl Magnet {
+    /// Get the BitTorrent info hash, if the exact topic is a valid `urn:btih` hash
+    pub fn info_hash(&self) -> Option<InfoHash> {
+        if !self.hash_type()?.eq_ignore_ascii_case("btih") {
+            return None;
+        }
+        InfoHash::parse(self.hash()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InfoHash;
+    use crate::Magnet;
+    use alloc::string::ToString;
+
+    const HEX: &str = "08ada5a7a6183aae1e09d831df6748d566095a10";
+
+    #[test]
+    fn parse_test() {
+        let hash = InfoHash::parse(HEX).unwrap();
+        assert_eq!(hash.as_bytes()[..3], [0x08, 0xad, 0xa5]);
+        assert_eq!(hash.to_string(), HEX);
+        assert_eq!(InfoHash::parse(&HEX.to_uppercase()), Some(hash));
+
+        // The same hash in base32
+        assert_eq!(
+            InfoHash::parse("BCW2LJ5GDA5K4HQJ3AY56Z2I2VTASWQQ"),
+            Some(hash)
+        );
+
+        assert_eq!(InfoHash::parse(&HEX[1..]), None);
+        assert_eq!(InfoHash::parse("zz"), None);
+    }
+
+    #[test]
+    fn magnet_info_hash_test() {
+        let magnet = Magnet::new(&alloc::format!("magnet:?xt=urn:btih:{}&dn=Sintel", HEX)).unwrap();
+        assert_eq!(
+            magnet.info_hash().map(|h| h.to_string()).as_deref(),
+            Some(HEX)
+        );
+
+        let magnet =
+            Magnet::new("magnet:?xt=urn:sha1:2aae6c35c94fcfb415dbe95f408b9ce91ee846ed").unwrap();
+        assert_eq!(magnet.info_hash(), None);
+
+        let magnet = Magnet::new("magnet:?dn=nothing").unwrap();
+        assert_eq!(magnet.info_hash(), None);
+    }
+}
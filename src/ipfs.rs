@@ -0,0 +1,629 @@
+//! IPFS content identifiers (CIDs) and `ipfs://` URIs, and carrying them in magnets
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::encoding::{
+    base32_decode_vec, base32_encode, base_x_decode, base_x_encode, hex_decode_vec, hex_encode,
+    percent_decode, percent_encode, read_varint, write_varint,
+};
+use crate::{Magnet, MagnetBuilder};
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE36_ALPHABET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Multiformats varints are at most 9 bytes long
+const MULTIFORMAT_VARINT_LEN: usize = 9;
+
+/// Longest CID text accepted: a base16 CID with a 128-byte digest (the largest identity hash
+/// IPFS implementations accept) and its prefixes. Longer text is rejected before the base
+/// conversions, which take time quadratic in its length.
+const MAX_CID_TEXT_LEN: usize = 2 * (128 + 32) + 1;
+
+/// The multibase encodings a CID can be written in
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum Multibase {
+    /// Lowercase hexadecimal, prefixed with `f`
+    Base16,
+    /// Lowercase unpadded RFC 4648 base32, prefixed with `b` (the default for CIDv1)
+    Base32,
+    /// Lowercase base36, prefixed with `k` (used for IPNS keys in subdomains)
+    Base36,
+    /// Bitcoin's base58, prefixed with `z`
+    Base58Btc,
+}
+
+/// An IPFS content identifier: a multihash of some content, tagged with the codec of that
+/// content
+///
+/// Version 0 CIDs are the bare base58 SHA-256 multihashes of dag-pb content (`Qm...`); version
+/// 1 CIDs add a version, a codec and a multibase prefix (`bafy...`).
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Cid {
+    version: u64,
+    codec: u64,
+    hash_code: u64,
+    digest: Vec<u8>,
+}
+
+impl Cid {
+    /// The multicodec of raw bytes
+    pub const RAW: u64 = 0x55;
+    /// The multicodec of dag-pb (UnixFS) nodes, the codec of every v0 CID
+    pub const DAG_PB: u64 = 0x70;
+    /// The multicodec of libp2p public keys, used by IPNS names
+    pub const LIBP2P_KEY: u64 = 0x72;
+    /// The multihash code of SHA-256
+    pub const SHA2_256: u64 = 0x12;
+
+    /// Make a version 1 CID from a codec, a multihash code and a digest
+    pub fn new_v1(codec: u64, hash_code: u64, digest: &[u8]) -> Self {
+        Cid {
+            version: 1,
+            codec,
+            hash_code,
+            digest: digest.to_vec(),
+        }
+    }
+
+    /// Make the version 0 CID of a SHA-256 digest of dag-pb content
+    pub fn new_v0(digest: [u8; 32]) -> Self {
+        Cid {
+            version: 0,
+            codec: Self::DAG_PB,
+            hash_code: Self::SHA2_256,
+            digest: digest.to_vec(),
+        }
+    }
+
+    /// Parse a CID written as a v0 base58 string or as a v1 multibase string (base16, base32,
+    /// base36 or base58btc)
+    pub fn parse(text: &str) -> Option<Self> {
+        if text.len() > MAX_CID_TEXT_LEN {
+            return None;
+        }
+        if text.len() == 46 && text.starts_with("Qm") {
+            return Self::from_bytes(&base_x_decode(text, BASE58_ALPHABET)?);
+        }
+
+        let mut chars = text.chars();
+        let bytes = match chars.next()? {
+            'f' | 'F' => hex_decode_vec(chars.as_str())?,
+            'b' | 'B' => base32_decode_vec(chars.as_str())?,
+            'k' | 'K' => base_x_decode(&chars.as_str().to_ascii_lowercase(), BASE36_ALPHABET)?,
+            'z' => base_x_decode(chars.as_str(), BASE58_ALPHABET)?,
+            _ => return None,
+        };
+        // A multibase string always holds a v1 CID, never the bare multihash of a v0 one
+        Self::from_bytes(&bytes).filter(|cid| cid.version == 1)
+    }
+
+    /// Parse the binary form of a CID
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() == 34 && bytes[0] == 0x12 && bytes[1] == 0x20 {
+            return Some(Self::new_v0(bytes[2..].try_into().ok()?));
+        }
+
+        let mut rest = bytes;
+        let version = read_varint(&mut rest, MULTIFORMAT_VARINT_LEN)?;
+        if version != 1 {
+            return None;
+        }
+        let codec = read_varint(&mut rest, MULTIFORMAT_VARINT_LEN)?;
+        let hash_code = read_varint(&mut rest, MULTIFORMAT_VARINT_LEN)?;
+        let length = read_varint(&mut rest, MULTIFORMAT_VARINT_LEN)?;
+        if rest.len() as u64 != length {
+            return None;
+        }
+        Some(Self::new_v1(codec, hash_code, rest))
+    }
+
+    /// Get the binary form of the CID
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.digest.len() + 8);
+        if self.version == 1 {
+            write_varint(&mut out, 1);
+            write_varint(&mut out, self.codec);
+        }
+        write_varint(&mut out, self.hash_code);
+        write_varint(&mut out, self.digest.len() as u64);
+        out.extend_from_slice(&self.digest);
+        out
+    }
+
+    /// Get the version of the CID (0 or 1)
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Get the multicodec of the content, like [`Cid::RAW`] or [`Cid::DAG_PB`]
+    pub fn codec(&self) -> u64 {
+        self.codec
+    }
+
+    /// Get the multihash code of the digest, like [`Cid::SHA2_256`]
+    pub fn hash_code(&self) -> u64 {
+        self.hash_code
+    }
+
+    /// Get the digest of the content
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// Get the version 1 form of the CID, which names the same content
+    pub fn to_v1(&self) -> Self {
+        Self::new_v1(self.codec, self.hash_code, &self.digest)
+    }
+
+    /// Write the CID in the given multibase
+    ///
+    /// A v0 CID has no multibase form, so it is converted to v1 first.
+    pub fn encode(&self, base: Multibase) -> String {
+        let bytes = self.to_v1().to_bytes();
+        match base {
+            Multibase::Base16 => format!("f{}", hex_encode(&bytes)),
+            Multibase::Base32 => format!("b{}", base32_encode(&bytes).to_ascii_lowercase()),
+            Multibase::Base36 => format!("k{}", base_x_encode(&bytes, BASE36_ALPHABET)),
+            Multibase::Base58Btc => format!("z{}", base_x_encode(&bytes, BASE58_ALPHABET)),
+        }
+    }
+}
+
+impl fmt::Display for Cid {
+    /// Writes v0 CIDs in base58 and v1 CIDs in base32, as IPFS does
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.version == 0 {
+            f.write_str(&base_x_encode(&self.to_bytes(), BASE58_ALPHABET))
+        } else {
+            f.write_str(&self.encode(Multibase::Base32))
+        }
+    }
+}
+
+/// Whether an [`IpfsUri`] names immutable content or a mutable IPNS name
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum IpfsNamespace {
+    /// `ipfs://`: content addressed by a CID
+    Ipfs,
+    /// `ipns://`: an IPNS key or a DNSLink domain that points to content
+    Ipns,
+}
+
+impl IpfsNamespace {
+    fn as_str(self) -> &'static str {
+        match self {
+            IpfsNamespace::Ipfs => "ipfs",
+            IpfsNamespace::Ipns => "ipns",
+        }
+    }
+}
+
+/// A path into IPFS: a root CID (or IPNS name) and a path below it
+///
+/// Parses `ipfs://` and `ipns://` URIs, `/ipfs/...` paths, and the HTTP URLs of path gateways
+/// (`https://gateway/ipfs/<cid>/...`) and subdomain gateways (`https://<cid>.ipfs.gateway/...`).
+/// Displays as an `ipfs://` or `ipns://` URI.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct IpfsUri {
+    namespace: IpfsNamespace,
+    root: String,
+    path: String,
+}
+
+impl IpfsUri {
+    /// Make a URI for `path` (empty, or starting with `/`) below the content of `cid`
+    pub fn new(cid: &Cid, path: &str) -> Self {
+        IpfsUri {
+            namespace: IpfsNamespace::Ipfs,
+            root: cid.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    /// Parse an IPFS URI, path or gateway URL
+    ///
+    /// The root of an `ipfs` URI must be a valid CID.
+    pub fn parse(text: &str) -> Option<Self> {
+        if let Some((scheme, rest)) = text.split_once("://") {
+            match scheme.to_ascii_lowercase().as_str() {
+                "ipfs" => Self::from_parts(IpfsNamespace::Ipfs, rest),
+                "ipns" => Self::from_parts(IpfsNamespace::Ipns, rest),
+                "http" | "https" => Self::parse_gateway_url(rest),
+                _ => None,
+            }
+        } else {
+            Self::parse_path(text)
+        }
+    }
+
+    /// Parse `/ipfs/<root>/...` or `/ipns/<root>/...`
+    fn parse_path(path: &str) -> Option<Self> {
+        if let Some(rest) = path.strip_prefix("/ipfs/") {
+            Self::from_parts(IpfsNamespace::Ipfs, rest)
+        } else if let Some(rest) = path.strip_prefix("/ipns/") {
+            Self::from_parts(IpfsNamespace::Ipns, rest)
+        } else {
+            None
+        }
+    }
+
+    /// Parse the part of a gateway URL after `http://` or `https://`
+    fn parse_gateway_url(rest: &str) -> Option<Self> {
+        let (host, path) = rest.find('/').map_or((rest, ""), |i| rest.split_at(i));
+        if let Some(uri) = Self::parse_path(path) {
+            return Some(uri);
+        }
+
+        let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+        let mut labels = host.splitn(3, '.');
+        let root = labels.next()?;
+        let namespace = match labels.next()? {
+            "ipfs" => IpfsNamespace::Ipfs,
+            "ipns" => IpfsNamespace::Ipns,
+            _ => return None,
+        };
+        labels.next()?;
+        // DNSLink names are written in a single label, with `.` as `-` and `-` as `--`
+        let root = match namespace {
+            IpfsNamespace::Ipns if root.contains('-') => root
+                .replace("--", "\0")
+                .replace('-', ".")
+                .replace('\0', "-"),
+            _ => root.to_string(),
+        };
+        Self::from_parts(namespace, &format!("{}{}", root, path))
+    }
+
+    /// Build a URI from its namespace and `<root>/<path>`
+    fn from_parts(namespace: IpfsNamespace, rest: &str) -> Option<Self> {
+        let (root, path) = rest
+            .find(['/', '?', '#'])
+            .map_or((rest, ""), |i| rest.split_at(i));
+        if root.is_empty() {
+            return None;
+        }
+        let root = match namespace {
+            IpfsNamespace::Ipfs => Cid::parse(root)?.to_string(),
+            IpfsNamespace::Ipns => root.to_string(),
+        };
+        Some(IpfsUri {
+            namespace,
+            root,
+            path: path.to_string(),
+        })
+    }
+
+    /// Get the namespace of the URI
+    pub fn namespace(&self) -> IpfsNamespace {
+        self.namespace
+    }
+
+    /// Get the root of the URI: a CID, or an IPNS key or DNSLink domain
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Get the path below the root, empty or starting with `/` (any query is kept)
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Get the CID of the root of an `ipfs` URI
+    pub fn cid(&self) -> Option<Cid> {
+        match self.namespace {
+            IpfsNamespace::Ipfs => Cid::parse(&self.root),
+            IpfsNamespace::Ipns => None,
+        }
+    }
+
+    /// Get the URL of the content on a path gateway, like `https://ipfs.io`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use magnet_url::IpfsUri;
+    ///
+    /// let uri = IpfsUri::parse("ipfs://QmY7Yh4UquoXHLPFo2XbhXkhBvFoPwmQUSa92pxnxjQuPU/a.txt").unwrap();
+    /// assert_eq!(
+    ///     uri.to_gateway_url("https://ipfs.io/"),
+    ///     "https://ipfs.io/ipfs/QmY7Yh4UquoXHLPFo2XbhXkhBvFoPwmQUSa92pxnxjQuPU/a.txt"
+    /// );
+    /// ```
+    pub fn to_gateway_url(&self, gateway: &str) -> String {
+        format!(
+            "{}/{}/{}{}",
+            gateway.trim_end_matches('/'),
+            self.namespace.as_str(),
+            self.root,
+            self.path
+        )
+    }
+
+    /// Get the URL of the content on a subdomain gateway, like `https://dweb.link`
+    ///
+    /// Hostnames are case-insensitive, so CIDs are written in base32, IPNS keys (libp2p peer IDs
+    /// like `12D3KooW...`, or CIDs) as base36 `libp2p-key` CIDs, and DNSLink domains have their
+    /// `.` and `-` escaped. Returns `None` if `gateway` is not an `http(s)://host` URL.
+    pub fn to_subdomain_gateway_url(&self, gateway: &str) -> Option<String> {
+        let (scheme, host) = gateway.trim_end_matches('/').split_once("://")?;
+        if host.is_empty() || host.contains('/') {
+            return None;
+        }
+        let label = match self.namespace {
+            IpfsNamespace::Ipfs => Cid::parse(&self.root)?.encode(Multibase::Base32),
+            IpfsNamespace::Ipns => match parse_peer_id(&self.root) {
+                Some(key) => key.encode(Multibase::Base36),
+                None => self.root.replace('-', "--").replace('.', "-"),
+            },
+        };
+        Some(format!(
+            "{}://{}.{}.{}{}",
+            scheme,
+            label,
+            self.namespace.as_str(),
+            host,
+            self.path
+        ))
+    }
+}
+
+/// Parse the name of an IPNS key, a libp2p peer ID, into a `libp2p-key` CID
+///
+/// Peer IDs are written as bare base58 multihashes of the public key (`12D3KooW...` for the
+/// identity hash of an Ed25519 key, `Qm...` for the SHA-256 of an RSA key) or as CIDs.
+fn parse_peer_id(text: &str) -> Option<Cid> {
+    let cid = if text.starts_with('1') && text.len() <= MAX_CID_TEXT_LEN {
+        let bytes = base_x_decode(text, BASE58_ALPHABET)?;
+        let mut rest = bytes.as_slice();
+        let hash_code = read_varint(&mut rest, MULTIFORMAT_VARINT_LEN)?;
+        let length = read_varint(&mut rest, MULTIFORMAT_VARINT_LEN)?;
+        if rest.len() as u64 != length {
+            return None;
+        }
+        Cid::new_v1(Cid::LIBP2P_KEY, hash_code, rest)
+    } else {
+        Cid::parse(text)?
+    };
+    Some(Cid::new_v1(Cid::LIBP2P_KEY, cid.hash_code, &cid.digest))
+}
+
+impl fmt::Display for IpfsUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}://{}{}",
+            self.namespace.as_str(),
+            self.root,
+            self.path
+        )
+    }
+}
+
+impl Magnet {
+    /// Get the IPFS CID of the content, from a valid `urn:ipfs` exact topic or else from an
+    /// `ipfs://` URI or IPFS gateway URL in `xs`
+    pub fn ipfs_cid(&self) -> Option<Cid> {
+        self.exact_topic("ipfs")
+            .and_then(Cid::parse)
+            .or_else(|| self.ipfs_source()?.cid())
+    }
+
+    /// Get the IPFS URI in `xs`, written as an `ipfs://` or `ipns://` URI or a gateway URL
+    pub fn ipfs_source(&self) -> Option<IpfsUri> {
+        IpfsUri::parse(&percent_decode(self.source()?)?)
+    }
+}
+
+impl MagnetBuilder {
+    /// Add the CID of the content as a `urn:ipfs` exact topic
+    pub fn add_ipfs_cid(self, cid: &Cid) -> Self {
+        self.add_exact_topic("ipfs", &cid.to_string())
+    }
+
+    /// Set `xs` to an `ipfs://` or `ipns://` URI
+    pub fn ipfs_source(self, uri: &IpfsUri) -> Self {
+        self.source(&percent_encode(&uri.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256::Sha256;
+
+    const V0: &str = "QmY7Yh4UquoXHLPFo2XbhXkhBvFoPwmQUSa92pxnxjQuPU";
+    const V1: &str = "bafybeierhgbz4zp2x2u67urqrgfnrnlukciupzenpqpipiz5nwtq7uxpx4";
+
+    #[test]
+    fn parse_v0_test() {
+        let cid = Cid::parse(V0).unwrap();
+        assert_eq!(cid.version(), 0);
+        assert_eq!(cid.codec(), Cid::DAG_PB);
+        assert_eq!(cid.hash_code(), Cid::SHA2_256);
+        assert_eq!(cid.digest().len(), 32);
+        assert_eq!(cid.to_string(), V0);
+        assert_eq!(cid.to_bytes().len(), 34);
+        assert_eq!(Cid::from_bytes(&cid.to_bytes()), Some(cid));
+    }
+
+    #[test]
+    fn parse_multibase_test() {
+        let v0 = Cid::parse(V0).unwrap();
+        let v1 = v0.to_v1();
+        assert_eq!(v1.to_string(), V1);
+
+        let spellings = [
+            V1,
+            "BAFYBEIERHGBZ4ZP2X2U67URQRGFNRNLUKCIUPZENPQPIPIZ5NWTQ7UXPX4",
+            "k2jmtxuzexuhsb3h9uxar34ob4bqu7xjoi2qmk2h2syu9cepr2mwt9z3",
+            "zdj7WfCo4VYhPH8A3hBXmVDZubFp8TF7VBYLkyfhdMTnAyoZQ",
+            "f017012209139839e65fabea9efd230898ad8b574509147e48d7c1e87a33d6da70fd2efbf",
+        ];
+        for text in spellings {
+            assert_eq!(Cid::parse(text).as_ref(), Some(&v1), "{}", text);
+        }
+        assert_eq!(v1.encode(Multibase::Base36), spellings[2]);
+        assert_eq!(v1.encode(Multibase::Base58Btc), spellings[3]);
+        assert_eq!(v1.encode(Multibase::Base16), spellings[4]);
+        assert_eq!(v0.encode(Multibase::Base32), V1);
+    }
+
+    #[test]
+    fn raw_cid_test() {
+        let cid = Cid::new_v1(Cid::RAW, Cid::SHA2_256, &Sha256::digest(b"hello"));
+        assert_eq!(
+            cid.to_string(),
+            "bafkreibm6jg3ux5qumhcn2b3flc3tyu6dmlb4xa7u5bf44yegnrjhc4yeq"
+        );
+    }
+
+    #[test]
+    fn invalid_cid_test() {
+        assert_eq!(Cid::parse(""), None);
+        assert_eq!(
+            Cid::parse("QmY7Yh4UquoXHLPFo2XbhXkhBvFoPwmQUSa92pxnxjQuP"),
+            None
+        );
+        assert_eq!(Cid::parse("x1234"), None);
+        // A codec written as a 10-byte varint, longer than multiformats allow
+        let mut bytes = alloc::vec![0x01];
+        bytes.extend([0x80; 9]);
+        bytes.extend([0x01, 0x12, 0x00]);
+        assert_eq!(Cid::from_bytes(&bytes), None);
+        // Too long to be a CID, and rejected without decoding it
+        assert_eq!(Cid::parse(&format!("z{}", "2".repeat(60_000))), None);
+        assert_eq!(Cid::parse(&V1[..V1.len() - 2]), None);
+        // A v0 multihash is not a valid v1 multibase CID
+        assert_eq!(
+            Cid::parse("f12209139839e65fabea9efd230898ad8b574509147e48d7c1e87a33d6da70fd2efbf"),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_uri_test() {
+        let expected = IpfsUri::parse(&format!("ipfs://{}/dir/a.txt", V1)).unwrap();
+        assert_eq!(expected.namespace(), IpfsNamespace::Ipfs);
+        assert_eq!(expected.root(), V1);
+        assert_eq!(expected.path(), "/dir/a.txt");
+        assert_eq!(expected.to_string(), format!("ipfs://{}/dir/a.txt", V1));
+
+        let spellings = [
+            format!("/ipfs/{}/dir/a.txt", V1),
+            format!("https://ipfs.io/ipfs/{}/dir/a.txt", V1),
+            format!("https://{}.ipfs.dweb.link/dir/a.txt", V1),
+        ];
+        for text in &spellings {
+            assert_eq!(IpfsUri::parse(text).as_ref(), Some(&expected), "{}", text);
+        }
+
+        let v0 = IpfsUri::parse(&format!("ipfs://{}", V0)).unwrap();
+        assert_eq!(v0.root(), V0);
+        assert_eq!(v0.path(), "");
+        assert_eq!(v0.cid(), Cid::parse(V0));
+
+        assert_eq!(IpfsUri::parse("ipfs://not-a-cid"), None);
+        assert_eq!(IpfsUri::parse("https://example.com/index.html"), None);
+        assert_eq!(IpfsUri::parse("ftp://ipfs.io/ipfs/x"), None);
+    }
+
+    #[test]
+    fn ipns_test() {
+        let uri =
+            IpfsUri::parse("https://en-wikipedia--on--ipfs-org.ipns.dweb.link/wiki/").unwrap();
+        assert_eq!(uri.namespace(), IpfsNamespace::Ipns);
+        assert_eq!(uri.root(), "en.wikipedia-on-ipfs.org");
+        assert_eq!(uri.cid(), None);
+        assert_eq!(uri.to_string(), "ipns://en.wikipedia-on-ipfs.org/wiki/");
+        assert_eq!(
+            uri.to_subdomain_gateway_url("https://dweb.link").as_deref(),
+            Some("https://en-wikipedia--on--ipfs-org.ipns.dweb.link/wiki/")
+        );
+        assert_eq!(
+            uri.to_gateway_url("https://ipfs.io"),
+            "https://ipfs.io/ipns/en.wikipedia-on-ipfs.org/wiki/"
+        );
+    }
+
+    #[test]
+    fn ipns_key_test() {
+        // The identity multihash of an Ed25519 public key
+        let mut key = alloc::vec![0x00, 0x24, 0x08, 0x01, 0x12, 0x20];
+        key.extend(1..=32u8);
+        let peer_id = base_x_encode(&key, BASE58_ALPHABET);
+        assert!(peer_id.starts_with("12D3KooW"));
+
+        let expected = Cid::new_v1(Cid::LIBP2P_KEY, 0x00, &key[2..]).encode(Multibase::Base36);
+        assert!(expected.starts_with("k51qzi5uqu5d"));
+        for root in [peer_id.clone(), expected.clone()] {
+            let uri = IpfsUri::parse(&format!("ipns://{}/a.txt", root)).unwrap();
+            assert_eq!(
+                uri.to_subdomain_gateway_url("https://dweb.link").as_deref(),
+                Some(format!("https://{}.ipns.dweb.link/a.txt", expected).as_str())
+            );
+        }
+
+        // RSA peer IDs are the SHA-256 multihash of the key, like a v0 CID
+        let uri = IpfsUri::parse(&format!("ipns://{}", V0)).unwrap();
+        let url = uri.to_subdomain_gateway_url("https://dweb.link").unwrap();
+        let label = url
+            .strip_prefix("https://")
+            .and_then(|rest| rest.strip_suffix(".ipns.dweb.link"))
+            .unwrap();
+        assert!(label.starts_with("k2k4r8"));
+        let cid = Cid::parse(label).unwrap();
+        assert_eq!(cid.codec(), Cid::LIBP2P_KEY);
+        assert_eq!(cid.digest(), Cid::parse(V0).unwrap().digest());
+    }
+
+    #[test]
+    fn gateway_url_test() {
+        let uri = IpfsUri::new(&Cid::parse(V0).unwrap(), "/a.txt");
+        assert_eq!(
+            uri.to_gateway_url("https://ipfs.io/"),
+            format!("https://ipfs.io/ipfs/{}/a.txt", V0)
+        );
+        assert_eq!(
+            uri.to_subdomain_gateway_url("https://dweb.link").as_deref(),
+            Some(format!("https://{}.ipfs.dweb.link/a.txt", V1).as_str())
+        );
+        assert_eq!(uri.to_subdomain_gateway_url("dweb.link"), None);
+        assert_eq!(
+            IpfsUri::parse(&uri.to_gateway_url("http://127.0.0.1:8080")),
+            Some(uri)
+        );
+    }
+
+    #[test]
+    fn magnet_test() {
+        let cid = Cid::parse(V1).unwrap();
+        let magnet = MagnetBuilder::new()
+            .hash_type("btih")
+            .hash("08ada5a7a6183aae1e09d831df6748d566095a10")
+            .add_ipfs_cid(&cid)
+            .build();
+        let magnet = Magnet::new(&magnet.to_string()).unwrap();
+        assert_eq!(magnet.exact_topic("ipfs"), Some(V1));
+        assert_eq!(magnet.ipfs_cid(), Some(cid.clone()));
+        assert_eq!(magnet.ipfs_source(), None);
+
+        let magnet = Magnet::new(&format!(
+            "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&xs=https%3A%2F%2Fipfs.io%2Fipfs%2F{}",
+            V1
+        ))
+        .unwrap();
+        assert_eq!(magnet.ipfs_cid(), Some(cid.clone()));
+
+        let uri = IpfsUri::new(&cid, "/video.mkv");
+        let magnet = MagnetBuilder::new().ipfs_source(&uri).build();
+        assert_eq!(
+            magnet.source(),
+            Some(format!("ipfs://{}/video.mkv", V1).as_str())
+        );
+        assert_eq!(magnet.ipfs_source(), Some(uri));
+        assert_eq!(magnet.ipfs_cid(), Some(cid));
+    }
+}
@@ -66,10 +66,100 @@
 //!     }
 //! }
 //! ```
-
-use std::error::Error;
-use std::fmt;
-use std::fmt::{Display, Formatter};
+//!
+//! ## `no_std` support
+//! The crate is `no_std` and only needs an allocator. The default `std` feature adds the
+//! `std::error::Error` implementation for `MagnetError` and the I/O based APIs like
+//! `MagnetReader`; disable default features to build without it.
+
+#![no_std]
+
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::{Display, Formatter};
+
+mod bencode;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod compact;
+#[cfg(feature = "std")]
+mod create;
+#[cfg(feature = "dht")]
+pub mod dht;
+mod ed2k;
+mod encoding;
+#[cfg(feature = "std")]
+mod file_hash;
+mod gnutella;
+#[cfg(feature = "std")]
+pub mod health;
+#[cfg(feature = "http-tracker")]
+pub mod http_tracker;
+mod info_hash;
+mod ipfs;
+#[cfg(feature = "lsd")]
+pub mod lsd;
+mod md4;
+#[cfg(feature = "metadata")]
+pub mod metadata;
+#[cfg(feature = "qr")]
+mod png;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "qr")]
+mod qr;
+#[cfg(any(
+    feature = "udp-tracker",
+    feature = "http-tracker",
+    feature = "dht",
+    feature = "lsd",
+    feature = "metadata"
+))]
+mod random;
+mod sanitize;
+#[cfg(any(feature = "udp-tracker", feature = "http-tracker"))]
+pub mod scrape;
+mod shorten;
+mod sha1;
+mod sha256;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(all(test, feature = "std"))]
+mod temp_dir;
+mod tiger;
+mod torrent;
+#[cfg(any(feature = "udp-tracker", feature = "http-tracker"))]
+mod tracker;
+mod tracker_list;
+#[cfg(feature = "udp-tracker")]
+pub mod udp_tracker;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use compact::{CompactError, TrackerDictionary};
+#[cfg(feature = "std")]
+pub use create::{CreateError, CreatedTorrent, TorrentCreator, TorrentVersion};
+pub use ed2k::{Ed2kError, Ed2kHasher, Ed2kLink};
+#[cfg(feature = "std")]
+pub use file_hash::FileHash;
+pub use gnutella::{Bitprint, Sha1Hash, TigerTreeHash};
+pub use info_hash::InfoHash;
+pub use ipfs::{Cid, IpfsNamespace, IpfsUri, Multibase};
+#[cfg(feature = "qr")]
+pub use qr::{QrCode, QrError, QrErrorCorrection};
+pub use sanitize::{BlockRule, SanitizeReport, Sanitizer};
+pub use shorten::{ShortenReport, Shortener};
+pub use tiger::TigerTree;
+pub use torrent::TorrentError;
+pub use tracker_list::{TrackerList, TrackerListError, Transport};
+#[cfg(feature = "std")]
+pub use stream::{MagnetLine, MagnetReader, ParallelMagnetReader};
 
 /// The various ways the Magnet parsing can fail
 #[derive(Debug, Clone, Hash, PartialEq)]
@@ -86,7 +176,8 @@ impl Display for MagnetError {
     }
 }
 
-impl Error for MagnetError {}
+#[cfg(feature = "std")]
+impl std::error::Error for MagnetError {}
 
 /// Represents a parsed magnet URL with all its components
 #[derive(Debug, Clone, Hash, PartialEq)]
@@ -97,12 +188,17 @@ pub struct Magnet {
     hash_type: Option<String>,
     /// (xt / exact topic) Torrent hash
     hash: Option<String>,
+    /// Further exact topics, for magnets naming the same file in several networks, as
+    /// (hash type, hash) pairs
+    extra_topics: Vec<(String, String)>,
     /// (xl): The size (in bytes) of the torrent
     length: Option<u64>,
     /// (xs): Download source for the file or the address of a P2P source
     source: Option<String>,
     /// Tracker URLs (tr) used to obtain resources for BitTorrent downloads
     trackers: Vec<String>,
+    /// (x.pe) Addresses (`host:port`) of peers to connect to directly
+    peers: Vec<String>,
     /// (kt) Search keywords to search for in P2P networks
     search_keywords: Option<String>,
     /// (ws) The payload data served over HTTP(S)
@@ -151,9 +247,11 @@ impl Magnet {
             display_name: None,
             hash_type: None,
             hash: None,
+            extra_topics: Vec::new(),
             length: None,
             source: None,
             trackers: Vec::new(),
+            peers: Vec::new(),
             search_keywords: None,
             web_seed: None,
             acceptable_source: None,
@@ -168,12 +266,12 @@ impl Magnet {
             if let Some((key, value)) = param.split_once('=') {
                 match key {
                     "dn" => magnet.display_name = Some(value.to_string()),
-                    "xt" => {
+                    // Several exact topics are given as repeated `xt` or as `xt.1`, `xt.2`, ...
+                    _ if key == "xt" || key.strip_prefix("xt.").is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())) => {
                         // Handle xt=urn:hash_type:hash format
                         if let Some(urn_part) = value.strip_prefix("urn:") {
-                            if let Some((hash_type, hash)) = urn_part.split_once(':') {
-                                magnet.hash_type = Some(hash_type.to_string());
-                                magnet.hash = Some(hash.to_string());
+                            if let Some((hash_type, hash)) = split_urn(urn_part) {
+                                magnet.add_exact_topic(hash_type, hash);
                             }
                         }
                     },
@@ -183,6 +281,7 @@ impl Magnet {
                         }
                     },
                     "tr" => magnet.trackers.push(value.to_string()),
+                    "x.pe" => magnet.peers.push(value.to_string()),
                     "kt" => magnet.search_keywords = Some(value.to_string()),
                     "ws" => magnet.web_seed = Some(value.to_string()),
                     "xs" => magnet.source = Some(value.to_string()),
@@ -202,15 +301,42 @@ impl Magnet {
     }
 
     /// Get the hash type used in the exact topic
+    ///
+    /// With several exact topics this is the first one's (before 3.0 it was the last one's), see
+    /// [`exact_topic`](Self::exact_topic) to look one up by type.
     pub fn hash_type(&self) -> Option<&str> {
         self.hash_type.as_deref()
     }
 
     /// Get the torrent hash
+    ///
+    /// With several exact topics this is the first one's, like [`hash_type`](Self::hash_type).
     pub fn hash(&self) -> Option<&str> {
         self.hash.as_deref()
     }
 
+    /// Get every exact topic as (hash type, hash) pairs, starting with `hash_type` and `hash`
+    pub fn exact_topics(&self) -> impl Iterator<Item = (&str, &str)> {
+        let first = self.hash.as_deref().map(|hash| (self.hash_type.as_deref().unwrap_or(""), hash));
+        first.into_iter().chain(self.extra_topics.iter().map(|(hash_type, hash)| (hash_type.as_str(), hash.as_str())))
+    }
+
+    /// Get the hash of the exact topic with the given hash type (like `btih` or `ed2k`), ignoring case
+    pub fn exact_topic(&self, hash_type: &str) -> Option<&str> {
+        self.exact_topics().find(|(t, _)| t.eq_ignore_ascii_case(hash_type)).map(|(_, hash)| hash)
+    }
+
+    /// Add an exact topic: the first one sets `hash_type` and `hash` (unless either is already
+    /// set), later ones are kept after it
+    fn add_exact_topic(&mut self, hash_type: &str, hash: &str) {
+        if self.hash.is_none() && self.hash_type.is_none() {
+            self.hash_type = Some(hash_type.to_string());
+            self.hash = Some(hash.to_string());
+        } else {
+            self.extra_topics.push((hash_type.to_string(), hash.to_string()));
+        }
+    }
+
     /// Get the size (in bytes) of the torrent
     pub fn length(&self) -> Option<u64> {
         self.length
@@ -226,6 +352,11 @@ impl Magnet {
         &self.trackers
     }
 
+    /// Get the addresses of peers to connect to directly
+    pub fn peers(&self) -> &[String] {
+        &self.peers
+    }
+
     /// Get the search keywords
     pub fn search_keywords(&self) -> Option<&str> {
         self.search_keywords.as_deref()
@@ -247,6 +378,20 @@ impl Magnet {
     }
 }
 
+/// Split the part of an exact topic after `urn:` into its hash type and hash
+///
+/// Only `tree:tiger` (and its `tree:tiger/` and `tree:tiger/1024` spellings) has a colon in the
+/// hash type, anything after the first colon of other types is the hash.
+fn split_urn(urn: &str) -> Option<(&str, &str)> {
+    for hash_type in ["tree:tiger", "tree:tiger/", "tree:tiger/1024"] {
+        let prefix = urn.get(..hash_type.len()).filter(|prefix| prefix.eq_ignore_ascii_case(hash_type));
+        if let Some(hash) = prefix.and_then(|_| urn[hash_type.len()..].strip_prefix(':')) {
+            return Some((&urn[..hash_type.len()], hash));
+        }
+    }
+    urn.split_once(':')
+}
+
 impl fmt::Display for Magnet {
     /// Generates a magnet URL string from the Magnet struct
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -263,6 +408,10 @@ impl fmt::Display for Magnet {
             );
         }
 
+        for (hash_type, hash) in &self.extra_topics {
+            magnet_string = format!("{}&xt=urn:{}:{}", magnet_string, hash_type, hash);
+        }
+
         // Helper function to add parameters
         let add_param = |name: &str, value: &Option<String>, base: &str| -> String {
             if let Some(val) = value {
@@ -284,6 +433,10 @@ impl fmt::Display for Magnet {
             magnet_string = format!("{}&tr={}", magnet_string, tracker);
         }
 
+        for peer in &self.peers {
+            magnet_string = format!("{}&x.pe={}", magnet_string, peer);
+        }
+
         // Add remaining optional parameters
         magnet_string = add_param("ws", &self.web_seed, &magnet_string);
         magnet_string = add_param("xs", &self.source, &magnet_string);
@@ -323,9 +476,11 @@ impl MagnetBuilder {
                 display_name: None,
                 hash_type: None,
                 hash: None,
+                extra_topics: Vec::new(),
                 length: None,
                 source: None,
                 trackers: Vec::new(),
+                peers: Vec::new(),
                 search_keywords: None,
                 web_seed: None,
                 acceptable_source: None,
@@ -352,6 +507,14 @@ impl MagnetBuilder {
         self
     }
 
+    /// Add an exact topic, for a magnet naming the same file in several networks
+    ///
+    /// The first exact topic is the one set by `hash_type` and `hash`.
+    pub fn add_exact_topic(mut self, hash_type: &str, hash: &str) -> Self {
+        self.magnet.add_exact_topic(hash_type, hash);
+        self
+    }
+
     /// Set the size (in bytes) of the torrent
     pub fn length(mut self, length: u64) -> Self {
         self.magnet.length = Some(length);
@@ -376,6 +539,12 @@ impl MagnetBuilder {
         self
     }
 
+    /// Add the address (`host:port`) of a peer to connect to directly
+    pub fn add_peer(mut self, peer: &str) -> Self {
+        self.magnet.peers.push(peer.to_string());
+        self
+    }
+
     /// Set the search keywords
     pub fn search_keywords(mut self, keywords: &str) -> Self {
         self.magnet.search_keywords = Some(keywords.to_string());
@@ -415,7 +584,7 @@ impl Default for MagnetBuilder {
 #[cfg(test)]
 mod tests {
     use crate::{Magnet, MagnetBuilder, MagnetError};
-    use std::error::Error;
+    use alloc::string::ToString;
 
     #[test]
     fn sintel_test() {
@@ -487,7 +656,8 @@ mod tests {
         assert_eq!(result, Err(MagnetError::NotAMagnetURL));
         let err = result.unwrap_err();
         assert_eq!(err.to_string(), "provided link is not a valid magnet URL");
-        assert!(err.source().is_none());
+        #[cfg(feature = "std")]
+        assert!(std::error::Error::source(&err).is_none());
     }
 
     #[test]
@@ -597,10 +767,82 @@ mod tests {
         assert_eq!(parsed_magnet.hash(), Some("1234567890abcdef1234567890abcdef12345678"));
         assert_eq!(parsed_magnet.length(), None);
         assert!(parsed_magnet.trackers().is_empty());
+        assert!(parsed_magnet.peers().is_empty());
         assert_eq!(parsed_magnet.search_keywords(), None);
         assert_eq!(parsed_magnet.web_seed(), None);
         assert_eq!(parsed_magnet.acceptable_source(), None);
         assert_eq!(parsed_magnet.manifest(), None);
         assert_eq!(parsed_magnet.source(), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn peers_test() {
+        let magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&x.pe=10.0.0.1:6881&x.pe=%5B%3A%3A1%5D%3A51413").unwrap();
+        assert_eq!(magnet.peers(), ["10.0.0.1:6881", "%5B%3A%3A1%5D%3A51413"]);
+
+        let magnet = MagnetBuilder::new()
+            .hash_type("btih")
+            .hash("08ada5a7a6183aae1e09d831df6748d566095a10")
+            .add_tracker("udp://tracker.example.com:6969")
+            .add_peer("peer.example.com:6881")
+            .build();
+        assert_eq!(
+            magnet.to_string(),
+            "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&tr=udp://tracker.example.com:6969&x.pe=peer.example.com:6881"
+        );
+        assert_eq!(Magnet::new(&magnet.to_string()).unwrap(), magnet);
+    }
+
+    #[test]
+    fn exact_topics_test() {
+        let magnet = Magnet::new("magnet:?xt=urn:ed2k:354b15e68fb8f36d7cd88ff94116cdc1&xl=10826029&dn=mediawiki-1.15.1.tar.gz&xt=urn:aich:W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW&xt.3=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10").unwrap();
+        assert_eq!(magnet.hash_type(), Some("ed2k"));
+        assert_eq!(magnet.hash(), Some("354b15e68fb8f36d7cd88ff94116cdc1"));
+        assert_eq!(
+            magnet.exact_topics().collect::<alloc::vec::Vec<_>>(),
+            [
+                ("ed2k", "354b15e68fb8f36d7cd88ff94116cdc1"),
+                ("aich", "W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW"),
+                ("btih", "08ada5a7a6183aae1e09d831df6748d566095a10"),
+            ]
+        );
+        assert_eq!(magnet.exact_topic("AICH"), Some("W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW"));
+        assert_eq!(magnet.exact_topic("sha1"), None);
+
+        let magnet = MagnetBuilder::new()
+            .add_exact_topic("ed2k", "354b15e68fb8f36d7cd88ff94116cdc1")
+            .add_exact_topic("aich", "W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW")
+            .length(10826029)
+            .build();
+        assert_eq!(
+            magnet.to_string(),
+            "magnet:?xt=urn:ed2k:354b15e68fb8f36d7cd88ff94116cdc1&xt=urn:aich:W4OIA4P5SDXGZ7SCWZHCMGRJLHSGS4DW&xl=10826029"
+        );
+        assert_eq!(Magnet::new(&magnet.to_string()).unwrap(), magnet);
+
+        // The first exact topic is the one returned by `hash`, not the last one as before 3.0
+        let magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&xt=urn:ed2k:354b15e68fb8f36d7cd88ff94116cdc1").unwrap();
+        assert_eq!(magnet.hash_type(), Some("btih"));
+        assert_eq!(magnet.hash(), Some("08ada5a7a6183aae1e09d831df6748d566095a10"));
+
+        // An explicit hash type is not replaced by an added exact topic
+        let magnet = MagnetBuilder::new()
+            .hash_type("btih")
+            .add_exact_topic("ed2k", "354b15e68fb8f36d7cd88ff94116cdc1")
+            .build();
+        assert_eq!(magnet.hash_type(), Some("btih"));
+        assert_eq!(magnet.hash(), None);
+        assert_eq!(magnet.exact_topic("ed2k"), Some("354b15e68fb8f36d7cd88ff94116cdc1"));
+
+        // Only the Tiger tree hash types contain colons
+        let magnet = Magnet::new("magnet:?xt=urn:x-custom:a:b&xt=urn:TREE:TIGER/1024:LWPNACQD&xt=urn:tree:tiger/:c").unwrap();
+        assert_eq!(
+            magnet.exact_topics().collect::<alloc::vec::Vec<_>>(),
+            [("x-custom", "a:b"), ("TREE:TIGER/1024", "LWPNACQD"), ("tree:tiger/", "c")]
+        );
+        assert_eq!(Magnet::new(&magnet.to_string()).unwrap(), magnet);
+
+        let magnet = Magnet::new("magnet:?dn=nothing").unwrap();
+        assert_eq!(magnet.exact_topics().count(), 0);
+    }
+}
@@ -0,0 +1,365 @@
+//! Local Service Discovery (BEP 14), enabled by the `lsd` feature
+//!
+//! [`Lsd`] finds peers on the local network without trackers or the DHT: it multicasts
+//! `BT-SEARCH` announces for the info hashes of the torrents it was given, and listens for the
+//! announces of other clients, as described in
+//! [BEP 14](https://www.bittorrent.org/beps/bep_0014.html).
+//!
+//! Only the IPv4 multicast group is supported.
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::Instant;
+
+use crate::random::random_u64;
+use crate::{InfoHash, Magnet};
+
+/// The IPv4 multicast group and port LSD announces are sent to
+pub const LSD_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(239, 192, 152, 143), 6771);
+
+/// Number of info hashes per announce, which keeps announces under the 1400 bytes BEP 14 allows
+const HASHES_PER_ANNOUNCE: usize = 20;
+
+/// An LSD client that announces torrents and reports the peers announcing them
+///
+/// BEP 14 asks clients not to announce a torrent more than once a minute, so call
+/// [`announce`](Lsd::announce) about every 5 minutes and when a torrent is added.
+///
+/// # Example
+///
+/// ```no_run
+/// use magnet_url::lsd::Lsd;
+/// use magnet_url::Magnet;
+///
+/// let magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10").unwrap();
+///
+/// let mut lsd = Lsd::bind(6881).unwrap().add_magnet(&magnet);
+/// lsd.announce().unwrap();
+/// loop {
+///     let (info_hash, peer) = lsd.recv().unwrap();
+///     println!("{} is shared by {}", info_hash, peer);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Lsd {
+    listener: UdpSocket,
+    sender: UdpSocket,
+    group: SocketAddrV4,
+    port: u16,
+    cookie: String,
+    info_hashes: Vec<InfoHash>,
+    timeout: Option<Duration>,
+    pending: VecDeque<(InfoHash, SocketAddr)>,
+}
+
+impl Lsd {
+    /// Join the LSD multicast group on the default interface, announcing peers on `port`
+    ///
+    /// This binds UDP port 6771, so it fails if another LSD client on this machine already uses
+    /// it.
+    pub fn bind(port: u16) -> io::Result<Self> {
+        Self::with_group(LSD_GROUP, Ipv4Addr::UNSPECIFIED, port)
+    }
+
+    /// Join a multicast group on the interface with the address `interface`, announcing peers on
+    /// `port`
+    ///
+    /// `Ipv4Addr::UNSPECIFIED` picks the default interface. A group port of 0 binds a random
+    /// port, which [`group`](Lsd::group) returns.
+    pub fn with_group(group: SocketAddrV4, interface: Ipv4Addr, port: u16) -> io::Result<Self> {
+        let listener = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, group.port()))?;
+        listener.join_multicast_v4(group.ip(), &interface)?;
+        let group = SocketAddrV4::new(*group.ip(), listener.local_addr()?.port());
+
+        // Multicasts leave through the interface of the address they are sent from
+        let sender = UdpSocket::bind((interface, 0))?;
+        sender.set_multicast_loop_v4(true)?;
+
+        Ok(Self {
+            listener,
+            sender,
+            group,
+            port,
+            cookie: format!("{:016x}", random_u64()),
+            info_hashes: Vec::new(),
+            timeout: None,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Add a torrent to announce and to report peers for
+    pub fn add_info_hash(mut self, info_hash: InfoHash) -> Self {
+        if !self.info_hashes.contains(&info_hash) {
+            self.info_hashes.push(info_hash);
+        }
+        self
+    }
+
+    /// Add the torrent of a magnet. Magnets without a `urn:btih` exact topic are ignored.
+    pub fn add_magnet(self, magnet: &Magnet) -> Self {
+        match magnet.info_hash() {
+            Some(info_hash) => self.add_info_hash(info_hash),
+            None => self,
+        }
+    }
+
+    /// Set how long [`recv`](Lsd::recv) waits for an announce. It waits forever by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Get the multicast group and port announces are sent to
+    pub fn group(&self) -> SocketAddrV4 {
+        self.group
+    }
+
+    /// Get the torrents being announced
+    pub fn info_hashes(&self) -> &[InfoHash] {
+        &self.info_hashes
+    }
+
+    /// Multicast an announce for every torrent
+    pub fn announce(&self) -> io::Result<()> {
+        for info_hashes in self.info_hashes.chunks(HASHES_PER_ANNOUNCE) {
+            let message = announce_message(self.group, self.port, info_hashes, &self.cookie);
+            self.sender.send_to(message.as_bytes(), self.group)?;
+        }
+        Ok(())
+    }
+
+    /// Wait for another client to announce one of our torrents, and get the info hash and the
+    /// address of that peer
+    ///
+    /// If no torrent was added, the peers of every announced torrent are returned. Our own
+    /// announces and invalid messages are skipped. Fails with `WouldBlock` or `TimedOut` when the
+    /// timeout runs out.
+    pub fn recv(&mut self) -> io::Result<(InfoHash, SocketAddr)> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut buf = [0u8; 1500];
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(io::ErrorKind::TimedOut.into());
+                }
+                self.listener.set_read_timeout(Some(remaining))?;
+            } else {
+                self.listener.set_read_timeout(None)?;
+            }
+
+            let (len, from) = self.listener.recv_from(&mut buf)?;
+            let Some(announce) = parse_announce(&buf[..len]) else {
+                continue;
+            };
+            if announce.cookie == Some(self.cookie.as_str()) {
+                continue;
+            }
+            let peer = SocketAddr::new(from.ip(), announce.port);
+            for info_hash in announce.info_hashes {
+                if self.info_hashes.is_empty() || self.info_hashes.contains(&info_hash) {
+                    self.pending.push_back((info_hash, peer));
+                }
+            }
+        }
+    }
+}
+
+/// A parsed `BT-SEARCH` announce
+#[derive(Debug, PartialEq, Eq)]
+struct Announce<'a> {
+    port: u16,
+    info_hashes: Vec<InfoHash>,
+    cookie: Option<&'a str>,
+}
+
+fn announce_message(
+    group: SocketAddrV4,
+    port: u16,
+    info_hashes: &[InfoHash],
+    cookie: &str,
+) -> String {
+    let mut message = format!(
+        "BT-SEARCH * HTTP/1.1\r\nHost: {}\r\nPort: {}\r\n",
+        group, port
+    );
+    for info_hash in info_hashes {
+        message.push_str(&format!("Infohash: {}\r\n", info_hash));
+    }
+    message.push_str(&format!("cookie: {}\r\n\r\n\r\n", cookie));
+    message
+}
+
+/// Parse an announce. Header names are case-insensitive, and unknown headers are ignored.
+fn parse_announce(data: &[u8]) -> Option<Announce<'_>> {
+    let text = core::str::from_utf8(data).ok()?;
+    let mut lines = text.split("\r\n");
+    if lines.next()? != "BT-SEARCH * HTTP/1.1" {
+        return None;
+    }
+
+    let mut port = None;
+    let mut info_hashes = Vec::new();
+    let mut cookie = None;
+    for line in lines.take_while(|line| !line.is_empty()) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("port") {
+            port = value.parse().ok().filter(|&port| port != 0);
+        } else if name.eq_ignore_ascii_case("infohash") {
+            // BEP 14 only allows hex, but parse base32 too like `urn:btih`
+            info_hashes.extend(InfoHash::parse(value));
+        } else if name.eq_ignore_ascii_case("cookie") {
+            cookie = Some(value);
+        }
+    }
+
+    if info_hashes.is_empty() {
+        return None;
+    }
+    Some(Announce {
+        port: port?,
+        info_hashes,
+        cookie,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{announce_message, parse_announce, Lsd, LSD_GROUP};
+    use crate::{InfoHash, Magnet};
+    use core::time::Duration;
+    use std::io;
+    use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+
+    const HASH: &str = "08ada5a7a6183aae1e09d831df6748d566095a10";
+    const OTHER: &str = "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed";
+
+    /// An LSD client on the loopback interface, with its own group port
+    fn loopback_lsd(port: u16) -> Lsd {
+        let group = SocketAddrV4::new(*LSD_GROUP.ip(), 0);
+        Lsd::with_group(group, Ipv4Addr::LOCALHOST, port)
+            .unwrap()
+            .timeout(Duration::from_secs(2))
+    }
+
+    #[test]
+    fn parse_announce_test() {
+        let hash = InfoHash::parse(HASH).unwrap();
+        let other = InfoHash::parse(OTHER).unwrap();
+        let message = announce_message(LSD_GROUP, 6881, &[hash, other], "abc");
+        assert_eq!(
+            message,
+            alloc::format!(
+                "BT-SEARCH * HTTP/1.1\r\nHost: 239.192.152.143:6771\r\nPort: 6881\r\n\
+                 Infohash: {}\r\nInfohash: {}\r\ncookie: abc\r\n\r\n\r\n",
+                HASH,
+                OTHER
+            )
+        );
+
+        let announce = parse_announce(message.as_bytes()).unwrap();
+        assert_eq!(announce.port, 6881);
+        assert_eq!(announce.info_hashes, [hash, other]);
+        assert_eq!(announce.cookie, Some("abc"));
+
+        // Other clients may use other casing and leave out the cookie
+        let announce = parse_announce(
+            b"BT-SEARCH * HTTP/1.1\r\nhost: 239.192.152.143:6771\r\nport:6882\r\n\
+              INFOHASH: 08ADA5A7A6183AAE1E09D831DF6748D566095A10\r\n\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(announce.port, 6882);
+        assert_eq!(announce.info_hashes, [hash]);
+        assert_eq!(announce.cookie, None);
+
+        // Missing port, bad info hash, other requests
+        let no_port = alloc::format!("BT-SEARCH * HTTP/1.1\r\nInfohash: {}\r\n\r\n", HASH);
+        assert_eq!(parse_announce(no_port.as_bytes()), None);
+        assert_eq!(
+            parse_announce(b"BT-SEARCH * HTTP/1.1\r\nPort: 1\r\nInfohash: 08ad\r\n\r\n"),
+            None
+        );
+        assert_eq!(parse_announce(b"GET / HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(parse_announce(&[0xff, 0xfe]), None);
+    }
+
+    #[test]
+    fn announce_test() {
+        let magnet = Magnet::new(&alloc::format!("magnet:?xt=urn:btih:{}", HASH)).unwrap();
+        let no_hash = Magnet::new("magnet:?dn=nothing").unwrap();
+        let lsd = loopback_lsd(6881).add_magnet(&magnet).add_magnet(&no_hash);
+        assert_eq!(lsd.info_hashes(), [magnet.info_hash().unwrap()]);
+        lsd.announce().unwrap();
+
+        // The announce loops back to our own listener
+        let mut buf = [0u8; 1500];
+        lsd.listener
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let (len, _) = lsd.listener.recv_from(&mut buf).unwrap();
+        let announce = parse_announce(&buf[..len]).unwrap();
+        assert_eq!(announce.port, 6881);
+        assert_eq!(announce.info_hashes, lsd.info_hashes());
+        assert_eq!(announce.cookie, Some(lsd.cookie.as_str()));
+    }
+
+    #[test]
+    fn recv_test() {
+        let hash = InfoHash::parse(HASH).unwrap();
+        let other = InfoHash::parse(OTHER).unwrap();
+        let mut lsd = loopback_lsd(6881).add_info_hash(hash);
+
+        // Another client on the loopback interface
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let send = |message: &str| {
+            peer.send_to(message.as_bytes(), lsd.group()).unwrap();
+        };
+        send(&announce_message(lsd.group(), 7000, &[hash], &lsd.cookie));
+        send("not an announce");
+        send(&announce_message(lsd.group(), 7001, &[other], "theirs"));
+        send(&announce_message(
+            lsd.group(),
+            7002,
+            &[other, hash],
+            "theirs",
+        ));
+
+        // Only the announce of our torrent from another client is reported
+        assert_eq!(
+            lsd.recv().unwrap(),
+            (hash, SocketAddr::from((Ipv4Addr::LOCALHOST, 7002)))
+        );
+        let err = lsd.recv().unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ));
+    }
+
+    #[test]
+    fn recv_all_test() {
+        let hash = InfoHash::parse(HASH).unwrap();
+        let other = InfoHash::parse(OTHER).unwrap();
+        let mut lsd = loopback_lsd(6881);
+
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let message = announce_message(lsd.group(), 7000, &[hash, other], "theirs");
+        peer.send_to(message.as_bytes(), lsd.group()).unwrap();
+
+        let from = SocketAddr::from((Ipv4Addr::LOCALHOST, 7000));
+        assert_eq!(lsd.recv().unwrap(), (hash, from));
+        assert_eq!(lsd.recv().unwrap(), (other, from));
+    }
+}
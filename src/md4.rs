@@ -0,0 +1,153 @@
+//! A small MD4 implementation, used to compute ed2k hashes
+
+/// Incremental MD4 hasher
+#[derive(Clone)]
+pub(crate) struct Md4 {
+    state: [u32; 4],
+    buffer: [u8; 64],
+    buffer_len: usize,
+    total_len: u64,
+}
+
+impl Md4 {
+    /// Create a new hasher
+    pub(crate) fn new() -> Self {
+        Self {
+            state: [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476],
+            buffer: [0; 64],
+            buffer_len: 0,
+            total_len: 0,
+        }
+    }
+
+    /// Hash a complete message in one go
+    #[cfg(test)]
+    pub(crate) fn digest(data: &[u8]) -> [u8; 16] {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finish()
+    }
+
+    /// Feed more data into the hasher
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        self.total_len = self.total_len.wrapping_add(data.len() as u64);
+
+        if self.buffer_len > 0 {
+            let take = (64 - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&data[..take]);
+            self.buffer_len += take;
+            data = &data[take..];
+
+            if self.buffer_len < 64 {
+                return;
+            }
+            let block = self.buffer;
+            self.compress(&block);
+            self.buffer_len = 0;
+        }
+
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            self.compress(block.try_into().unwrap());
+        }
+
+        let rest = blocks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffer_len = rest.len();
+    }
+
+    /// Finish hashing and return the digest
+    pub(crate) fn finish(mut self) -> [u8; 16] {
+        let bit_len = self.total_len.wrapping_mul(8);
+
+        let mut padding = [0u8; 72];
+        padding[0] = 0x80;
+        let pad_len = if self.buffer_len < 56 {
+            56 - self.buffer_len
+        } else {
+            120 - self.buffer_len
+        };
+        self.update(&padding[..pad_len]);
+        self.update(&bit_len.to_le_bytes());
+
+        let mut out = [0u8; 16];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        out
+    }
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut x = [0u32; 16];
+        for (word, chunk) in x.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        // Each round visits the words in its own order, with its own shifts
+        const ORDER: [[usize; 16]; 3] = [
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+            [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+            [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+        ];
+        const SHIFTS: [[u32; 4]; 3] = [[3, 7, 11, 19], [3, 5, 9, 13], [3, 9, 11, 15]];
+
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for (round, (order, shifts)) in ORDER.iter().zip(&SHIFTS).enumerate() {
+            for (i, &k) in order.iter().enumerate() {
+                let (f, constant) = match round {
+                    0 => ((b & c) | (!b & d), 0),
+                    1 => ((b & c) | (b & d) | (c & d), 0x5A827999),
+                    _ => (b ^ c ^ d, 0x6ED9EBA1),
+                };
+                let temp = a
+                    .wrapping_add(f)
+                    .wrapping_add(x[k])
+                    .wrapping_add(constant)
+                    .rotate_left(shifts[i % 4]);
+                a = d;
+                d = c;
+                c = b;
+                b = temp;
+            }
+        }
+
+        self.state[0] = self.state[0].wrapping_add(a);
+        self.state[1] = self.state[1].wrapping_add(b);
+        self.state[2] = self.state[2].wrapping_add(c);
+        self.state[3] = self.state[3].wrapping_add(d);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Md4;
+    use crate::encoding::hex_encode;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn known_digests_test() {
+        let cases: [(&[u8], &str); 5] = [
+            (b"", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+            (b"a", "bde52cb31de33e46245e05fbdbd6fb24"),
+            (b"abc", "a448017aaf21d8525fc10ae87aa6729d"),
+            (b"message digest", "d9130a8164549fe818874806e1c7014b"),
+            (
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "e33b4ddc9c38f2199c3e7b164fcc0536",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(hex_encode(&Md4::digest(input)), expected);
+        }
+    }
+
+    #[test]
+    fn incremental_matches_one_shot_test() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let mut hasher = Md4::new();
+        for chunk in data.chunks(37) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finish(), Md4::digest(&data));
+    }
+}
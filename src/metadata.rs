@@ -0,0 +1,626 @@
+//! Metadata download from peers (BEP 9), enabled by the `metadata` feature
+//!
+//! A magnet only names a torrent by its info hash. The `info` dictionary itself (file names,
+//! sizes and piece hashes) has to be downloaded from peers that already have it, using the
+//! extension protocol of [BEP 10](https://www.bittorrent.org/beps/bep_0010.html) and the
+//! `ut_metadata` extension of [BEP 9](https://www.bittorrent.org/beps/bep_0009.html). The
+//! downloaded dictionary is verified against the info hash before it is returned.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Instant;
+
+use crate::bencode::{self, Value};
+use crate::encoding::percent_decode;
+use crate::random::peer_id;
+use crate::sha1::Sha1;
+use crate::{InfoHash, Magnet};
+
+const PROTOCOL: &[u8; 19] = b"BitTorrent protocol";
+
+/// Message ID of extension protocol messages
+const EXTENDED: u8 = 20;
+
+/// Our ID for `ut_metadata` messages, announced in the extension handshake
+const UT_METADATA_ID: u8 = 3;
+
+/// Size of a metadata piece
+const PIECE_SIZE: usize = 16 * 1024;
+
+/// Largest metadata accepted from a peer
+const MAX_METADATA_SIZE: usize = 16 * 1024 * 1024;
+
+/// Largest message accepted from a peer (bitfields of huge torrents can exceed a piece)
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// The ways fetching metadata can fail
+#[derive(Debug)]
+pub enum MetadataError {
+    /// The magnet has no BitTorrent info hash
+    NoInfoHash,
+    /// No peers were given
+    NoPeers,
+    /// Connecting to the peer or talking to it failed
+    Io(io::Error),
+    /// The peer's handshake is invalid or for another torrent
+    Handshake,
+    /// The peer does not support the extension protocol or `ut_metadata`
+    Unsupported,
+    /// The peer does not have (or refused to send) the metadata
+    Rejected,
+    /// The peer sent a malformed message
+    InvalidMessage,
+    /// The downloaded metadata does not match the info hash
+    HashMismatch,
+    /// The peer did not send the whole metadata before the deadline
+    Deadline,
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataError::NoInfoHash => write!(f, "magnet has no BitTorrent info hash"),
+            MetadataError::NoPeers => write!(f, "no peers to fetch the metadata from"),
+            MetadataError::Io(err) => write!(f, "peer I/O error: {}", err),
+            MetadataError::Handshake => write!(f, "invalid handshake from peer"),
+            MetadataError::Unsupported => write!(f, "peer does not support metadata exchange"),
+            MetadataError::Rejected => write!(f, "peer rejected the metadata request"),
+            MetadataError::InvalidMessage => write!(f, "peer sent an invalid message"),
+            MetadataError::HashMismatch => write!(f, "metadata does not match the info hash"),
+            MetadataError::Deadline => write!(f, "peer did not send the metadata in time"),
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MetadataError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MetadataError {
+    fn from(err: io::Error) -> Self {
+        MetadataError::Io(err)
+    }
+}
+
+/// Downloads the `info` dictionary of a torrent from its peers
+///
+/// # Example
+///
+/// ```no_run
+/// use magnet_url::Magnet;
+/// use magnet_url::metadata::{torrent_file, MetadataFetcher};
+///
+/// let magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&x.pe=10.0.0.1:6881").unwrap();
+/// let info = MetadataFetcher::from_magnet(&magnet).unwrap().fetch(magnet.peer_addrs()).unwrap();
+/// std::fs::write("sintel.torrent", torrent_file(&magnet, &info)).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataFetcher {
+    info_hash: InfoHash,
+    peer_id: [u8; 20],
+    timeout: Duration,
+    deadline: Duration,
+}
+
+impl MetadataFetcher {
+    /// Create a fetcher for the torrent with this info hash, with a random peer ID
+    pub fn new(info_hash: InfoHash) -> Self {
+        Self {
+            info_hash,
+            peer_id: peer_id(),
+            timeout: Duration::from_secs(10),
+            deadline: Duration::from_secs(60),
+        }
+    }
+
+    /// Create a fetcher for a magnet's torrent
+    pub fn from_magnet(magnet: &Magnet) -> Result<Self, MetadataError> {
+        Ok(Self::new(
+            magnet.info_hash().ok_or(MetadataError::NoInfoHash)?,
+        ))
+    }
+
+    /// Set the 20-byte peer ID sent in the handshake, instead of a random one
+    pub fn peer_id(mut self, peer_id: [u8; 20]) -> Self {
+        self.peer_id = peer_id;
+        self
+    }
+
+    /// Set the timeout for connecting to a peer and for each read from it
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how long to wait for a single peer to send the whole metadata
+    ///
+    /// Unlike [`timeout`](Self::timeout), this also stops peers that keep the connection alive
+    /// without ever sending the metadata.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Fetch the metadata from the first of `peers` that can provide it
+    ///
+    /// If every peer fails, the error of the last one is returned.
+    pub fn fetch(
+        &self,
+        peers: impl IntoIterator<Item = SocketAddr>,
+    ) -> Result<Vec<u8>, MetadataError> {
+        let mut last_err = MetadataError::NoPeers;
+        for peer in peers {
+            match self.fetch_from(peer) {
+                Ok(metadata) => return Ok(metadata),
+                Err(err) => last_err = err,
+            }
+        }
+        Err(last_err)
+    }
+
+    /// Fetch the metadata from a single peer
+    pub fn fetch_from(&self, peer: SocketAddr) -> Result<Vec<u8>, MetadataError> {
+        let deadline = Instant::now() + self.deadline;
+        let mut stream = TcpStream::connect_timeout(&peer, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        // BitTorrent handshake, announcing support for the extension protocol
+        let mut handshake = Vec::with_capacity(68);
+        handshake.push(PROTOCOL.len() as u8);
+        handshake.extend_from_slice(PROTOCOL);
+        handshake.extend_from_slice(&[0, 0, 0, 0, 0, 0x10, 0, 0]);
+        handshake.extend_from_slice(self.info_hash.as_bytes());
+        handshake.extend_from_slice(&self.peer_id);
+        stream.write_all(&handshake)?;
+
+        let mut reply = [0u8; 68];
+        stream.read_exact(&mut reply)?;
+        if reply[0] as usize != PROTOCOL.len()
+            || reply[1..20] != PROTOCOL[..]
+            || reply[28..48] != self.info_hash.as_bytes()[..]
+        {
+            return Err(MetadataError::Handshake);
+        }
+        if reply[25] & 0x10 == 0 {
+            return Err(MetadataError::Unsupported);
+        }
+
+        let ours = Value::Dict(vec![(
+            &b"m"[..],
+            Value::Dict(vec![(
+                &b"ut_metadata"[..],
+                Value::Int(UT_METADATA_ID.into()),
+            )]),
+        )]);
+        send_extended(&mut stream, 0, &ours)?;
+
+        // Wait for the peer's extension handshake, skipping any other messages
+        let (peer_id, size) = loop {
+            let Some(payload) = read_extended(&mut stream, deadline, self.timeout)? else {
+                continue;
+            };
+            if payload.first() != Some(&0) {
+                continue;
+            }
+            let handshake =
+                bencode::decode(&payload[1..]).map_err(|_| MetadataError::InvalidMessage)?;
+            let id = handshake
+                .get("m")
+                .and_then(|m| m.get("ut_metadata"))
+                .and_then(Value::as_int)
+                .and_then(|id| u8::try_from(id).ok())
+                .filter(|&id| id != 0)
+                .ok_or(MetadataError::Unsupported)?;
+            let size = handshake
+                .get("metadata_size")
+                .and_then(Value::as_int)
+                .and_then(|size| usize::try_from(size).ok())
+                .filter(|&size| size > 0 && size <= MAX_METADATA_SIZE)
+                .ok_or(MetadataError::InvalidMessage)?;
+            break (id, size);
+        };
+
+        // Request every piece at once, then collect them in any order
+        let pieces = size.div_ceil(PIECE_SIZE);
+        for piece in 0..pieces {
+            let request = Value::Dict(vec![
+                (&b"msg_type"[..], Value::Int(0)),
+                (&b"piece"[..], Value::Int(piece as i64)),
+            ]);
+            send_extended(&mut stream, peer_id, &request)?;
+        }
+
+        let mut metadata = vec![0u8; size];
+        let mut received = vec![false; pieces];
+        let mut remaining = pieces;
+        while remaining > 0 {
+            let Some(payload) = read_extended(&mut stream, deadline, self.timeout)? else {
+                continue;
+            };
+            if payload.first() != Some(&UT_METADATA_ID) {
+                continue;
+            }
+            let (message, len) =
+                bencode::decode_prefix(&payload[1..]).map_err(|_| MetadataError::InvalidMessage)?;
+            match message.get("msg_type").and_then(Value::as_int) {
+                Some(1) => {}
+                Some(2) => return Err(MetadataError::Rejected),
+                _ => continue,
+            }
+
+            let piece = message
+                .get("piece")
+                .and_then(Value::as_int)
+                .and_then(|piece| usize::try_from(piece).ok())
+                .filter(|&piece| piece < pieces)
+                .ok_or(MetadataError::InvalidMessage)?;
+            let start = piece * PIECE_SIZE;
+            let end = (start + PIECE_SIZE).min(size);
+            let data = &payload[1 + len..];
+            if data.len() != end - start {
+                return Err(MetadataError::InvalidMessage);
+            }
+            metadata[start..end].copy_from_slice(data);
+            if !received[piece] {
+                received[piece] = true;
+                remaining -= 1;
+            }
+        }
+
+        if Sha1::digest(&metadata) != *self.info_hash.as_bytes() {
+            return Err(MetadataError::HashMismatch);
+        }
+        Ok(metadata)
+    }
+}
+
+impl Magnet {
+    /// Resolve the magnet's `x.pe` peer addresses, skipping those that cannot be resolved
+    pub fn peer_addrs(&self) -> Vec<SocketAddr> {
+        self.peers()
+            .iter()
+            .filter_map(|peer| percent_decode(peer))
+            .filter_map(|peer| peer.to_socket_addrs().ok())
+            .flatten()
+            .collect()
+    }
+}
+
+/// Build a `.torrent` file from downloaded metadata and the magnet's trackers
+pub fn torrent_file(magnet: &Magnet, info: &[u8]) -> Vec<u8> {
+    let trackers: Vec<_> = magnet
+        .trackers()
+        .iter()
+        .filter_map(|t| percent_decode(t))
+        .collect();
+
+    let mut torrent = b"d".to_vec();
+    if let Some(first) = trackers.first() {
+        let announce = Value::Bytes(first.as_bytes());
+        let tiers = trackers
+            .iter()
+            .map(|t| Value::List(vec![Value::Bytes(t.as_bytes())]))
+            .collect();
+        for (key, value) in [
+            ("announce", announce),
+            ("announce-list", Value::List(tiers)),
+        ] {
+            bencode::encode(&Value::Bytes(key.as_bytes()), &mut torrent);
+            bencode::encode(&value, &mut torrent);
+        }
+    }
+    // The info dictionary is copied as is, so its hash does not change
+    torrent.extend_from_slice(b"4:info");
+    torrent.extend_from_slice(info);
+    torrent.push(b'e');
+    torrent
+}
+
+/// Send an extension protocol message with a bencoded dictionary
+fn send_extended(stream: &mut TcpStream, id: u8, message: &Value<'_>) -> io::Result<()> {
+    let mut payload = vec![EXTENDED, id];
+    bencode::encode(message, &mut payload);
+
+    let mut packet = (payload.len() as u32).to_be_bytes().to_vec();
+    packet.extend_from_slice(&payload);
+    stream.write_all(&packet)
+}
+
+/// Read the next message, returning the payload of extension messages (starting with the
+/// extension ID) and `None` for any other message
+fn read_extended(
+    stream: &mut TcpStream,
+    deadline: Instant,
+    timeout: Duration,
+) -> Result<Option<Vec<u8>>, MetadataError> {
+    let mut len = [0u8; 4];
+    read_before(stream, &mut len, deadline, timeout)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(MetadataError::InvalidMessage);
+    }
+
+    let mut message = vec![0u8; len];
+    read_before(stream, &mut message, deadline, timeout)?;
+    match message.split_first() {
+        Some((&EXTENDED, payload)) => Ok(Some(payload.to_vec())),
+        _ => Ok(None),
+    }
+}
+
+/// Fill `buf` like `read_exact`, but fail once `deadline` passes, even if the peer keeps
+/// sending a byte at a time
+fn read_before(
+    stream: &mut TcpStream,
+    mut buf: &mut [u8],
+    deadline: Instant,
+    timeout: Duration,
+) -> Result<(), MetadataError> {
+    while !buf.is_empty() {
+        let remaining = deadline
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or(MetadataError::Deadline)?;
+        stream.set_read_timeout(Some(remaining.min(timeout)))?;
+        match stream.read(buf) {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            Ok(n) => buf = &mut buf[n..],
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) if Instant::now() >= deadline => {
+                return match err.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                        Err(MetadataError::Deadline)
+                    }
+                    _ => Err(err.into()),
+                }
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{torrent_file, MetadataError, MetadataFetcher, PIECE_SIZE};
+    use crate::bencode::{self, Value};
+    use crate::sha1::Sha1;
+    use crate::{InfoHash, Magnet};
+    use alloc::format;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::thread::{self, JoinHandle};
+    use std::time::Instant;
+
+    /// How the fake peer behaves
+    #[derive(Clone, Copy, PartialEq)]
+    enum Behavior {
+        Honest,
+        Corrupt,
+        Reject,
+        NoExtensions,
+        KeepAlive,
+    }
+
+    /// An info dictionary spanning three metadata pieces
+    fn metadata() -> Vec<u8> {
+        let pieces: Vec<u8> = (0..40_000u32).map(|i| (i % 251) as u8).collect();
+        let mut info = format!(
+            "d6:lengthi1e4:name4:test12:piece lengthi16384e6:pieces{}:",
+            pieces.len()
+        )
+        .into_bytes();
+        info.extend_from_slice(&pieces);
+        info.push(b'e');
+        info
+    }
+
+    fn info_hash(metadata: &[u8]) -> InfoHash {
+        InfoHash::new(Sha1::digest(metadata))
+    }
+
+    fn send(stream: &mut TcpStream, id: u8, message: &[u8]) {
+        let mut packet = ((message.len() + 1) as u32).to_be_bytes().to_vec();
+        packet.push(id);
+        packet.extend_from_slice(message);
+        // The client hangs up early when it rejects the metadata
+        let _ = stream.write_all(&packet);
+    }
+
+    fn receive(stream: &mut TcpStream) -> Vec<u8> {
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).unwrap();
+        let mut message = vec![0u8; u32::from_be_bytes(len) as usize];
+        stream.read_exact(&mut message).unwrap();
+        message
+    }
+
+    /// A fake peer serving `metadata` to one connection
+    fn fake_peer(metadata: Vec<u8>, behavior: Behavior) -> (SocketAddr, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut handshake = [0u8; 68];
+            stream.read_exact(&mut handshake).unwrap();
+            assert_eq!(&handshake[1..20], b"BitTorrent protocol");
+            assert_eq!(handshake[25] & 0x10, 0x10);
+            assert_eq!(&handshake[48..56], b"-MU3000-");
+
+            // Echo the handshake with our own peer ID
+            if behavior == Behavior::NoExtensions {
+                handshake[25] = 0;
+            }
+            handshake[48..].copy_from_slice(b"-XX0000-000000000000");
+            stream.write_all(&handshake).unwrap();
+            if behavior == Behavior::NoExtensions {
+                return;
+            }
+            if behavior == Behavior::KeepAlive {
+                // Keep the connection open without ever sending the metadata
+                while stream.write_all(&[0, 0, 0, 0]).is_ok() {
+                    thread::sleep(Duration::from_millis(20));
+                }
+                return;
+            }
+
+            // A bitfield and a keep-alive before the extension handshake
+            send(&mut stream, 5, &[0xff]);
+            stream.write_all(&[0, 0, 0, 0]).unwrap();
+            let ours = format!(
+                "d1:md11:ut_metadatai7ee13:metadata_sizei{}ee",
+                metadata.len()
+            );
+            send(&mut stream, 20, &[&[0], ours.as_bytes()].concat());
+
+            let theirs = receive(&mut stream);
+            let m = bencode::decode(&theirs[2..]).unwrap();
+            let their_id = m
+                .get("m")
+                .and_then(|m| m.get("ut_metadata"))
+                .and_then(Value::as_int)
+                .unwrap() as u8;
+
+            let pieces = metadata.len().div_ceil(PIECE_SIZE);
+            let mut requests = Vec::new();
+            for _ in 0..pieces {
+                let request = receive(&mut stream);
+                assert_eq!(request[..2], [20, 7]);
+                let request = bencode::decode(&request[2..]).unwrap();
+                assert_eq!(request.get("msg_type"), Some(&Value::Int(0)));
+                requests.push(request.get("piece").and_then(Value::as_int).unwrap() as usize);
+            }
+
+            // Answer in reverse order
+            for &piece in requests.iter().rev() {
+                let start = piece * PIECE_SIZE;
+                let end = (start + PIECE_SIZE).min(metadata.len());
+                let mut message = vec![their_id];
+                if behavior == Behavior::Reject {
+                    message
+                        .extend_from_slice(format!("d8:msg_typei2e5:piecei{}ee", piece).as_bytes());
+                } else {
+                    let header = format!(
+                        "d8:msg_typei1e5:piecei{}e10:total_sizei{}ee",
+                        piece,
+                        metadata.len()
+                    );
+                    message.extend_from_slice(header.as_bytes());
+                    message.extend_from_slice(&metadata[start..end]);
+                    if behavior == Behavior::Corrupt && piece == 1 {
+                        *message.last_mut().unwrap() ^= 1;
+                    }
+                }
+                send(&mut stream, 20, &message);
+            }
+        });
+        (addr, handle)
+    }
+
+    fn fetcher(metadata: &[u8]) -> MetadataFetcher {
+        MetadataFetcher::new(info_hash(metadata)).timeout(Duration::from_secs(5))
+    }
+
+    #[test]
+    fn fetch_test() {
+        let metadata = metadata();
+        let (addr, handle) = fake_peer(metadata.clone(), Behavior::Honest);
+        assert_eq!(fetcher(&metadata).fetch_from(addr).unwrap(), metadata);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn fetch_errors_test() {
+        let metadata = metadata();
+
+        let (addr, handle) = fake_peer(metadata.clone(), Behavior::Corrupt);
+        assert!(matches!(
+            fetcher(&metadata).fetch_from(addr),
+            Err(MetadataError::HashMismatch)
+        ));
+        handle.join().unwrap();
+
+        let (addr, handle) = fake_peer(metadata.clone(), Behavior::Reject);
+        assert!(matches!(
+            fetcher(&metadata).fetch_from(addr),
+            Err(MetadataError::Rejected)
+        ));
+        handle.join().unwrap();
+
+        let (addr, handle) = fake_peer(metadata.clone(), Behavior::NoExtensions);
+        assert!(matches!(
+            fetcher(&metadata).fetch_from(addr),
+            Err(MetadataError::Unsupported)
+        ));
+        handle.join().unwrap();
+
+        let (addr, handle) = fake_peer(metadata.clone(), Behavior::KeepAlive);
+        let start = Instant::now();
+        assert!(matches!(
+            fetcher(&metadata)
+                .deadline(Duration::from_millis(200))
+                .fetch_from(addr),
+            Err(MetadataError::Deadline)
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        handle.join().unwrap();
+
+        assert!(matches!(
+            fetcher(&metadata).fetch([]),
+            Err(MetadataError::NoPeers)
+        ));
+    }
+
+    #[test]
+    fn fetch_from_magnet_peers_test() {
+        let metadata = metadata();
+
+        // The first peer refuses connections, the second one has the metadata
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let (addr, handle) = fake_peer(metadata.clone(), Behavior::Honest);
+        let link = format!(
+            "magnet:?xt=urn:btih:{}&tr=udp%3A%2F%2Ftracker.example.com%3A6969&x.pe={}&x.pe={}",
+            info_hash(&metadata),
+            closed,
+            addr
+        );
+        let magnet = Magnet::new(&link).unwrap();
+        assert_eq!(magnet.peer_addrs(), [closed, addr]);
+
+        let fetched = MetadataFetcher::from_magnet(&magnet)
+            .unwrap()
+            .fetch(magnet.peer_addrs())
+            .unwrap();
+        handle.join().unwrap();
+
+        // The torrent file built from the metadata has the same info hash
+        let torrent = torrent_file(&magnet, &fetched);
+        let from_torrent = Magnet::from_torrent(&torrent).unwrap();
+        assert_eq!(from_torrent.info_hash(), magnet.info_hash());
+        assert_eq!(from_torrent.display_name(), Some("test"));
+        assert_eq!(from_torrent.trackers(), ["udp://tracker.example.com:6969"]);
+
+        let magnet =
+            Magnet::new("magnet:?xt=urn:sha1:2aae6c35c94fcfb415dbe95f408b9ce91ee846ed").unwrap();
+        assert!(matches!(
+            MetadataFetcher::from_magnet(&magnet),
+            Err(MetadataError::NoInfoHash)
+        ));
+    }
+}
@@ -0,0 +1,237 @@
+//! A minimal PNG encoder for black and white images, used to render QR codes
+//!
+//! Images are written as 1-bit grayscale and compressed with fixed-Huffman deflate, matching
+//! only against the previous row and the previous byte, which is all the redundancy a scaled-up
+//! QR code has.
+
+use alloc::vec::Vec;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Encode a `width` by `height` black and white image as a PNG, where `is_black(x, y)` gives
+/// the colour of each pixel
+pub(crate) fn encode_bilevel(
+    width: u32,
+    height: u32,
+    is_black: impl Fn(u32, u32) -> bool,
+) -> Vec<u8> {
+    // Each row is a filter type byte (0, none) followed by 8 pixels per byte, 1 for white
+    let stride = width.div_ceil(8) as usize + 1;
+    let mut raw = Vec::with_capacity(stride * height as usize);
+    for y in 0..height {
+        raw.push(0);
+        for byte_x in 0..width.div_ceil(8) {
+            let mut byte = 0u8;
+            for bit in 0..8 {
+                let x = byte_x * 8 + bit;
+                if x < width && !is_black(x, y) {
+                    byte |= 0x80 >> bit;
+                }
+            }
+            raw.push(byte);
+        }
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth 1, grayscale, deflate, adaptive filtering, no interlacing
+    header.extend_from_slice(&[1, 0, 0, 0, 0]);
+
+    let mut png = Vec::from(SIGNATURE);
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_compress(&raw, stride));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// CRC-32 (ISO 3309), as used by PNG chunks
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+/// Bits written least significant first, as deflate wants them
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, length: u32) {
+        self.buffer |= u64::from(value) << self.bits;
+        self.bits += length;
+        while self.bits >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    /// Write a Huffman code, which deflate stores most significant bit first
+    fn write_code(&mut self, code: u32, length: u32) {
+        self.write(code.reverse_bits() >> (32 - length), length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.out.push(self.buffer as u8);
+        }
+        self.out
+    }
+}
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Compress `data` into a zlib stream of a single fixed-Huffman deflate block, looking for
+/// repeats one byte and one row (`stride` bytes) back
+fn zlib_compress(data: &[u8], stride: usize) -> Vec<u8> {
+    let mut writer = BitWriter {
+        out: Vec::from([0x78, 0x01]),
+        buffer: 0,
+        bits: 0,
+    };
+    // Final block, fixed Huffman codes
+    writer.write(1, 1);
+    writer.write(1, 2);
+
+    let mut i = 0;
+    while i < data.len() {
+        let (length, distance) = [stride, 1]
+            .iter()
+            .filter(|&&distance| distance <= i && distance <= 32768)
+            .map(|&distance| {
+                let length = data[i..]
+                    .iter()
+                    .zip(&data[i - distance..])
+                    .take(258)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                (length, distance)
+            })
+            .max_by_key(|&(length, _)| length)
+            .unwrap_or((0, 0));
+
+        if length >= 3 {
+            write_match(&mut writer, length, distance);
+            i += length;
+        } else {
+            write_symbol(&mut writer, u16::from(data[i]));
+            i += 1;
+        }
+    }
+    write_symbol(&mut writer, 256);
+
+    let mut out = writer.finish();
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Write a literal/length symbol with the fixed Huffman code
+fn write_symbol(writer: &mut BitWriter, symbol: u16) {
+    let symbol = u32::from(symbol);
+    match symbol {
+        0..=143 => writer.write_code(0x30 + symbol, 8),
+        144..=255 => writer.write_code(0x190 + symbol - 144, 9),
+        256..=279 => writer.write_code(symbol - 256, 7),
+        _ => writer.write_code(0xC0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let code = LENGTH_BASES
+        .iter()
+        .rposition(|&base| base as usize <= length)
+        .unwrap();
+    write_symbol(writer, 257 + code as u16);
+    writer.write(
+        (length - LENGTH_BASES[code] as usize) as u32,
+        u32::from(LENGTH_EXTRA_BITS[code]),
+    );
+
+    let code = DISTANCE_BASES
+        .iter()
+        .rposition(|&base| base as usize <= distance)
+        .unwrap();
+    writer.write_code(code as u32, 5);
+    writer.write(
+        (distance - DISTANCE_BASES[code] as usize) as u32,
+        u32::from(DISTANCE_EXTRA_BITS[code]),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_test() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn zlib_compress_test() {
+        // Checked by inflating with Python's zlib
+        assert_eq!(
+            zlib_compress(b"aaaaaaaaab", 4),
+            [0x78, 0x01, 0x4b, 0x84, 0x81, 0x24, 0x00, 0x14, 0xe2, 0x03, 0xcc]
+        );
+    }
+
+    #[test]
+    fn encode_bilevel_test() {
+        let png = encode_bilevel(3, 2, |x, y| x == y);
+        assert_eq!(png[..8], SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], [0, 0, 0, 3, 0, 0, 0, 2]);
+        assert_eq!(
+            &png[png.len() - 12..],
+            [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]
+        );
+    }
+}
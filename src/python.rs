@@ -0,0 +1,209 @@
+//! Python bindings, built with PyO3 when the `python` feature is enabled
+//!
+//! The extension module is built with maturin (see `pyproject.toml`) and imported as
+//! `magnet_url`:
+//!
+//! ```python
+//! from magnet_url import Magnet, MagnetBuilder, MagnetError
+//!
+//! magnet = Magnet("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel")
+//! print(magnet.display_name, magnet.trackers)
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::mem;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+
+use crate::{Magnet as RustMagnet, MagnetBuilder as RustMagnetBuilder, MagnetError as RustMagnetError, TorrentError};
+
+pyo3::create_exception!(
+    magnet_url,
+    MagnetError,
+    PyValueError,
+    "Raised when a magnet URL or torrent file cannot be parsed"
+);
+
+impl From<RustMagnetError> for PyErr {
+    fn from(err: RustMagnetError) -> PyErr {
+        MagnetError::new_err(err.to_string())
+    }
+}
+
+impl From<TorrentError> for PyErr {
+    fn from(err: TorrentError) -> PyErr {
+        MagnetError::new_err(err.to_string())
+    }
+}
+
+/// A parsed magnet URL
+///
+/// Equality and hashing follow the Rust `PartialEq` and `Hash` implementations, so two magnets
+/// are equal when all of their fields are.
+#[pyclass(name = "Magnet", module = "magnet_url", frozen, eq, hash)]
+#[derive(PartialEq, Hash)]
+pub struct PyMagnet(RustMagnet);
+
+#[pymethods]
+impl PyMagnet {
+    /// Parse a magnet URL, raising `MagnetError` if it is invalid
+    #[new]
+    fn new(link: &str) -> PyResult<Self> {
+        Ok(PyMagnet(RustMagnet::new(link)?))
+    }
+
+    /// Create a magnet URL from the contents of a `.torrent` file
+    #[staticmethod]
+    fn from_torrent(data: &[u8]) -> PyResult<Self> {
+        Ok(PyMagnet(RustMagnet::from_torrent(data)?))
+    }
+
+    #[getter]
+    fn display_name(&self) -> Option<&str> {
+        self.0.display_name()
+    }
+
+    #[getter]
+    fn hash_type(&self) -> Option<&str> {
+        self.0.hash_type()
+    }
+
+    #[getter]
+    fn hash(&self) -> Option<&str> {
+        self.0.hash()
+    }
+
+    #[getter]
+    fn length(&self) -> Option<u64> {
+        self.0.length()
+    }
+
+    #[getter]
+    fn source(&self) -> Option<&str> {
+        self.0.source()
+    }
+
+    #[getter]
+    fn trackers(&self) -> Vec<String> {
+        self.0.trackers().to_vec()
+    }
+
+    #[getter]
+    fn peers(&self) -> Vec<String> {
+        self.0.peers().to_vec()
+    }
+
+    #[getter]
+    fn search_keywords(&self) -> Option<&str> {
+        self.0.search_keywords()
+    }
+
+    #[getter]
+    fn web_seed(&self) -> Option<&str> {
+        self.0.web_seed()
+    }
+
+    #[getter]
+    fn acceptable_source(&self) -> Option<&str> {
+        self.0.acceptable_source()
+    }
+
+    #[getter]
+    fn manifest(&self) -> Option<&str> {
+        self.0.manifest()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let link = PyString::new(py, &self.0.to_string()).repr()?;
+        Ok(alloc::format!("Magnet({})", link))
+    }
+}
+
+/// Builder for creating magnet URLs, with chainable setters
+#[pyclass(name = "MagnetBuilder", module = "magnet_url")]
+#[derive(Default)]
+pub struct PyMagnetBuilder(RustMagnetBuilder);
+
+impl PyMagnetBuilder {
+    /// Apply a builder method in place, returning the builder for chaining
+    fn apply(
+        mut slf: PyRefMut<'_, Self>,
+        f: impl FnOnce(RustMagnetBuilder) -> RustMagnetBuilder,
+    ) -> PyRefMut<'_, Self> {
+        slf.0 = f(mem::take(&mut slf.0));
+        slf
+    }
+}
+
+#[pymethods]
+impl PyMagnetBuilder {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn display_name<'py>(slf: PyRefMut<'py, Self>, name: &str) -> PyRefMut<'py, Self> {
+        Self::apply(slf, |b| b.display_name(name))
+    }
+
+    fn hash_type<'py>(slf: PyRefMut<'py, Self>, hash_type: &str) -> PyRefMut<'py, Self> {
+        Self::apply(slf, |b| b.hash_type(hash_type))
+    }
+
+    fn hash<'py>(slf: PyRefMut<'py, Self>, hash: &str) -> PyRefMut<'py, Self> {
+        Self::apply(slf, |b| b.hash(hash))
+    }
+
+    fn length(slf: PyRefMut<'_, Self>, length: u64) -> PyRefMut<'_, Self> {
+        Self::apply(slf, |b| b.length(length))
+    }
+
+    fn source<'py>(slf: PyRefMut<'py, Self>, source: &str) -> PyRefMut<'py, Self> {
+        Self::apply(slf, |b| b.source(source))
+    }
+
+    fn add_tracker<'py>(slf: PyRefMut<'py, Self>, tracker: &str) -> PyRefMut<'py, Self> {
+        Self::apply(slf, |b| b.add_tracker(tracker))
+    }
+
+    fn add_trackers(slf: PyRefMut<'_, Self>, trackers: Vec<String>) -> PyRefMut<'_, Self> {
+        let trackers: Vec<&str> = trackers.iter().map(String::as_str).collect();
+        Self::apply(slf, |b| b.add_trackers(&trackers))
+    }
+
+    fn search_keywords<'py>(slf: PyRefMut<'py, Self>, keywords: &str) -> PyRefMut<'py, Self> {
+        Self::apply(slf, |b| b.search_keywords(keywords))
+    }
+
+    fn web_seed<'py>(slf: PyRefMut<'py, Self>, web_seed: &str) -> PyRefMut<'py, Self> {
+        Self::apply(slf, |b| b.web_seed(web_seed))
+    }
+
+    fn acceptable_source<'py>(slf: PyRefMut<'py, Self>, source: &str) -> PyRefMut<'py, Self> {
+        Self::apply(slf, |b| b.acceptable_source(source))
+    }
+
+    fn manifest<'py>(slf: PyRefMut<'py, Self>, manifest: &str) -> PyRefMut<'py, Self> {
+        Self::apply(slf, |b| b.manifest(manifest))
+    }
+
+    /// Build the magnet. The builder is reset, so it can be reused.
+    fn build(&mut self) -> PyMagnet {
+        PyMagnet(mem::take(&mut self.0).build())
+    }
+}
+
+#[pymodule]
+fn magnet_url(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyMagnet>()?;
+    m.add_class::<PyMagnetBuilder>()?;
+    m.add("MagnetError", m.py().get_type::<MagnetError>())?;
+    Ok(())
+}
@@ -0,0 +1,865 @@
+//! QR codes (ISO/IEC 18004, byte mode) of magnet links, rendered as SVG, PNG or Unicode text
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::Magnet;
+
+/// How much of a QR code can be damaged and still be read
+///
+/// Higher levels spend more of the symbol on error correction, so they hold less data.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QrErrorCorrection {
+    /// About 7% of the codewords can be restored
+    Low,
+    /// About 15% of the codewords can be restored
+    Medium,
+    /// About 25% of the codewords can be restored
+    Quartile,
+    /// About 30% of the codewords can be restored
+    High,
+}
+
+impl QrErrorCorrection {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The two bits identifying the level in the format information
+    fn format_bits(self) -> u32 {
+        match self {
+            QrErrorCorrection::Low => 1,
+            QrErrorCorrection::Medium => 0,
+            QrErrorCorrection::Quartile => 3,
+            QrErrorCorrection::High => 2,
+        }
+    }
+}
+
+impl fmt::Display for QrErrorCorrection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letter = match self {
+            QrErrorCorrection::Low => "L",
+            QrErrorCorrection::Medium => "M",
+            QrErrorCorrection::Quartile => "Q",
+            QrErrorCorrection::High => "H",
+        };
+        f.write_str(letter)
+    }
+}
+
+/// The ways encoding a QR code can fail
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum QrError {
+    /// The data is longer than the largest QR code (version 40) holds at the requested error
+    /// correction level
+    DataTooLong {
+        /// Length of the data, in bytes
+        length: usize,
+        /// Most bytes a QR code holds at the requested level
+        capacity: usize,
+        /// The requested error correction level
+        error_correction: QrErrorCorrection,
+    },
+    /// The PNG image would be wider than [`QrCode::MAX_PNG_SIZE`] pixels at the requested scale and
+    /// border
+    ImageTooLarge,
+}
+
+impl fmt::Display for QrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QrError::DataTooLong {
+                length,
+                capacity,
+                error_correction,
+            } => write!(
+                f,
+                "{} bytes do not fit in a QR code at error correction level {} (at most {})",
+                length, error_correction, capacity
+            ),
+            QrError::ImageTooLarge => write!(
+                f,
+                "PNG image would be larger than {0} by {0} pixels",
+                QrCode::MAX_PNG_SIZE
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for QrError {}
+// Error correction codewords per block and number of blocks, by level and version (index 0
+// is unused)
+#[rustfmt::skip]
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28, 30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28],
+    [0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30, 30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+];
+#[rustfmt::skip]
+const ERROR_CORRECTION_BLOCKS: [[u8; 41]; 4] = [
+    [0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13, 14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25],
+    [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49],
+    [0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29, 34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68],
+    [0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32, 35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81],
+];
+
+/// A QR code: a square grid of dark and light modules
+///
+/// # Example
+///
+/// ```
+/// use magnet_url::{Magnet, QrErrorCorrection};
+///
+/// let magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel").unwrap();
+/// let qr = magnet.to_qr_code(QrErrorCorrection::Medium).unwrap();
+/// assert_eq!(qr.size(), 4 * qr.version() as usize + 17);
+/// let svg = qr.to_svg(4);
+/// # assert!(svg.starts_with("<svg"));
+/// ```
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QrCode {
+    version: u8,
+    error_correction: QrErrorCorrection,
+    mask: u8,
+    size: usize,
+    /// Row-major, `true` for dark
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// Largest width and height of a PNG image rendered by [`to_png`](Self::to_png), in pixels
+    pub const MAX_PNG_SIZE: u32 = 16_384;
+
+    /// Encode `data` in the smallest QR code that holds it at `min_error_correction`
+    ///
+    /// The error correction level is raised as far as it goes without needing a larger code,
+    /// and the mask is the one the standard's penalty rules score best.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`QrError::DataTooLong`] if the data does not fit even in a version 40 code
+    pub fn encode(data: &[u8], min_error_correction: QrErrorCorrection) -> Result<Self, QrError> {
+        let version = (1..=40)
+            .find(|&version| data.len() <= Self::capacity(version, min_error_correction))
+            .ok_or(QrError::DataTooLong {
+                length: data.len(),
+                capacity: Self::capacity(40, min_error_correction),
+                error_correction: min_error_correction,
+            })?;
+
+        let mut error_correction = min_error_correction;
+        for level in [
+            QrErrorCorrection::Medium,
+            QrErrorCorrection::Quartile,
+            QrErrorCorrection::High,
+        ] {
+            if level > error_correction && data.len() <= Self::capacity(version, level) {
+                error_correction = level;
+            }
+        }
+
+        let codewords = add_error_correction(
+            &data_codewords(data, version, error_correction),
+            version,
+            error_correction,
+        );
+
+        let mut matrix = Matrix::new(version);
+        matrix.draw_function_patterns();
+        matrix.draw_codewords(&codewords);
+
+        // Try every mask, keeping the one with the lowest penalty
+        let mut best: Option<(u32, u8)> = None;
+        for mask in 0..8 {
+            matrix.apply_mask(mask);
+            matrix.draw_format_bits(error_correction, mask);
+            let penalty = matrix.penalty();
+            if best.is_none_or(|(best_penalty, _)| penalty < best_penalty) {
+                best = Some((penalty, mask));
+            }
+            matrix.apply_mask(mask);
+        }
+        let mask = best.map_or(0, |(_, mask)| mask);
+        matrix.apply_mask(mask);
+        matrix.draw_format_bits(error_correction, mask);
+
+        Ok(QrCode {
+            version,
+            error_correction,
+            mask,
+            size: matrix.size,
+            modules: matrix.modules,
+        })
+    }
+
+    /// Get how many bytes a QR code of `version` (1 to 40) holds at `error_correction`
+    pub fn capacity(version: u8, error_correction: QrErrorCorrection) -> usize {
+        let count_bits = if version < 10 { 8 } else { 16 };
+        (data_codeword_count(version, error_correction) * 8 - 4 - count_bits) / 8
+    }
+
+    /// Get the version of the code, from 1 to 40
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Get the error correction level the code was encoded at
+    pub fn error_correction(&self) -> QrErrorCorrection {
+        self.error_correction
+    }
+
+    /// Get the mask pattern applied to the code, from 0 to 7
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// Get the width and height of the code, in modules
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get whether the module at column `x` and row `y` is dark; modules outside the code
+    /// (in its quiet zone) are light
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y * self.size + x]
+    }
+
+    /// Whether the module at (`x`, `y`) of the code surrounded by a `border` is dark
+    fn is_dark_with_border(&self, x: usize, y: usize, border: usize) -> bool {
+        x >= border && y >= border && self.is_dark(x - border, y - border)
+    }
+
+    /// Render the code as an SVG image, with a quiet zone `border` modules wide
+    ///
+    /// The image is one unit per module, so it scales to any size.
+    pub fn to_svg(&self, border: u32) -> String {
+        let border = border as usize;
+        let dimension = self.size + border * 2;
+        let mut path = String::new();
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.is_dark(x, y) {
+                    if !path.is_empty() {
+                        path.push(' ');
+                    }
+                    path.push_str(&format!("M{},{}h1v1h-1z", x + border, y + border));
+                }
+            }
+        }
+        format!(
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" viewBox=\"0 0 {0} {0}\" stroke=\"none\">",
+                "<rect width=\"100%\" height=\"100%\" fill=\"#FFFFFF\"/>",
+                "<path d=\"{1}\" fill=\"#000000\"/>",
+                "</svg>\n"
+            ),
+            dimension, path
+        )
+    }
+
+    /// Render the code as a black and white PNG image, `scale` pixels per module, with a quiet
+    /// zone `border` modules wide
+    ///
+    /// # Errors
+    ///
+    /// Returns [`QrError::ImageTooLarge`] if the image would be wider than
+    /// [`MAX_PNG_SIZE`](Self::MAX_PNG_SIZE)
+    pub fn to_png(&self, scale: u32, border: u32) -> Result<Vec<u8>, QrError> {
+        let scale = scale.max(1);
+        let dimension = border
+            .checked_mul(2)
+            .and_then(|borders| borders.checked_add(self.size as u32))
+            .and_then(|modules| modules.checked_mul(scale))
+            .filter(|&dimension| dimension <= Self::MAX_PNG_SIZE)
+            .ok_or(QrError::ImageTooLarge)?;
+        Ok(crate::png::encode_bilevel(dimension, dimension, |x, y| {
+            self.is_dark_with_border((x / scale) as usize, (y / scale) as usize, border as usize)
+        }))
+    }
+
+    /// Render the code as lines of Unicode block characters, two modules per character, with
+    /// a quiet zone `border` modules wide
+    ///
+    /// Light modules are drawn as blocks and dark ones as spaces, so the code scans on
+    /// terminals with a dark background.
+    pub fn to_unicode(&self, border: u32) -> String {
+        let border = border as usize;
+        let dimension = self.size + border * 2;
+        let mut out = String::new();
+        for y in (0..dimension).step_by(2) {
+            for x in 0..dimension {
+                let top = !self.is_dark_with_border(x, y, border);
+                let bottom = y + 1 < dimension && !self.is_dark_with_border(x, y + 1, border);
+                out.push(match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl fmt::Display for QrCode {
+    /// Writes the code as Unicode blocks with a 4 module quiet zone, see [`QrCode::to_unicode`]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_unicode(4))
+    }
+}
+
+impl Magnet {
+    /// Encode the magnet link in a QR code, at `min_error_correction` or higher
+    ///
+    /// # Errors
+    ///
+    /// Returns [`QrError::DataTooLong`] if the link is too long for a QR code at that level
+    pub fn to_qr_code(&self, min_error_correction: QrErrorCorrection) -> Result<QrCode, QrError> {
+        QrCode::encode(self.to_string().as_bytes(), min_error_correction)
+    }
+}
+
+/// Number of modules that hold data and error correction codewords in a code of `version`
+fn raw_data_modules(version: u8) -> usize {
+    let version = version as usize;
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        modules -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+fn data_codeword_count(version: u8, error_correction: QrErrorCorrection) -> usize {
+    let level = error_correction.index();
+    let version = version as usize;
+    raw_data_modules(version as u8) / 8
+        - ECC_CODEWORDS_PER_BLOCK[level][version] as usize
+            * ERROR_CORRECTION_BLOCKS[level][version] as usize
+}
+
+/// Build the data codewords: a byte mode segment, the terminator and padding
+fn data_codewords(data: &[u8], version: u8, error_correction: QrErrorCorrection) -> Vec<u8> {
+    let capacity = data_codeword_count(version, error_correction);
+    let mut bits = BitBuffer::default();
+    bits.push(0b0100, 4);
+    bits.push(data.len() as u32, if version < 10 { 8 } else { 16 });
+    for &byte in data {
+        bits.push(u32::from(byte), 8);
+    }
+
+    let terminator = (capacity * 8 - bits.len).min(4);
+    bits.push(0, terminator as u32);
+    bits.push(0, ((8 - bits.len % 8) % 8) as u32);
+
+    let mut codewords = bits.bytes;
+    for pad in [0xEC, 0x11].iter().cycle() {
+        if codewords.len() >= capacity {
+            break;
+        }
+        codewords.push(*pad);
+    }
+    codewords
+}
+
+/// Split the data codewords into blocks, add the Reed-Solomon codewords of each block and
+/// interleave them
+fn add_error_correction(data: &[u8], version: u8, error_correction: QrErrorCorrection) -> Vec<u8> {
+    let level = error_correction.index();
+    let block_count = ERROR_CORRECTION_BLOCKS[level][version as usize] as usize;
+    let ecc_length = ECC_CODEWORDS_PER_BLOCK[level][version as usize] as usize;
+    let raw_codewords = raw_data_modules(version) / 8;
+    let short_blocks = block_count - raw_codewords % block_count;
+    let short_block_length = raw_codewords / block_count;
+
+    // Short blocks get a placeholder byte so every block is the same length
+    let divisor = reed_solomon_divisor(ecc_length);
+    let mut blocks = Vec::with_capacity(block_count);
+    let mut start = 0;
+    for i in 0..block_count {
+        let length = short_block_length - ecc_length + usize::from(i >= short_blocks);
+        let mut block = data[start..start + length].to_vec();
+        start += length;
+        let ecc = reed_solomon_remainder(&block, &divisor);
+        if i < short_blocks {
+            block.push(0);
+        }
+        block.extend_from_slice(&ecc);
+        blocks.push(block);
+    }
+
+    let mut out = Vec::with_capacity(raw_codewords);
+    for i in 0..blocks[0].len() {
+        for (j, block) in blocks.iter().enumerate() {
+            if i != short_block_length - ecc_length || j >= short_blocks {
+                out.push(block[i]);
+            }
+        }
+    }
+    out
+}
+
+/// The generator polynomial of degree `degree`, without its leading 1 term
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut divisor = vec![0u8; degree];
+    divisor[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            divisor[j] = gf_multiply(divisor[j], root);
+            if j + 1 < degree {
+                divisor[j] ^= divisor[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    divisor
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut remainder = vec![0u8; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ remainder.remove(0);
+        remainder.push(0);
+        for (r, &d) in remainder.iter_mut().zip(divisor) {
+            *r ^= gf_multiply(d, factor);
+        }
+    }
+    remainder
+}
+
+/// Multiply in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z = 0u16;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= u16::from((y >> i) & 1) * u16::from(x);
+    }
+    z as u8
+}
+
+#[derive(Default)]
+struct BitBuffer {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitBuffer {
+    /// Append the low `count` bits of `value`, most significant first
+    fn push(&mut self, value: u32, count: u32) {
+        for i in (0..count).rev() {
+            if self.len.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if (value >> i) & 1 != 0 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> (self.len % 8);
+            }
+            self.len += 1;
+        }
+    }
+}
+
+/// The modules of a code being built, and which of them belong to function patterns
+struct Matrix {
+    version: u8,
+    size: usize,
+    modules: Vec<bool>,
+    function: Vec<bool>,
+}
+
+impl Matrix {
+    fn new(version: u8) -> Self {
+        let size = version as usize * 4 + 17;
+        Matrix {
+            version,
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        self.draw_finder(3, 3);
+        self.draw_finder(size - 4, 3);
+        self.draw_finder(3, size - 4);
+
+        let positions = alignment_positions(self.version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // The corners with finder patterns have no alignment pattern
+                let finder_corner = (i == 0 && (j == 0 || j == last)) || (i == last && j == 0);
+                if !finder_corner {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+
+        // Reserve the format areas, drawn for real once the mask is known
+        self.draw_format_bits(QrErrorCorrection::Low, 0);
+        self.draw_version();
+    }
+
+    /// Draw a finder pattern and its separator around the center module (`x`, `y`)
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4..=4isize {
+            for dx in -4..=4isize {
+                let (xx, yy) = (x as isize + dx, y as isize + dy);
+                if (0..self.size as isize).contains(&xx) && (0..self.size as isize).contains(&yy) {
+                    let distance = dx.abs().max(dy.abs());
+                    self.set_function(xx as usize, yy as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, x: usize, y: usize) {
+        for dy in -2..=2isize {
+            for dx in -2..=2isize {
+                let dark = dx.abs().max(dy.abs()) != 1;
+                self.set_function((x as isize + dx) as usize, (y as isize + dy) as usize, dark);
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, error_correction: QrErrorCorrection, mask: u8) {
+        let bits = format_bits(error_correction, mask);
+        let bit = |i: u32| (bits >> i) & 1 != 0;
+        let size = self.size;
+
+        // Around the top left finder
+        for i in 0..6 {
+            self.set_function(8, i as usize, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i as usize, 8, bit(i));
+        }
+
+        // Split between the other two finders
+        for i in 0..8 {
+            self.set_function(size - 1 - i as usize, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i as usize, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version(&mut self) {
+        if self.version < 7 {
+            return;
+        }
+        let bits = version_bits(self.version);
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let a = self.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Fill the non-function modules with the codewords, in the zigzag order of the standard
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let mut i = 0;
+        let mut right = size - 1;
+        loop {
+            // The vertical timing pattern is skipped entirely
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vertical in 0..size {
+                let y = if upward {
+                    size - 1 - vertical
+                } else {
+                    vertical
+                };
+                for x in [right, right - 1] {
+                    if !self.function[y * size + x] && i < codewords.len() * 8 {
+                        self.modules[y * size + x] = (codewords[i / 8] >> (7 - i % 8)) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    /// XOR mask pattern `mask` onto the non-function modules (applying it twice undoes it)
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let index = y * self.size + x;
+                if invert && !self.function[index] {
+                    self.modules[index] = !self.modules[index];
+                }
+            }
+        }
+    }
+
+    /// Score the code with the standard's four penalty rules; lower scans more reliably
+    fn penalty(&self) -> u32 {
+        let size = self.size;
+        let mut penalty = 0;
+
+        // Rows and columns: runs of 5 or more modules of one colour, and finder-like patterns
+        for horizontal in [true, false] {
+            for a in 0..size {
+                let line: Vec<bool> = (0..size)
+                    .map(|b| {
+                        if horizontal {
+                            self.get(b, a)
+                        } else {
+                            self.get(a, b)
+                        }
+                    })
+                    .collect();
+
+                let mut run = 1;
+                for b in 1..=size {
+                    if b < size && line[b] == line[b - 1] {
+                        run += 1;
+                        continue;
+                    }
+                    if run >= 5 {
+                        penalty += 3 + (run - 5) as u32;
+                    }
+                    run = 1;
+                }
+
+                const FINDER_LIKE: [bool; 11] = [
+                    true, false, true, true, true, false, true, false, false, false, false,
+                ];
+                for window in line.windows(11) {
+                    if window == FINDER_LIKE || window.iter().rev().eq(FINDER_LIKE.iter()) {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+
+        // 2x2 blocks of one colour
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.get(x, y);
+                if dark == self.get(x + 1, y)
+                    && dark == self.get(x, y + 1)
+                    && dark == self.get(x + 1, y + 1)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+
+        // Balance of dark and light modules, 10 points per 5% away from half
+        let total = size * size;
+        let dark = self.modules.iter().filter(|&&dark| dark).count();
+        let k = (dark * 20).abs_diff(total * 10).div_ceil(total) - 1;
+        penalty + k as u32 * 10
+    }
+}
+
+/// The centers of the alignment patterns, along each axis
+fn alignment_positions(version: u8) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let version = version as usize;
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let size = version * 4 + 17;
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// The 15 bits of format information: the level and mask with a BCH code, masked
+fn format_bits(error_correction: QrErrorCorrection, mask: u8) -> u32 {
+    let data = error_correction.format_bits() << 3 | u32::from(mask);
+    let mut remainder = data;
+    for _ in 0..10 {
+        remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+    }
+    (data << 10 | remainder) ^ 0x5412
+}
+
+/// The 18 bits of version information: the version with a BCH code
+fn version_bits(version: u8) -> u32 {
+    let version = u32::from(version);
+    let mut remainder = version;
+    for _ in 0..12 {
+        remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1F25);
+    }
+    version << 12 | remainder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity_test() {
+        assert_eq!(QrCode::capacity(1, QrErrorCorrection::Low), 17);
+        assert_eq!(QrCode::capacity(1, QrErrorCorrection::High), 7);
+        assert_eq!(QrCode::capacity(10, QrErrorCorrection::Medium), 213);
+        assert_eq!(QrCode::capacity(40, QrErrorCorrection::Low), 2953);
+        assert_eq!(QrCode::capacity(40, QrErrorCorrection::Quartile), 1663);
+        assert_eq!(raw_data_modules(7) / 8, 196);
+        assert_eq!(raw_data_modules(40) / 8, 3706);
+    }
+
+    #[test]
+    fn alignment_positions_test() {
+        assert_eq!(alignment_positions(1), Vec::<usize>::new());
+        assert_eq!(alignment_positions(2), [6, 18]);
+        assert_eq!(alignment_positions(7), [6, 22, 38]);
+        assert_eq!(alignment_positions(32), [6, 34, 60, 86, 112, 138]);
+        assert_eq!(alignment_positions(40), [6, 30, 58, 86, 114, 142, 170]);
+    }
+
+    #[test]
+    fn format_and_version_bits_test() {
+        assert_eq!(format_bits(QrErrorCorrection::Low, 0), 0b111011111000100);
+        assert_eq!(format_bits(QrErrorCorrection::Medium, 0), 0b101010000010010);
+        assert_eq!(
+            format_bits(QrErrorCorrection::Quartile, 0),
+            0b011010101011111
+        );
+        assert_eq!(format_bits(QrErrorCorrection::High, 0), 0b001011010001001);
+        assert_eq!(format_bits(QrErrorCorrection::Low, 4), 0b110011000101111);
+        assert_eq!(version_bits(7), 0b000111110010010100);
+        assert_eq!(version_bits(40), 0b101000110001101001);
+    }
+
+    #[test]
+    fn reed_solomon_test() {
+        // The "HELLO WORLD" 1-M example of the standard
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            reed_solomon_remainder(&data, &reed_solomon_divisor(10)),
+            [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+    }
+
+    #[test]
+    fn data_codewords_test() {
+        let codewords = data_codewords(b"hi", 1, QrErrorCorrection::High);
+        assert_eq!(
+            codewords,
+            [0x40, 0x26, 0x86, 0x90, 0xEC, 0x11, 0xEC, 0x11, 0xEC]
+        );
+    }
+
+    #[test]
+    fn encode_test() {
+        let qr = QrCode::encode(b"hello", QrErrorCorrection::Low).unwrap();
+        assert_eq!(qr.version(), 1);
+        // Five bytes still fit in a version 1 code at the highest level
+        assert_eq!(qr.error_correction(), QrErrorCorrection::High);
+        assert_eq!(qr.size(), 21);
+
+        // Finder patterns in three corners, and the always dark module
+        for (x, y) in [(0, 0), (20, 0), (0, 20), (8, 13)] {
+            assert!(qr.is_dark(x, y));
+        }
+        assert!(!qr.is_dark(7, 7));
+        assert!(!qr.is_dark(21, 0));
+
+        let magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel&tr=udp%3A%2F%2Fexplodie.org%3A6969").unwrap();
+        let qr = magnet.to_qr_code(QrErrorCorrection::Medium).unwrap();
+        assert_eq!(qr.version(), 6);
+        assert_eq!(qr.error_correction(), QrErrorCorrection::Medium);
+    }
+
+    #[test]
+    fn data_too_long_test() {
+        let data = vec![b'a'; 1300];
+        assert_eq!(
+            QrCode::encode(&data, QrErrorCorrection::High),
+            Err(QrError::DataTooLong {
+                length: 1300,
+                capacity: 1273,
+                error_correction: QrErrorCorrection::High,
+            })
+        );
+        let qr = QrCode::encode(&data, QrErrorCorrection::Quartile).unwrap();
+        assert_eq!(qr.version(), 36);
+        assert_eq!(qr.error_correction(), QrErrorCorrection::Quartile);
+    }
+
+    #[test]
+    fn render_test() {
+        let qr = QrCode::encode(b"hello", QrErrorCorrection::Low).unwrap();
+
+        let svg = qr.to_svg(4);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("viewBox=\"0 0 29 29\""));
+        assert!(svg.contains("M4,4h1v1h-1z"));
+        let dark = (0..21 * 21).filter(|i| qr.is_dark(i % 21, i / 21)).count();
+        assert_eq!(svg.matches('M').count(), dark);
+
+        let text = qr.to_unicode(1);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 12);
+        assert!(lines.iter().all(|line| line.chars().count() == 23));
+        assert!(lines[0].starts_with("█▀▀▀▀▀▀▀█"));
+        assert_eq!(qr.to_string(), qr.to_unicode(4));
+
+        let png = qr.to_png(2, 4).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        assert_eq!(&png[16..24], [0, 0, 0, 58, 0, 0, 0, 58]);
+
+        // 29 modules of 564 pixels fit, 565 do not, and overflowing sizes are rejected
+        assert!(qr.to_png(564, 4).is_ok());
+        assert_eq!(qr.to_png(565, 4), Err(QrError::ImageTooLarge));
+        assert_eq!(qr.to_png(4_000_000_000, 4), Err(QrError::ImageTooLarge));
+        assert_eq!(qr.to_png(1, u32::MAX), Err(QrError::ImageTooLarge));
+    }
+}
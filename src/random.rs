@@ -0,0 +1,42 @@
+//! Random numbers for transaction, peer and node IDs, without a dependency on a RNG crate
+
+use core::hash::{BuildHasher, Hasher};
+use std::collections::hash_map::RandomState;
+
+/// Get a random number
+///
+/// Every `RandomState` gets fresh random SipHash keys, so hashing nothing is enough. This is
+/// fine for protocol identifiers, but not for anything cryptographic.
+pub(crate) fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Peer ID prefix, in the Azureus style: client `MU`, version 3.0.0
+#[cfg(any(feature = "udp-tracker", feature = "http-tracker", feature = "metadata"))]
+const PEER_ID_PREFIX: &[u8; 8] = b"-MU3000-";
+
+/// Get a new random peer ID, made of the client prefix and 12 random digits
+///
+/// The digits are printable ASCII, as many trackers log peer IDs.
+#[cfg(any(feature = "udp-tracker", feature = "http-tracker", feature = "metadata"))]
+pub(crate) fn peer_id() -> [u8; 20] {
+    let mut peer_id = [0u8; 20];
+    peer_id[..8].copy_from_slice(PEER_ID_PREFIX);
+    for chunk in peer_id[8..].chunks_mut(6) {
+        let random = random_u64();
+        for (i, byte) in chunk.iter_mut().enumerate() {
+            *byte = b'0' + ((random >> (i * 8)) % 10) as u8;
+        }
+    }
+    peer_id
+}
+
+/// Get random bytes
+#[cfg(feature = "dht")]
+pub(crate) fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    for chunk in bytes.chunks_mut(8) {
+        chunk.copy_from_slice(&random_u64().to_le_bytes()[..chunk.len()]);
+    }
+    bytes
+}
@@ -0,0 +1,554 @@
+//! Removing private or unwanted information from magnets before sharing them
+//!
+//! [`Sanitizer`] drops (or redacts) the trackers and peers matching a blocklist, removes passkeys
+//! from tracker URLs, and can remove the `x.pe` peers and the `xs` source altogether. Everything
+//! it changes is listed in a [`SanitizeReport`].
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::net::IpAddr;
+
+use crate::encoding::{percent_decode, percent_encode};
+use crate::Magnet;
+
+/// Query parameters of tracker URLs that usually hold a passkey
+const PASSKEY_PARAMS: &[&str] = &[
+    "passkey",
+    "authkey",
+    "torrent_pass",
+    "pk",
+    "key",
+    "token",
+    "secret",
+    "auth",
+    "uid",
+];
+
+/// The host that redacted trackers point to (`.invalid` names never resolve)
+const REDACTED_HOST: &str = "redacted.invalid";
+
+/// A rule matching the trackers and peers to block
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum BlockRule {
+    /// A domain and all of its subdomains, lowercase
+    Domain(String),
+    /// An IP address range, given by an address and the number of leading bits to compare
+    Cidr(IpAddr, u8),
+    /// A pattern matched against the whole URL (or `host:port` of a peer), ignoring case, where
+    /// `*` matches any text
+    Pattern(String),
+}
+
+impl BlockRule {
+    /// Parse a rule, guessing its kind
+    ///
+    /// IP addresses, with or without a `/prefix`, are CIDR ranges. `*.example.com` and
+    /// `.example.com` are the domain `example.com`, and other rules with a `*` are patterns.
+    /// Anything else is a domain. Returns `None` for an empty rule or an invalid prefix.
+    pub fn parse(rule: &str) -> Option<Self> {
+        let rule = rule.trim();
+        if rule.is_empty() {
+            return None;
+        }
+
+        let (addr, prefix) = match rule.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (rule, None),
+        };
+        if let Ok(addr) = addr.parse::<IpAddr>() {
+            let max = if addr.is_ipv4() { 32 } else { 128 };
+            let prefix = match prefix {
+                Some(prefix) => prefix.parse().ok().filter(|&p| p <= max)?,
+                None => max,
+            };
+            return Some(BlockRule::Cidr(addr, prefix));
+        }
+
+        let domain = rule
+            .strip_prefix("*.")
+            .or_else(|| rule.strip_prefix('.'))
+            .unwrap_or(rule);
+        if domain.contains('*') || domain.contains('/') {
+            Some(BlockRule::Pattern(rule.to_ascii_lowercase()))
+        } else {
+            Some(BlockRule::Domain(
+                domain.trim_end_matches('.').to_ascii_lowercase(),
+            ))
+        }
+    }
+
+    /// Check whether a URL, or a peer's `host:port`, matches the rule
+    pub fn matches(&self, url: &str) -> bool {
+        match self {
+            BlockRule::Domain(domain) => host(url).is_some_and(|host| {
+                let host = host.trim_end_matches('.').to_ascii_lowercase();
+                host == *domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|sub| sub.ends_with('.'))
+            }),
+            BlockRule::Cidr(range, prefix) => host(url)
+                .and_then(|host| host.parse::<IpAddr>().ok())
+                .is_some_and(|addr| in_range(addr, *range, *prefix)),
+            BlockRule::Pattern(pattern) => glob_match(pattern, &url.to_ascii_lowercase()),
+        }
+    }
+}
+
+/// Settings for [`Magnet::sanitize`]
+///
+/// By default, only passkeys are removed.
+///
+/// # Example
+///
+/// ```
+/// use magnet_url::{BlockRule, Magnet, Sanitizer};
+///
+/// let mut magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&tr=http%3A%2F%2Ftracker.private.example%2Fannounce%3Fpasskey%3D0123abcd&tr=udp%3A%2F%2Ftracker.internal.example.com%3A6969&x.pe=10.0.0.5%3A6881").unwrap();
+///
+/// let sanitizer = Sanitizer::new()
+///     .block(BlockRule::parse("internal.example.com").unwrap())
+///     .remove_peers(true);
+/// let report = magnet.sanitize(&sanitizer);
+///
+/// assert_eq!(magnet.trackers(), ["http://tracker.private.example/announce"]);
+/// assert!(magnet.peers().is_empty());
+/// assert_eq!(report.blocked_trackers.len(), 1);
+/// assert_eq!(report.stripped_trackers.len(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sanitizer {
+    rules: Vec<BlockRule>,
+    redact: bool,
+    strip_passkeys: bool,
+    remove_peers: bool,
+    remove_source: bool,
+}
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            redact: false,
+            strip_passkeys: true,
+            remove_peers: false,
+            remove_source: false,
+        }
+    }
+}
+
+impl Sanitizer {
+    /// Create a sanitizer that only removes passkeys
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Block the trackers and peers matching `rule`
+    pub fn block(mut self, rule: BlockRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Block the rules of a list with one rule per line (see [`BlockRule::parse`])
+    ///
+    /// Blank lines, lines starting with `#` and invalid rules are skipped.
+    pub fn blocklist(mut self, text: &str) -> Self {
+        self.rules.extend(
+            text.lines()
+                .filter(|line| !line.trim_start().starts_with('#'))
+                .filter_map(BlockRule::parse),
+        );
+        self
+    }
+
+    /// Replace the host of blocked trackers with `redacted.invalid` instead of removing them
+    pub fn redact(mut self, redact: bool) -> Self {
+        self.redact = redact;
+        self
+    }
+
+    /// Remove passkey parameters (like `passkey`, `authkey` or `torrent_pass`) from tracker
+    /// URLs. Enabled by default.
+    pub fn strip_passkeys(mut self, strip_passkeys: bool) -> Self {
+        self.strip_passkeys = strip_passkeys;
+        self
+    }
+
+    /// Remove every `x.pe` peer, not only the blocked ones
+    pub fn remove_peers(mut self, remove_peers: bool) -> Self {
+        self.remove_peers = remove_peers;
+        self
+    }
+
+    /// Remove the `xs` source
+    pub fn remove_source(mut self, remove_source: bool) -> Self {
+        self.remove_source = remove_source;
+        self
+    }
+
+    fn blocking_rule(&self, url: &str) -> Option<&BlockRule> {
+        self.rules.iter().find(|rule| rule.matches(url))
+    }
+}
+
+/// What [`Magnet::sanitize`] changed. URLs are percent-decoded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SanitizeReport {
+    /// Trackers removed or redacted, with the rule they matched
+    pub blocked_trackers: Vec<(String, BlockRule)>,
+    /// Trackers that had passkeys removed, as they were before
+    pub stripped_trackers: Vec<String>,
+    /// Removed `x.pe` peers
+    pub removed_peers: Vec<String>,
+    /// The removed `xs` source
+    pub removed_source: Option<String>,
+}
+
+impl SanitizeReport {
+    /// Check whether the magnet was left unchanged
+    pub fn is_empty(&self) -> bool {
+        self.blocked_trackers.is_empty()
+            && self.stripped_trackers.is_empty()
+            && self.removed_peers.is_empty()
+            && self.removed_source.is_none()
+    }
+}
+
+impl Magnet {
+    /// Remove private or unwanted trackers, peers and sources, as set up in `sanitizer`
+    pub fn sanitize(&mut self, sanitizer: &Sanitizer) -> SanitizeReport {
+        let mut report = SanitizeReport::default();
+
+        let mut trackers = Vec::with_capacity(self.trackers.len());
+        for tracker in self.trackers.drain(..) {
+            let url = percent_decode(&tracker).unwrap_or_else(|| tracker.clone());
+            if let Some(rule) = sanitizer.blocking_rule(&url) {
+                if sanitizer.redact {
+                    trackers.push(percent_encode(&redact(&url)));
+                }
+                report.blocked_trackers.push((url, rule.clone()));
+                continue;
+            }
+            match sanitizer
+                .strip_passkeys
+                .then(|| strip_passkeys(&url))
+                .flatten()
+            {
+                Some(stripped) => {
+                    trackers.push(percent_encode(&stripped));
+                    report.stripped_trackers.push(url);
+                }
+                None => trackers.push(tracker),
+            }
+        }
+        self.trackers = trackers;
+
+        let (removed, kept) = self.peers.drain(..).partition(|peer| {
+            let peer = percent_decode(peer).unwrap_or_else(|| peer.clone());
+            sanitizer.remove_peers || sanitizer.blocking_rule(&peer).is_some()
+        });
+        self.peers = kept;
+        report.removed_peers = removed
+            .into_iter()
+            .map(|peer: String| percent_decode(&peer).unwrap_or(peer))
+            .collect();
+
+        if sanitizer.remove_source {
+            report.removed_source = self
+                .source
+                .take()
+                .map(|source| percent_decode(&source).unwrap_or(source));
+        }
+        report
+    }
+}
+
+/// Get the host of a URL or of a `host:port` address, without the brackets of IPv6 addresses
+fn host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = if let Some(bracketed) = authority.strip_prefix('[') {
+        bracketed.split_once(']')?.0
+    } else if authority.matches(':').count() > 1 {
+        // A bare IPv6 address, without a port
+        authority
+    } else {
+        authority.split(':').next()?
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+fn in_range(addr: IpAddr, range: IpAddr, prefix: u8) -> bool {
+    match (addr, range) {
+        (IpAddr::V4(addr), IpAddr::V4(range)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(addr) & mask == u32::from(range) & mask
+        }
+        (IpAddr::V6(addr), IpAddr::V6(range)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(addr) & mask == u128::from(range) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Match `text` against a pattern where `*` matches any text
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and the text position it currently matches up to
+    let mut star = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the `*` match one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Remove passkey parameters from a URL's query, or `None` if it has none
+fn strip_passkeys(url: &str) -> Option<String> {
+    let (base, rest) = url.split_once('?')?;
+    let (query, fragment) = match rest.split_once('#') {
+        Some((query, fragment)) => (query, Some(fragment)),
+        None => (rest, None),
+    };
+
+    let is_passkey = |param: &&str| {
+        let name = param.split('=').next().unwrap_or_default();
+        PASSKEY_PARAMS
+            .iter()
+            .any(|passkey| name.eq_ignore_ascii_case(passkey))
+    };
+    let params: Vec<&str> = query.split('&').collect();
+    if !params.iter().any(is_passkey) {
+        return None;
+    }
+
+    let kept: Vec<&str> = params
+        .into_iter()
+        .filter(|param| !param.is_empty() && !is_passkey(param))
+        .collect();
+    let mut url = base.to_string();
+    if !kept.is_empty() {
+        url.push('?');
+        url.push_str(&kept.join("&"));
+    }
+    if let Some(fragment) = fragment {
+        url.push('#');
+        url.push_str(fragment);
+    }
+    Some(url)
+}
+
+/// Replace the host (and credentials) of a URL, keeping its scheme and port
+fn redact(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return REDACTED_HOST.to_string();
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let port = host(url)
+        .and_then(|host| authority.rsplit_once(host))
+        .map(|(_, after)| after.trim_start_matches(']'))
+        .filter(|after| after.starts_with(':'))
+        .unwrap_or_default();
+    alloc::format!("{}://{}{}", scheme, REDACTED_HOST, port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_match, host, redact, strip_passkeys, BlockRule, Sanitizer};
+    use crate::Magnet;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use core::net::IpAddr;
+
+    #[test]
+    fn parse_rule_test() {
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+        assert_eq!(
+            BlockRule::parse("10.0.0.0/8"),
+            Some(BlockRule::Cidr(ip("10.0.0.0"), 8))
+        );
+        assert_eq!(
+            BlockRule::parse("192.168.1.1"),
+            Some(BlockRule::Cidr(ip("192.168.1.1"), 32))
+        );
+        assert_eq!(
+            BlockRule::parse("fd00::/8"),
+            Some(BlockRule::Cidr(ip("fd00::"), 8))
+        );
+        assert_eq!(BlockRule::parse("10.0.0.0/33"), None);
+        assert_eq!(
+            BlockRule::parse(" *.Example.COM "),
+            Some(BlockRule::Domain("example.com".into()))
+        );
+        assert_eq!(
+            BlockRule::parse(".example.com."),
+            Some(BlockRule::Domain("example.com".into()))
+        );
+        assert_eq!(
+            BlockRule::parse("*/announce.php*"),
+            Some(BlockRule::Pattern("*/announce.php*".into()))
+        );
+        assert_eq!(BlockRule::parse("  "), None);
+    }
+
+    #[test]
+    fn matches_test() {
+        let domain = BlockRule::parse("example.com").unwrap();
+        assert!(domain.matches("udp://example.com:6969"));
+        assert!(domain.matches("http://tracker.EXAMPLE.com./announce"));
+        assert!(domain.matches("tracker.example.com:6881"));
+        assert!(!domain.matches("udp://notexample.com:6969"));
+        assert!(!domain.matches("udp://example.com.evil.net:6969"));
+
+        let cidr = BlockRule::parse("10.0.0.0/8").unwrap();
+        assert!(cidr.matches("udp://10.1.2.3:6969/announce"));
+        assert!(cidr.matches("10.255.0.1:6881"));
+        assert!(!cidr.matches("udp://11.0.0.1:6969"));
+        assert!(!cidr.matches("udp://[::1]:6969"));
+        assert!(BlockRule::parse("0.0.0.0/0").unwrap().matches("1.2.3.4:1"));
+
+        let cidr = BlockRule::parse("fd00::/8").unwrap();
+        assert!(cidr.matches("http://[fd12:3456::1]:8080/announce"));
+        assert!(cidr.matches("[fdff::1]:6881"));
+        assert!(!cidr.matches("http://[fe80::1]/announce"));
+
+        let pattern = BlockRule::parse("*/announce.php?*").unwrap();
+        assert!(pattern.matches("http://tracker.example.com/announce.php?passkey=1"));
+        assert!(!pattern.matches("http://tracker.example.com/announce"));
+    }
+
+    #[test]
+    fn helpers_test() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(glob_match("*abc", "ababc"));
+        assert!(!glob_match("a*b", "acbx"));
+        assert!(!glob_match("abc", "ab"));
+
+        assert_eq!(host("udp://user:pw@Tracker.com:1/x"), Some("Tracker.com"));
+        assert_eq!(host("http://[::1]:80/"), Some("::1"));
+        assert_eq!(host("::1"), Some("::1"));
+        assert_eq!(host("udp://"), None);
+
+        assert_eq!(
+            strip_passkeys("http://t.example/announce?passkey=abc&info=1&PK=2#top").as_deref(),
+            Some("http://t.example/announce?info=1#top")
+        );
+        assert_eq!(
+            strip_passkeys("http://t.example/announce?torrent_pass=abc").as_deref(),
+            Some("http://t.example/announce")
+        );
+        assert_eq!(strip_passkeys("http://t.example/announce?info=1"), None);
+        assert_eq!(strip_passkeys("udp://t.example:1"), None);
+
+        assert_eq!(
+            redact("http://user@tracker.example.com:8080/abc/announce?passkey=1"),
+            "http://redacted.invalid:8080"
+        );
+        assert_eq!(
+            redact("udp://[fd00::1]:6969"),
+            "udp://redacted.invalid:6969"
+        );
+        assert_eq!(
+            redact("https://tracker.example.com/x"),
+            "https://redacted.invalid"
+        );
+    }
+
+    #[test]
+    fn sanitize_test() {
+        let url = "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10\
+            &tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337%2Fannounce\
+            &tr=http%3A%2F%2Ftracker.private.example%2Fannounce.php%3Fpasskey%3Dabc%26uploaded%3D0\
+            &tr=udp%3A%2F%2F10.0.0.1%3A6969\
+            &tr=http%3A%2F%2Ftracker.corp.example.com%2Fannounce\
+            &x.pe=10.0.0.5%3A6881&x.pe=203.0.113.7%3A6881\
+            &xs=http%3A%2F%2Fcache.example.com%2Fsintel.torrent%3Ftoken%3Dsecret";
+        let sanitizer = Sanitizer::new().blocklist(
+            "# internal networks\n10.0.0.0/8\n\ncorp.example.com\nnot/a/valid/0.0.0.0/99\n",
+        );
+
+        let mut magnet = Magnet::new(url).unwrap();
+        let report = magnet.sanitize(&sanitizer);
+        assert_eq!(
+            magnet.trackers(),
+            [
+                "udp%3A%2F%2Ftracker.opentrackr.org%3A1337%2Fannounce",
+                "http://tracker.private.example/announce.php?uploaded%3D0",
+            ]
+        );
+        assert_eq!(magnet.peers(), ["203.0.113.7%3A6881"]);
+        assert!(magnet.source().is_some());
+
+        let blocked: Vec<&str> = report
+            .blocked_trackers
+            .iter()
+            .map(|(url, _)| url.as_str())
+            .collect();
+        assert_eq!(
+            blocked,
+            [
+                "udp://10.0.0.1:6969",
+                "http://tracker.corp.example.com/announce"
+            ]
+        );
+        assert_eq!(
+            report.blocked_trackers[1].1,
+            BlockRule::Domain("corp.example.com".into())
+        );
+        assert_eq!(
+            report.stripped_trackers,
+            ["http://tracker.private.example/announce.php?passkey=abc&uploaded=0"]
+        );
+        assert_eq!(report.removed_peers, ["10.0.0.5:6881"]);
+        assert_eq!(report.removed_source, None);
+
+        // Sanitizing again changes nothing
+        assert!(magnet.sanitize(&sanitizer).is_empty());
+
+        // Redacting, and removing every peer and the source
+        let sanitizer = sanitizer
+            .redact(true)
+            .strip_passkeys(false)
+            .remove_peers(true)
+            .remove_source(true);
+        let mut magnet = Magnet::new(url).unwrap();
+        let report = magnet.sanitize(&sanitizer);
+        assert_eq!(magnet.trackers().len(), 4);
+        assert_eq!(
+            magnet.trackers()[1],
+            "http%3A%2F%2Ftracker.private.example%2Fannounce.php%3Fpasskey%3Dabc%26uploaded%3D0"
+        );
+        assert_eq!(magnet.trackers()[2], "udp://redacted.invalid:6969");
+        assert_eq!(magnet.trackers()[3], "http://redacted.invalid");
+        assert!(magnet.peers().is_empty());
+        assert_eq!(magnet.source(), None);
+        assert_eq!(report.removed_peers.len(), 2);
+        assert_eq!(
+            report.removed_source.as_deref(),
+            Some("http://cache.example.com/sintel.torrent?token=secret")
+        );
+        assert!(report.stripped_trackers.is_empty());
+
+        // The sanitized magnet is still a valid magnet URL
+        let text = magnet.to_string();
+        assert_eq!(Magnet::new(&text).unwrap().trackers(), magnet.trackers());
+    }
+}
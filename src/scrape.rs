@@ -0,0 +1,447 @@
+//! Scraping every tracker of a magnet at once, enabled by the `udp-tracker` and `http-tracker`
+//! features
+//!
+//! Each tracker is scraped on its own thread, so one slow tracker doesn't hold up the others, and
+//! whatever arrived before the deadline is reported. The trackers' statistics overlap (a seeder
+//! usually announces to every tracker), so they are combined by taking the largest numbers
+//! instead of adding them up.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+use std::io;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Instant, SystemTime};
+
+use crate::encoding::percent_decode;
+#[cfg(feature = "http-tracker")]
+use crate::http_tracker::{HttpTracker, HttpTrackerError};
+#[cfg(feature = "udp-tracker")]
+use crate::udp_tracker::{UdpTracker, UdpTrackerError};
+use crate::{InfoHash, Magnet};
+
+pub use crate::tracker::ScrapeStats;
+
+/// The ways scraping one tracker can fail
+#[derive(Debug)]
+pub enum ScrapeError {
+    /// The UDP tracker failed
+    #[cfg(feature = "udp-tracker")]
+    Udp(UdpTrackerError),
+    /// The HTTP tracker failed
+    #[cfg(feature = "http-tracker")]
+    Http(HttpTrackerError),
+    /// The tracker did not answer before the deadline
+    Deadline,
+    /// No thread could be started to scrape the tracker
+    Thread(io::Error),
+}
+
+impl fmt::Display for ScrapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "udp-tracker")]
+            ScrapeError::Udp(err) => err.fmt(f),
+            #[cfg(feature = "http-tracker")]
+            ScrapeError::Http(err) => err.fmt(f),
+            ScrapeError::Deadline => write!(f, "tracker did not answer before the deadline"),
+            ScrapeError::Thread(err) => write!(f, "could not start a thread: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ScrapeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "udp-tracker")]
+            ScrapeError::Udp(err) => Some(err),
+            #[cfg(feature = "http-tracker")]
+            ScrapeError::Http(err) => Some(err),
+            ScrapeError::Deadline => None,
+            ScrapeError::Thread(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(feature = "udp-tracker")]
+impl From<UdpTrackerError> for ScrapeError {
+    fn from(err: UdpTrackerError) -> Self {
+        ScrapeError::Udp(err)
+    }
+}
+
+#[cfg(feature = "http-tracker")]
+impl From<HttpTrackerError> for ScrapeError {
+    fn from(err: HttpTrackerError) -> Self {
+        ScrapeError::Http(err)
+    }
+}
+
+/// The outcome of scraping one tracker
+#[derive(Debug)]
+pub struct TrackerScrape {
+    /// The tracker URL, percent-decoded
+    pub url: String,
+    /// The tracker's statistics, or why it could not be scraped
+    pub result: Result<ScrapeStats, ScrapeError>,
+    /// When the tracker answered, or when it failed or the deadline passed
+    pub time: SystemTime,
+}
+
+/// The outcome of scraping every supported tracker for one torrent
+#[derive(Debug)]
+pub struct ScrapeReport {
+    /// The info hash that was scraped
+    pub info_hash: InfoHash,
+    /// One result per tracker, in the order the trackers were given
+    pub trackers: Vec<TrackerScrape>,
+}
+
+/// The statistics of every tracker that answered, combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregateStats {
+    /// The largest number of seeders any tracker reported
+    pub seeders: u32,
+    /// The largest number of completed downloads any tracker reported
+    pub completed: u32,
+    /// The largest number of leechers any tracker reported
+    pub leechers: u32,
+    /// When the last answer arrived
+    pub latest: SystemTime,
+    /// Number of trackers that answered
+    pub trackers: usize,
+}
+
+impl ScrapeReport {
+    /// Combine the statistics of the trackers that answered, or `None` if none did
+    pub fn aggregate(&self) -> Option<AggregateStats> {
+        self.trackers
+            .iter()
+            .filter_map(|tracker| Some((tracker.result.as_ref().ok()?, tracker.time)))
+            .fold(None, |aggregate, (stats, time)| {
+                Some(match aggregate {
+                    None => AggregateStats {
+                        seeders: stats.seeders,
+                        completed: stats.completed,
+                        leechers: stats.leechers,
+                        latest: time,
+                        trackers: 1,
+                    },
+                    Some(aggregate) => AggregateStats {
+                        seeders: aggregate.seeders.max(stats.seeders),
+                        completed: aggregate.completed.max(stats.completed),
+                        leechers: aggregate.leechers.max(stats.leechers),
+                        latest: aggregate.latest.max(time),
+                        trackers: aggregate.trackers + 1,
+                    },
+                })
+            })
+    }
+
+    /// Get the trackers that failed, with their errors
+    pub fn errors(&self) -> impl Iterator<Item = (&str, &ScrapeError)> {
+        self.trackers
+            .iter()
+            .filter_map(|tracker| Some((tracker.url.as_str(), tracker.result.as_ref().err()?)))
+    }
+}
+
+/// Scrape `info_hash` from every supported tracker in `trackers` at the same time, waiting at
+/// most `deadline`
+///
+/// Tracker URLs may be percent-encoded, as they appear in a magnet. Duplicates and trackers of
+/// unsupported protocols (or of a protocol whose feature is disabled, like `wss://`) are
+/// skipped, and so are `https://` trackers, which the built-in HTTP client can't talk to (see
+/// [`HttpTracker::scrape_with`](crate::http_tracker::HttpTracker::scrape_with) for those).
+/// Trackers that haven't answered by the deadline get a [`ScrapeError::Deadline`]; their threads
+/// are left to finish in the background.
+pub fn scrape_trackers<'a>(
+    info_hash: InfoHash,
+    trackers: impl IntoIterator<Item = &'a str>,
+    deadline: Duration,
+) -> ScrapeReport {
+    let mut urls: Vec<String> = Vec::new();
+    for url in trackers {
+        if let Some(url) = percent_decode(url) {
+            if is_supported(&url) && !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+
+    let start = Instant::now();
+    let mut results: Vec<Option<(Result<ScrapeStats, ScrapeError>, SystemTime)>> =
+        urls.iter().map(|_| None).collect();
+    let mut remaining = urls.len();
+    let (sender, receiver) = mpsc::channel();
+    for (i, url) in urls.iter().enumerate() {
+        let url = url.clone();
+        let sender = sender.clone();
+        let spawned = thread::Builder::new()
+            .name(String::from("scrape"))
+            .spawn(move || {
+                let result = scrape_one(&url, info_hash, deadline);
+                // The receiver is gone if the deadline passed
+                let _ = sender.send((i, result, SystemTime::now()));
+            });
+        if let Err(err) = spawned {
+            results[i] = Some((Err(ScrapeError::Thread(err)), SystemTime::now()));
+            remaining -= 1;
+        }
+    }
+    drop(sender);
+
+    while remaining > 0 {
+        let timeout = deadline.saturating_sub(start.elapsed());
+        let Ok((i, result, time)) = receiver.recv_timeout(timeout) else {
+            break;
+        };
+        results[i] = Some((result, time));
+        remaining -= 1;
+    }
+
+    let now = SystemTime::now();
+    let trackers = urls
+        .into_iter()
+        .zip(results)
+        .map(|(url, result)| {
+            let (result, time) = result.unwrap_or((Err(ScrapeError::Deadline), now));
+            TrackerScrape { url, result, time }
+        })
+        .collect();
+    ScrapeReport {
+        info_hash,
+        trackers,
+    }
+}
+
+impl Magnet {
+    /// Scrape every supported tracker of the magnet at the same time, waiting at most
+    /// `deadline` (see [`scrape_trackers`])
+    ///
+    /// Returns `None` if the magnet has no BitTorrent info hash.
+    pub fn scrape_trackers(&self, deadline: Duration) -> Option<ScrapeReport> {
+        let trackers = self.trackers().iter().map(String::as_str);
+        Some(scrape_trackers(self.info_hash()?, trackers, deadline))
+    }
+}
+
+fn scheme(url: &str) -> Option<&str> {
+    url.split_once("://").map(|(scheme, _)| scheme)
+}
+
+fn is_supported(url: &str) -> bool {
+    match scheme(url) {
+        #[cfg(feature = "udp-tracker")]
+        Some(scheme) if scheme.eq_ignore_ascii_case("udp") => true,
+        #[cfg(feature = "http-tracker")]
+        Some(scheme) if scheme.eq_ignore_ascii_case("http") => true,
+        _ => false,
+    }
+}
+
+/// Scrape one tracker, with timeouts that end around the deadline
+fn scrape_one(
+    url: &str,
+    info_hash: InfoHash,
+    deadline: Duration,
+) -> Result<ScrapeStats, ScrapeError> {
+    let stats = match scheme(url) {
+        #[cfg(feature = "udp-tracker")]
+        Some(scheme) if scheme.eq_ignore_ascii_case("udp") => {
+            // Retransmit once halfway through the deadline, in case a packet was lost
+            let mut tracker = UdpTracker::with_decoded_url(url)?.timeout(deadline / 2).max_retries(1);
+            tracker.scrape(&[info_hash])?
+        }
+        #[cfg(feature = "http-tracker")]
+        Some(_) => HttpTracker::with_decoded_url(url)?
+            .timeout(deadline)
+            .scrape(&[info_hash])?,
+        _ => unreachable!("unsupported trackers are skipped"),
+    };
+    Ok(stats.into_iter().next().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scrape_trackers, ScrapeError};
+    use crate::{InfoHash, Magnet};
+    use alloc::format;
+    use alloc::string::String;
+    use core::time::Duration;
+
+    const HASH: &str = "08ada5a7a6183aae1e09d831df6748d566095a10";
+
+    /// A UDP tracker that answers `connect` and `scrape` requests with `stats` until the test ends
+    #[cfg(feature = "udp-tracker")]
+    fn fake_udp_tracker(stats: super::ScrapeStats) -> String {
+        use std::net::UdpSocket;
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 2048];
+            while let Ok((len, from)) = socket.recv_from(&mut buffer) {
+                let request = &buffer[..len];
+                let mut response = request[8..16].to_vec();
+                if request[8..12] == 0u32.to_be_bytes() {
+                    response.extend_from_slice(&42u64.to_be_bytes());
+                } else {
+                    response.extend_from_slice(&stats.seeders.to_be_bytes());
+                    response.extend_from_slice(&stats.completed.to_be_bytes());
+                    response.extend_from_slice(&stats.leechers.to_be_bytes());
+                }
+                socket.send_to(&response, from).unwrap();
+            }
+        });
+        format!("udp://{}/announce", addr)
+    }
+
+    /// An HTTP tracker that answers one request with `body`
+    #[cfg(feature = "http-tracker")]
+    fn fake_http_tracker(body: alloc::vec::Vec<u8>) -> String {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = alloc::vec::Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let len = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..len]);
+            }
+            assert!(request.starts_with(b"GET /scrape?passkey=a%2Fb&info_hash=%08%AD"));
+            let header = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len());
+            stream.write_all(header.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+        });
+        format!("http://{}/announce?passkey=a%2Fb", addr)
+    }
+
+    #[cfg(all(feature = "udp-tracker", feature = "http-tracker"))]
+    fn scrape_body(seeders: u32, completed: u32, leechers: u32) -> alloc::vec::Vec<u8> {
+        let mut body = b"d5:filesd20:".to_vec();
+        body.extend_from_slice(InfoHash::parse(HASH).unwrap().as_bytes());
+        body.extend_from_slice(
+            format!(
+                "d8:completei{}e10:downloadedi{}e10:incompletei{}eeee",
+                seeders, completed, leechers
+            )
+            .as_bytes(),
+        );
+        body
+    }
+
+    #[cfg(all(feature = "udp-tracker", feature = "http-tracker"))]
+    #[test]
+    fn magnet_scrape_test() {
+        use super::ScrapeStats;
+        use std::time::SystemTime;
+
+        let udp = fake_udp_tracker(ScrapeStats {
+            seeders: 10,
+            completed: 100,
+            leechers: 3,
+        });
+        let http = fake_http_tracker(scrape_body(7, 120, 5));
+        let magnet = Magnet::new(&format!(
+            "magnet:?xt=urn:btih:{}&tr={}&tr={}&tr={}&tr=wss%3A%2F%2Ftracker.example.com",
+            HASH,
+            crate::encoding::percent_encode(&udp),
+            crate::encoding::percent_encode(&http),
+            udp,
+        ))
+        .unwrap();
+
+        let before = SystemTime::now();
+        let report = magnet.scrape_trackers(Duration::from_secs(5)).unwrap();
+        assert_eq!(report.info_hash, magnet.info_hash().unwrap());
+
+        // The duplicate UDP tracker and the WebSocket tracker are skipped
+        assert_eq!(report.trackers.len(), 2);
+        assert_eq!(report.trackers[0].url, udp);
+        assert_eq!(report.trackers[1].url, http);
+        assert_eq!(
+            *report.trackers[1].result.as_ref().unwrap(),
+            ScrapeStats {
+                seeders: 7,
+                completed: 120,
+                leechers: 5,
+            }
+        );
+        assert_eq!(report.errors().count(), 0);
+
+        let aggregate = report.aggregate().unwrap();
+        assert_eq!(
+            (aggregate.seeders, aggregate.completed, aggregate.leechers),
+            (10, 120, 5)
+        );
+        assert_eq!(aggregate.trackers, 2);
+        assert!(aggregate.latest >= before);
+        assert!(report.trackers.iter().all(|t| t.time <= aggregate.latest));
+    }
+
+    #[cfg(feature = "udp-tracker")]
+    #[test]
+    fn deadline_test() {
+        use super::ScrapeStats;
+        use std::net::UdpSocket;
+        use std::time::Instant;
+
+        let answering = fake_udp_tracker(ScrapeStats {
+            seeders: 1,
+            completed: 2,
+            leechers: 3,
+        });
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let silent_url = format!("udp://{}", silent.local_addr().unwrap());
+
+        let start = Instant::now();
+        let info_hash = InfoHash::parse(HASH).unwrap();
+        let trackers = [answering.as_str(), silent_url.as_str()];
+        let report = scrape_trackers(info_hash, trackers, Duration::from_millis(400));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        assert!(report.trackers[0].result.is_ok());
+        let errors: alloc::vec::Vec<_> = report.errors().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, silent_url);
+        assert!(matches!(errors[0].1, ScrapeError::Deadline));
+        assert_eq!(report.aggregate().unwrap().seeders, 1);
+    }
+
+    #[cfg(feature = "http-tracker")]
+    #[test]
+    fn failures_test() {
+        let failing = fake_http_tracker(b"d14:failure reason9:not founde".to_vec());
+        let failing = crate::encoding::percent_encode(&failing);
+        let info_hash = InfoHash::parse(HASH).unwrap();
+        let report = scrape_trackers(
+            info_hash,
+            [failing.as_str(), "https://tracker.example.com/announce"],
+            Duration::from_secs(5),
+        );
+
+        // The HTTPS tracker is skipped, as it could only fail
+        assert_eq!(report.trackers.len(), 1);
+
+        assert_eq!(report.errors().count(), 1);
+        assert!(matches!(
+            report.trackers[0].result,
+            Err(ScrapeError::Http(crate::http_tracker::HttpTrackerError::Tracker(ref m))) if m == "not found"
+        ));
+        assert_eq!(report.aggregate(), None);
+    }
+
+    #[test]
+    fn no_info_hash_test() {
+        let magnet =
+            Magnet::new("magnet:?xt=urn:sha1:2aae6c35c94fcfb415dbe95f408b9ce91ee846ed").unwrap();
+        assert!(magnet.scrape_trackers(Duration::from_secs(1)).is_none());
+    }
+}
@@ -0,0 +1,305 @@
+//! Trimming magnets to fit a length budget, for QR codes, SMS and chat platforms
+//!
+//! [`Shortener`] keeps every exact topic, then adds back the other parameters from the most
+//! useful to the least while the link still fits. Everything it drops is listed in a
+//! [`ShortenReport`].
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::encoding::percent_decode;
+use crate::{BlockRule, Magnet};
+
+/// Settings for [`Magnet::shorten`]
+///
+/// Parameters are kept in this order, as long as they fit: `dn`, `xl`, the preferred trackers
+/// and web seed, the other trackers and web seed, then `xs`, `as`, `mt`, `kt` and the `x.pe`
+/// peers. Trackers are taken in the magnet's order, so sorting them by health first (with
+/// `TrackerHealth::sort_trackers`) keeps the healthiest ones.
+///
+/// # Example
+///
+/// ```
+/// use magnet_url::{BlockRule, Magnet, Shortener};
+///
+/// let mut magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel&tr=udp%3A%2F%2Fexplodie.org%3A6969&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337&tr=wss%3A%2F%2Ftracker.btorrent.xyz").unwrap();
+///
+/// let shortener = Shortener::new(120).prefer(BlockRule::parse("opentrackr.org").unwrap());
+/// let report = magnet.shorten(&shortener);
+///
+/// assert!(report.fits);
+/// assert!(magnet.to_string().len() <= 120);
+/// assert_eq!(magnet.trackers(), ["udp%3A%2F%2Ftracker.opentrackr.org%3A1337"]);
+/// assert_eq!(report.dropped.len(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortener {
+    max_length: usize,
+    preferred: Vec<BlockRule>,
+}
+
+impl Shortener {
+    /// Shorten magnets to at most `max_length` bytes
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            max_length,
+            preferred: Vec::new(),
+        }
+    }
+
+    /// Keep the trackers and web seed matching `rule` before the others
+    pub fn prefer(mut self, rule: BlockRule) -> Self {
+        self.preferred.push(rule);
+        self
+    }
+
+    fn is_preferred(&self, url: &str) -> bool {
+        let url = percent_decode(url).unwrap_or_else(|| url.to_string());
+        self.preferred.iter().any(|rule| rule.matches(&url))
+    }
+}
+
+/// What [`Magnet::shorten`] dropped
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShortenReport {
+    /// The parameters removed, as (key, value) pairs like `("tr", url)`, from the most useful
+    /// to the least. Values are percent-decoded.
+    pub dropped: Vec<(String, String)>,
+    /// Whether the magnet now fits the budget; `false` when its exact topics alone are longer
+    pub fits: bool,
+}
+
+impl ShortenReport {
+    /// Check whether the magnet was left unchanged
+    pub fn is_empty(&self) -> bool {
+        self.dropped.is_empty()
+    }
+}
+
+/// A parameter that can be dropped, with its value as it appears in the link
+enum Param {
+    DisplayName(String),
+    Length(u64),
+    Tracker(String),
+    WebSeed(String),
+    Source(String),
+    AcceptableSource(String),
+    Manifest(String),
+    SearchKeywords(String),
+    Peer(String),
+}
+
+impl Param {
+    fn key(&self) -> &'static str {
+        match self {
+            Param::DisplayName(_) => "dn",
+            Param::Length(_) => "xl",
+            Param::Tracker(_) => "tr",
+            Param::WebSeed(_) => "ws",
+            Param::Source(_) => "xs",
+            Param::AcceptableSource(_) => "as",
+            Param::Manifest(_) => "mt",
+            Param::SearchKeywords(_) => "kt",
+            Param::Peer(_) => "x.pe",
+        }
+    }
+
+    fn decoded_value(&self) -> String {
+        match self {
+            Param::Length(length) => length.to_string(),
+            Param::DisplayName(value)
+            | Param::Tracker(value)
+            | Param::WebSeed(value)
+            | Param::Source(value)
+            | Param::AcceptableSource(value)
+            | Param::Manifest(value)
+            | Param::SearchKeywords(value)
+            | Param::Peer(value) => percent_decode(value).unwrap_or_else(|| value.clone()),
+        }
+    }
+
+    fn add_to(&self, magnet: &mut Magnet) {
+        match self {
+            Param::DisplayName(value) => magnet.display_name = Some(value.clone()),
+            Param::Length(length) => magnet.length = Some(*length),
+            Param::Tracker(value) => magnet.trackers.push(value.clone()),
+            Param::WebSeed(value) => magnet.web_seed = Some(value.clone()),
+            Param::Source(value) => magnet.source = Some(value.clone()),
+            Param::AcceptableSource(value) => magnet.acceptable_source = Some(value.clone()),
+            Param::Manifest(value) => magnet.manifest = Some(value.clone()),
+            Param::SearchKeywords(value) => magnet.search_keywords = Some(value.clone()),
+            Param::Peer(value) => magnet.peers.push(value.clone()),
+        }
+    }
+
+    fn remove_from(&self, magnet: &mut Magnet) {
+        match self {
+            Param::DisplayName(_) => magnet.display_name = None,
+            Param::Length(_) => magnet.length = None,
+            Param::Tracker(_) => {
+                magnet.trackers.pop();
+            }
+            Param::WebSeed(_) => magnet.web_seed = None,
+            Param::Source(_) => magnet.source = None,
+            Param::AcceptableSource(_) => magnet.acceptable_source = None,
+            Param::Manifest(_) => magnet.manifest = None,
+            Param::SearchKeywords(_) => magnet.search_keywords = None,
+            Param::Peer(_) => {
+                magnet.peers.pop();
+            }
+        }
+    }
+}
+
+impl Magnet {
+    /// Drop the least useful parameters until the link is at most as long as the budget of
+    /// `shortener` (see [`Shortener`] for the order they are kept in)
+    ///
+    /// Exact topics are always kept, even if they alone are over the budget. A parameter that
+    /// doesn't fit is skipped, so a shorter one after it can still be kept.
+    pub fn shorten(&mut self, shortener: &Shortener) -> ShortenReport {
+        let mut report = ShortenReport::default();
+        if self.to_string().len() <= shortener.max_length {
+            report.fits = true;
+            return report;
+        }
+
+        // Candidates from the most useful to the least
+        let mut params = Vec::new();
+        params.extend(self.display_name.take().map(Param::DisplayName));
+        params.extend(self.length.take().map(Param::Length));
+        let trackers = core::mem::take(&mut self.trackers);
+        let web_seed = self.web_seed.take();
+        let (preferred, others): (Vec<String>, Vec<String>) = trackers
+            .into_iter()
+            .partition(|tracker| shortener.is_preferred(tracker));
+        params.extend(preferred.into_iter().map(Param::Tracker));
+        let (preferred_seed, other_seed) = match web_seed {
+            Some(seed) if shortener.is_preferred(&seed) => (Some(seed), None),
+            seed => (None, seed),
+        };
+        params.extend(preferred_seed.map(Param::WebSeed));
+        params.extend(others.into_iter().map(Param::Tracker));
+        params.extend(other_seed.map(Param::WebSeed));
+        params.extend(self.source.take().map(Param::Source));
+        params.extend(self.acceptable_source.take().map(Param::AcceptableSource));
+        params.extend(self.manifest.take().map(Param::Manifest));
+        params.extend(self.search_keywords.take().map(Param::SearchKeywords));
+        params.extend(self.peers.drain(..).map(Param::Peer));
+
+        for param in params {
+            param.add_to(self);
+            if self.to_string().len() > shortener.max_length {
+                param.remove_from(self);
+                report
+                    .dropped
+                    .push((param.key().to_string(), param.decoded_value()));
+            }
+        }
+
+        report.fits = self.to_string().len() <= shortener.max_length;
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    const HASH: &str = "08ada5a7a6183aae1e09d831df6748d566095a10";
+
+    fn magnet() -> Magnet {
+        Magnet::new(&format!(
+            "magnet:?xt=urn:btih:{}&dn=Sintel&xl=129241752&tr=udp%3A%2F%2Fexplodie.org%3A6969&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337&tr=wss%3A%2F%2Ftracker.btorrent.xyz&ws=https%3A%2F%2Fwebtorrent.io%2Ftorrents%2F&x.pe=10.0.0.1%3A6881&kt=sintel",
+            HASH
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn fits_already_test() {
+        let mut magnet = magnet();
+        let original = magnet.clone();
+        let report = magnet.shorten(&Shortener::new(1000));
+        assert!(report.fits);
+        assert!(report.is_empty());
+        assert_eq!(magnet, original);
+    }
+
+    #[test]
+    fn keeps_most_useful_test() {
+        let mut magnet = magnet();
+        let report = magnet.shorten(&Shortener::new(130));
+        assert!(report.fits);
+        assert_eq!(
+            magnet.to_string(),
+            format!(
+                "magnet:?xt=urn:btih:{}&dn=Sintel&xl=129241752&tr=udp%3A%2F%2Fexplodie.org%3A6969&kt=sintel",
+                HASH
+            )
+        );
+        assert_eq!(
+            report.dropped,
+            [
+                ("tr", "udp://tracker.opentrackr.org:1337"),
+                ("tr", "wss://tracker.btorrent.xyz"),
+                ("ws", "https://webtorrent.io/torrents/"),
+                ("x.pe", "10.0.0.1:6881"),
+            ]
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+        );
+    }
+
+    #[test]
+    fn skips_params_that_do_not_fit_test() {
+        // Without room for the web seed, it is dropped, but the shorter search keywords and
+        // peer that come after it still fit
+        let mut magnet = magnet();
+        let budget =
+            magnet.to_string().len() - "&ws=https%3A%2F%2Fwebtorrent.io%2Ftorrents%2F".len();
+        let report = magnet.shorten(&Shortener::new(budget));
+        assert!(report.fits);
+        assert_eq!(
+            report.dropped,
+            [(
+                "ws".to_string(),
+                "https://webtorrent.io/torrents/".to_string()
+            )]
+        );
+        assert_eq!(magnet.trackers().len(), 3);
+        assert_eq!(magnet.search_keywords(), Some("sintel"));
+        assert_eq!(magnet.peers(), ["10.0.0.1%3A6881"]);
+    }
+
+    #[test]
+    fn preferred_test() {
+        let mut magnet = magnet();
+        let shortener = Shortener::new(170)
+            .prefer(BlockRule::parse("btorrent.xyz").unwrap())
+            .prefer(BlockRule::parse("webtorrent.io").unwrap());
+        let report = magnet.shorten(&shortener);
+        assert!(report.fits);
+        assert_eq!(magnet.trackers(), ["wss%3A%2F%2Ftracker.btorrent.xyz"]);
+        assert_eq!(
+            magnet.web_seed(),
+            Some("https%3A%2F%2Fwebtorrent.io%2Ftorrents%2F")
+        );
+        assert_eq!(magnet.display_name(), Some("Sintel"));
+        assert_eq!(report.dropped.len(), 4);
+    }
+
+    #[test]
+    fn exact_topics_kept_test() {
+        let mut magnet = magnet();
+        let report = magnet.shorten(&Shortener::new(10));
+        assert!(!report.fits);
+        assert_eq!(magnet.to_string(), format!("magnet:?xt=urn:btih:{}", HASH));
+        assert_eq!(report.dropped.len(), 8);
+        assert_eq!(report.dropped[0], ("dn".to_string(), "Sintel".to_string()));
+        assert_eq!(
+            report.dropped[1],
+            ("xl".to_string(), "129241752".to_string())
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! Streaming parsing of magnet lists with one link per line
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+use crate::{Magnet, MagnetError};
+
+/// A parsed line: its 1-based line number, and the magnet or the reason it could not be parsed
+pub type MagnetLine = (usize, Result<Magnet, MagnetError>);
+
+/// Number of lines handed to a worker thread at a time by `ParallelMagnetReader`
+const BATCH_SIZE: usize = 512;
+
+/// Iterator over the magnet links of a reader, one per line
+///
+/// Blank lines are skipped (but still counted), and surrounding whitespace is ignored. A line
+/// that is not a valid magnet URL yields an `Err` for that line only, so a single corrupt link
+/// does not stop the rest of the input from being read. The line buffer is reused, so reading
+/// does not allocate per line beyond the parsed `Magnet` itself.
+///
+/// The iterator only returns an `io::Error` if reading fails, after which it ends.
+///
+/// # Example
+///
+/// ```
+/// use magnet_url::MagnetReader;
+///
+/// let input = "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel\nnot a magnet\n";
+///
+/// for item in MagnetReader::new(input.as_bytes()) {
+///     let (line, result) = item.unwrap();
+///     match result {
+///         Ok(magnet) => println!("{}: {:?}", line, magnet.display_name()),
+///         Err(err) => eprintln!("{}: {}", line, err),
+///     }
+/// }
+/// ```
+pub struct MagnetReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    line_number: usize,
+    done: bool,
+}
+
+impl<R: BufRead> MagnetReader<R> {
+    /// Create a reader over the lines of `reader`
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            line_number: 0,
+            done: false,
+        }
+    }
+
+    /// Parse lines on `threads` worker threads, still yielding them in input order
+    ///
+    /// Reading stays on the calling thread, so `R` does not need to be `Send`. A `threads` value
+    /// of 0 is treated as 1.
+    pub fn parallel(self, threads: usize) -> ParallelMagnetReader<R> {
+        ParallelMagnetReader::new(self, threads.max(1))
+    }
+
+    /// Read the next non-blank line into the buffer, returning its line number
+    fn next_line(&mut self) -> Option<io::Result<usize>> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            self.buffer.clear();
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(_) => {
+                    self.line_number += 1;
+                    if !self.buffer.trim_ascii().is_empty() {
+                        return Some(Ok(self.line_number));
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for MagnetReader<R> {
+    type Item = io::Result<MagnetLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_line()?.map(|line| (line, parse_line(&self.buffer))))
+    }
+}
+
+/// Parse one line, treating invalid UTF-8 as an invalid magnet URL
+fn parse_line(line: &[u8]) -> Result<Magnet, MagnetError> {
+    match core::str::from_utf8(line.trim_ascii()) {
+        Ok(link) => Magnet::new(link),
+        Err(_) => Err(MagnetError::NotAMagnetURL),
+    }
+}
+
+/// A batch of lines sent to a worker, tagged with its position in the input
+struct Batch {
+    sequence: u64,
+    line_numbers: Vec<usize>,
+    lines: Vec<Vec<u8>>,
+}
+
+/// The parsed lines of a batch, along with its buffers so they can be reused
+struct ParsedBatch {
+    sequence: u64,
+    results: Vec<MagnetLine>,
+    lines: Vec<Vec<u8>>,
+}
+
+/// Iterator that parses the lines of a `MagnetReader` on several threads
+///
+/// Created by [`MagnetReader::parallel`]. Items are yielded in the same order, and with the
+/// same error handling, as `MagnetReader` itself.
+pub struct ParallelMagnetReader<R> {
+    reader: MagnetReader<R>,
+    jobs: Option<Sender<Batch>>,
+    results: Receiver<ParsedBatch>,
+    workers: Vec<JoinHandle<()>>,
+    /// Batches sent to workers but not yet yielded
+    in_flight: usize,
+    next_send: u64,
+    next_yield: u64,
+    /// Batches that finished before an earlier batch did
+    finished: BTreeMap<u64, ParsedBatch>,
+    current: alloc::vec::IntoIter<MagnetLine>,
+    spare_lines: Vec<Vec<Vec<u8>>>,
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead> ParallelMagnetReader<R> {
+    fn new(reader: MagnetReader<R>, threads: usize) -> Self {
+        let (job_sender, job_receiver) = mpsc::channel::<Batch>();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..threads)
+            .map(|_| {
+                let jobs = Arc::clone(&job_receiver);
+                let results = result_sender.clone();
+                thread::spawn(move || worker(jobs, results))
+            })
+            .collect();
+
+        Self {
+            reader,
+            jobs: Some(job_sender),
+            results: result_receiver,
+            workers,
+            in_flight: 0,
+            next_send: 0,
+            next_yield: 0,
+            finished: BTreeMap::new(),
+            current: Vec::new().into_iter(),
+            spare_lines: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Read and send batches until every worker has two batches queued, or the input ends
+    fn fill(&mut self) {
+        let Some(jobs) = &self.jobs else { return };
+
+        while self.in_flight < self.workers.len() * 2 && self.error.is_none() {
+            let mut lines = self.spare_lines.pop().unwrap_or_default();
+            let mut line_numbers = Vec::with_capacity(BATCH_SIZE);
+            let mut count = 0;
+
+            while count < BATCH_SIZE {
+                match self.reader.next_line() {
+                    Some(Ok(line_number)) => {
+                        if lines.len() <= count {
+                            lines.push(Vec::new());
+                        }
+                        lines[count].clear();
+                        lines[count].extend_from_slice(&self.reader.buffer);
+                        line_numbers.push(line_number);
+                        count += 1;
+                    }
+                    Some(Err(e)) => {
+                        self.error = Some(e);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+
+            if count == 0 {
+                self.spare_lines.push(lines);
+                break;
+            }
+
+            lines.truncate(count);
+            let batch = Batch {
+                sequence: self.next_send,
+                line_numbers,
+                lines,
+            };
+            if jobs.send(batch).is_err() {
+                break;
+            }
+            self.next_send += 1;
+            self.in_flight += 1;
+        }
+    }
+}
+
+fn worker(jobs: Arc<Mutex<Receiver<Batch>>>, results: Sender<ParsedBatch>) {
+    loop {
+        let batch = match jobs.lock() {
+            Ok(jobs) => match jobs.recv() {
+                Ok(batch) => batch,
+                Err(_) => return,
+            },
+            Err(_) => return,
+        };
+
+        let parsed = batch
+            .line_numbers
+            .iter()
+            .zip(&batch.lines)
+            .map(|(&line_number, line)| (line_number, parse_line(line)))
+            .collect();
+
+        let parsed = ParsedBatch {
+            sequence: batch.sequence,
+            results: parsed,
+            lines: batch.lines,
+        };
+        if results.send(parsed).is_err() {
+            return;
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ParallelMagnetReader<R> {
+    type Item = io::Result<MagnetLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.current.next() {
+                return Some(Ok(item));
+            }
+
+            self.fill();
+            if self.in_flight == 0 {
+                return self.error.take().map(Err);
+            }
+
+            let mut batch = match self.finished.remove(&self.next_yield) {
+                Some(batch) => batch,
+                None => loop {
+                    // All workers hold a sender, so this only fails if one of them panicked
+                    let batch = self.results.recv().expect("magnet parsing worker panicked");
+                    if batch.sequence == self.next_yield {
+                        break batch;
+                    }
+                    self.finished.insert(batch.sequence, batch);
+                },
+            };
+
+            self.next_yield += 1;
+            self.in_flight -= 1;
+            self.spare_lines.push(mem::take(&mut batch.lines));
+            self.current = batch.results.into_iter();
+        }
+    }
+}
+
+impl<R> Drop for ParallelMagnetReader<R> {
+    fn drop(&mut self) {
+        // Closing the job channel makes the workers exit once they finish their current batch
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MagnetReader;
+    use crate::MagnetError;
+    use alloc::format;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use std::io::{self, BufReader, Read};
+
+    const SINTEL: &str = "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel";
+
+    #[test]
+    fn reader_test() {
+        let mut input = format!("{}\n\n  https://example.com  \r\n{}\r\n", SINTEL, SINTEL).into_bytes();
+        // A line that is not valid UTF-8
+        input.extend_from_slice(b"magnet:?dn=\xff\xfe\n");
+
+        let lines: Vec<_> = MagnetReader::new(&input[..]).map(Result::unwrap).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].0, 1);
+        assert_eq!(lines[0].1.as_ref().unwrap().display_name(), Some("Sintel"));
+        assert_eq!(lines[1], (3, Err(MagnetError::NotAMagnetURL)));
+        assert_eq!(lines[2].0, 4);
+        assert!(lines[2].1.is_ok());
+        assert_eq!(lines[3], (5, Err(MagnetError::NotAMagnetURL)));
+    }
+
+    #[test]
+    fn parallel_keeps_order_test() {
+        let mut input = String::new();
+        for i in 0..5000 {
+            if i % 7 == 0 {
+                input.push_str("garbage\n");
+            } else {
+                input.push_str(&format!("{}&xl={}\n", SINTEL, i));
+            }
+        }
+
+        let sequential: Vec<_> = MagnetReader::new(input.as_bytes()).map(Result::unwrap).collect();
+        let parallel: Vec<_> = MagnetReader::new(input.as_bytes())
+            .parallel(4)
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(parallel.len(), 5000);
+        assert_eq!(parallel, sequential);
+        assert_eq!(parallel[1].1.as_ref().unwrap().length(), Some(1));
+        assert_eq!(parallel[4900], (4901, Err(MagnetError::NotAMagnetURL)));
+    }
+
+    /// A reader that returns an error after its data runs out
+    struct FailingReader<'a>(&'a [u8]);
+
+    impl Read for FailingReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::other("disk on fire"));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn io_error_test() {
+        let input = format!("{}\n{}\n", SINTEL, SINTEL);
+        let make_reader = || BufReader::with_capacity(16, FailingReader(input.as_bytes()));
+
+        let check = |items: Vec<io::Result<_>>| {
+            assert_eq!(items.len(), 3);
+            assert!(items[0].is_ok());
+            assert!(items[1].is_ok());
+            assert_eq!(items[2].as_ref().unwrap_err().to_string(), "disk on fire");
+        };
+
+        check(MagnetReader::new(make_reader()).collect());
+        check(MagnetReader::new(make_reader()).parallel(2).collect());
+    }
+}
@@ -0,0 +1,64 @@
+//! Temporary directories for the tests that read and write files
+
+use alloc::format;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An empty directory unique to this process and call, removed when dropped
+///
+/// The directory is removed even when the test panics, and tests running at the same time (or
+/// in another checkout) never share one.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create a directory whose name starts with `magnet-url-<name>`
+    pub(crate) fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let id = NEXT.fetch_add(1, Ordering::Relaxed);
+        let path =
+            std::env::temp_dir().join(format!("magnet-url-{}-{}-{}", name, std::process::id(), id));
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TempDir;
+
+    #[test]
+    fn temp_dir_test() {
+        let a = TempDir::new("temp-dir");
+        let b = TempDir::new("temp-dir");
+        assert_ne!(*a, *b);
+        std::fs::write(a.join("file"), b"x").unwrap();
+
+        let path = a.to_path_buf();
+        drop(a);
+        assert!(!path.exists());
+        assert!(b.is_dir());
+    }
+}
@@ -0,0 +1,214 @@
+//! Conversion from `.torrent` metainfo files (v1, v2 and hybrid) to magnet URLs
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::bencode::{self, Value};
+use crate::encoding::{hex_encode, percent_encode};
+use crate::sha1::Sha1;
+use crate::sha256::Sha256;
+use crate::{Magnet, MagnetBuilder};
+
+/// The ways reading a `.torrent` file can fail
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum TorrentError {
+    /// The data is not valid bencode
+    InvalidBencode,
+    /// The data has no `info` dictionary
+    MissingInfo,
+}
+
+impl fmt::Display for TorrentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TorrentError::InvalidBencode => {
+                write!(f, "provided data is not a valid torrent file")
+            }
+            TorrentError::MissingInfo => write!(f, "torrent file has no info dictionary"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TorrentError {}
+
+impl Magnet {
+    /// Create a magnet URL from the contents of a BitTorrent `.torrent` file
+    ///
+    /// The exact topic is the `btih` info hash of a v1 torrent, the `btmh` info hash of a v2
+    /// torrent, or both for a hybrid torrent. The display name, total
+    /// length, trackers (`announce` and `announce-list`) and first web seed (`url-list`) are
+    /// copied over. Text values are percent-encoded, like in a parsed magnet URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(TorrentError::InvalidBencode)` if the data is not valid bencode, or
+    /// `Err(TorrentError::MissingInfo)` if it has no `info` dictionary
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use magnet_url::Magnet;
+    /// let torrent = b"d8:announce23:udp://tracker.test:69694:infod6:lengthi5e4:name5:hello12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+    /// let magnet = Magnet::from_torrent(torrent).unwrap();
+    /// assert_eq!(magnet.hash_type(), Some("btih"));
+    /// assert_eq!(magnet.display_name(), Some("hello"));
+    /// assert_eq!(magnet.length(), Some(5));
+    /// ```
+    pub fn from_torrent(data: &[u8]) -> Result<Magnet, TorrentError> {
+        let torrent = bencode::decode(data).map_err(|_| TorrentError::InvalidBencode)?;
+        let raw_info = bencode::raw_dict_value(data, "info").ok_or(TorrentError::MissingInfo)?;
+        let info = torrent.get("info").ok_or(TorrentError::MissingInfo)?;
+        if !matches!(info, Value::Dict(_)) {
+            return Err(TorrentError::MissingInfo);
+        }
+
+        // v2 torrents (BEP 52) are identified by the SHA-256 of the info dictionary, as a
+        // multihash; hybrid torrents also carry the v1 `pieces` and get both exact topics
+        let v2 = info.get("meta version").and_then(Value::as_int) == Some(2);
+        let v1 = !v2 || info.get("pieces").is_some();
+        let mut builder = MagnetBuilder::new();
+        if v1 {
+            builder = builder.add_exact_topic("btih", &hex_encode(&Sha1::digest(raw_info)));
+        }
+        if v2 {
+            let btmh = alloc::format!("1220{}", hex_encode(&Sha256::digest(raw_info)));
+            builder = builder.add_exact_topic("btmh", &btmh);
+        }
+
+        if let Some(name) = info.get("name").and_then(Value::as_str) {
+            builder = builder.display_name(&percent_encode(name));
+        }
+
+        let length = match info.get("length").and_then(Value::as_int) {
+            Some(length) => Some(length),
+            None => match info.get("files").and_then(Value::as_list) {
+                // Padding files (BEP 47) are not part of the content
+                Some(files) => Some(
+                    files
+                        .iter()
+                        .filter(|file| !is_padding(file))
+                        .filter_map(|file| file.get("length").and_then(Value::as_int))
+                        .sum(),
+                ),
+                None => info.get("file tree").map(file_tree_length),
+            },
+        };
+        if let Some(length) = length.and_then(|l| u64::try_from(l).ok()) {
+            builder = builder.length(length);
+        }
+
+        let mut trackers: Vec<&str> = Vec::new();
+        if let Some(announce) = torrent.get("announce").and_then(Value::as_str) {
+            trackers.push(announce);
+        }
+        for tier in torrent.get("announce-list").and_then(Value::as_list).unwrap_or_default() {
+            for tracker in tier.as_list().unwrap_or_default() {
+                if let Some(tracker) = tracker.as_str() {
+                    if !trackers.contains(&tracker) {
+                        trackers.push(tracker);
+                    }
+                }
+            }
+        }
+        for tracker in trackers {
+            builder = builder.add_tracker(&percent_encode(tracker));
+        }
+
+        let web_seed = match torrent.get("url-list") {
+            Some(Value::List(urls)) => urls.first().and_then(Value::as_str),
+            Some(url) => url.as_str(),
+            None => None,
+        };
+        if let Some(web_seed) = web_seed {
+            builder = builder.web_seed(&percent_encode(web_seed));
+        }
+
+        Ok(builder.build())
+    }
+}
+
+fn is_padding(file: &Value<'_>) -> bool {
+    file.get("attr")
+        .and_then(Value::as_bytes)
+        .is_some_and(|attr| attr.contains(&b'p'))
+}
+
+/// Add up the lengths of the files of a v2 `file tree`, whose files are `{"": {"length": ..}}`
+fn file_tree_length(tree: &Value<'_>) -> i64 {
+    let Some(entries) = tree.as_dict() else {
+        return 0;
+    };
+    entries
+        .iter()
+        .map(|(name, value)| {
+            if name.is_empty() {
+                value.get("length").and_then(Value::as_int).unwrap_or(0)
+            } else {
+                file_tree_length(value)
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TorrentError;
+    use crate::Magnet;
+
+    #[test]
+    fn single_file_torrent_test() {
+        let torrent = b"d8:announce30:udp://tracker.example.com:696913:announce-listll30:udp://tracker.example.com:6969el26:http://other.example/annceee4:infod6:lengthi12345e4:name9:My Movie!12:piece lengthi16384e6:pieces20:01234567890123456789e8:url-list24:https://example.com/seede";
+        let magnet = Magnet::from_torrent(torrent).unwrap();
+
+        assert_eq!(magnet.hash_type(), Some("btih"));
+        assert_eq!(magnet.hash(), Some("d97db2e2638051bb29b7b567f0f608e0ee9622ee"));
+        assert_eq!(magnet.display_name(), Some("My%20Movie!"));
+        assert_eq!(magnet.length(), Some(12345));
+        assert_eq!(
+            magnet.trackers(),
+            &["udp://tracker.example.com:6969", "http://other.example/annce"]
+        );
+        assert_eq!(magnet.web_seed(), Some("https://example.com/seed"));
+    }
+
+    #[test]
+    fn multi_file_torrent_test() {
+        let torrent = b"d4:infod5:filesld6:lengthi10e4:pathl1:aeed6:lengthi32e4:pathl1:beee4:name3:dir12:piece lengthi16384e6:pieces20:01234567890123456789ee";
+        let magnet = Magnet::from_torrent(torrent).unwrap();
+
+        assert_eq!(magnet.display_name(), Some("dir"));
+        assert_eq!(magnet.length(), Some(42));
+        assert!(magnet.trackers().is_empty());
+        assert_eq!(magnet.web_seed(), None);
+    }
+
+    #[test]
+    fn v2_torrent_test() {
+        let torrent = b"d4:infod9:file treed1:ad0:d6:lengthi5e11:pieces root32:rrrrrrrrrrrrrrrrrrrrrrrrrrrrrrrre\
+                        ee12:meta versioni2e4:name1:a12:piece lengthi16384eee";
+        let magnet = Magnet::from_torrent(torrent).unwrap();
+
+        assert_eq!(magnet.hash_type(), Some("btmh"));
+        assert_eq!(
+            magnet.hash(),
+            Some("122095c49231cd7ffd83b3872185603eb9c37197692ca1f054d48bafbee9ddf2d06b")
+        );
+        assert_eq!(magnet.exact_topic("btih"), None);
+        assert_eq!(magnet.length(), Some(5));
+    }
+
+    #[test]
+    fn padding_files_test() {
+        let torrent = b"d4:infod5:filesld6:lengthi10e4:pathl1:aeed4:attr1:p6:lengthi16374e4:pathl4:.pad5:16374eed6:lengthi32e4:pathl1:beee4:name3:dir12:piece lengthi16384e6:pieces40:0123456789012345678901234567890123456789ee";
+        let magnet = Magnet::from_torrent(torrent).unwrap();
+        assert_eq!(magnet.length(), Some(42));
+    }
+
+    #[test]
+    fn invalid_torrent_test() {
+        assert_eq!(Magnet::from_torrent(b"not bencode"), Err(TorrentError::InvalidBencode));
+        assert_eq!(Magnet::from_torrent(b"d3:fooi1ee"), Err(TorrentError::MissingInfo));
+        assert_eq!(Magnet::from_torrent(b"d4:infoi1ee"), Err(TorrentError::MissingInfo));
+    }
+}
@@ -0,0 +1,198 @@
+//! Announce types shared by the tracker clients
+
+use alloc::vec::Vec;
+use core::time::Duration;
+use std::net::SocketAddr;
+
+use crate::random::{peer_id, random_u64};
+use crate::{InfoHash, Magnet};
+
+/// The event reported with an announce
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq)]
+pub enum AnnounceEvent {
+    /// A regular, periodic announce
+    #[default]
+    None,
+    /// The download finished
+    Completed,
+    /// The download started
+    Started,
+    /// The download stopped
+    Stopped,
+}
+
+/// The parameters of an announce, with chainable setters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnounceRequest {
+    pub(crate) info_hash: InfoHash,
+    pub(crate) peer_id: [u8; 20],
+    pub(crate) downloaded: u64,
+    pub(crate) left: u64,
+    pub(crate) uploaded: u64,
+    pub(crate) event: AnnounceEvent,
+    pub(crate) key: u32,
+    pub(crate) num_want: Option<u32>,
+    pub(crate) port: u16,
+}
+
+impl AnnounceRequest {
+    /// Create an announce for `info_hash`, listening for peers on `port`
+    ///
+    /// The peer ID and key are random, nothing is reported as transferred and `left` is unknown.
+    pub fn new(info_hash: InfoHash, port: u16) -> Self {
+        Self {
+            info_hash,
+            peer_id: peer_id(),
+            downloaded: 0,
+            left: i64::MAX as u64,
+            uploaded: 0,
+            event: AnnounceEvent::None,
+            key: random_u64() as u32,
+            num_want: None,
+            port,
+        }
+    }
+
+    /// Create an announce for a magnet's info hash, with `left` set to its length if known
+    ///
+    /// Returns `None` if the magnet has no BitTorrent info hash.
+    pub fn from_magnet(magnet: &Magnet, port: u16) -> Option<Self> {
+        let request = Self::new(magnet.info_hash()?, port);
+        Some(match magnet.length() {
+            Some(length) => request.left(length),
+            None => request,
+        })
+    }
+
+    /// Set the 20-byte peer ID, instead of a random one
+    pub fn peer_id(mut self, peer_id: [u8; 20]) -> Self {
+        self.peer_id = peer_id;
+        self
+    }
+
+    /// Set the number of bytes downloaded so far
+    pub fn downloaded(mut self, downloaded: u64) -> Self {
+        self.downloaded = downloaded;
+        self
+    }
+
+    /// Set the number of bytes left to download
+    pub fn left(mut self, left: u64) -> Self {
+        self.left = left;
+        self
+    }
+
+    /// Set the number of bytes uploaded so far
+    pub fn uploaded(mut self, uploaded: u64) -> Self {
+        self.uploaded = uploaded;
+        self
+    }
+
+    /// Set the event to report, like `Started` for the first announce
+    pub fn event(mut self, event: AnnounceEvent) -> Self {
+        self.event = event;
+        self
+    }
+
+    /// Set the key identifying this client across IP address changes, instead of a random one
+    pub fn key(mut self, key: u32) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Ask for at most `num_want` peers, instead of the tracker's default
+    pub fn num_want(mut self, num_want: u32) -> Self {
+        self.num_want = Some(num_want);
+        self
+    }
+
+    /// Get the info hash being announced
+    pub fn info_hash(&self) -> &InfoHash {
+        &self.info_hash
+    }
+}
+
+/// A tracker's answer to an announce
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnounceResponse {
+    /// How long to wait before announcing again
+    pub interval: Duration,
+    /// Number of peers still downloading
+    pub leechers: u32,
+    /// Number of peers with the complete torrent
+    pub seeders: u32,
+    /// Peers to connect to
+    pub peers: Vec<SocketAddr>,
+}
+
+/// A tracker's statistics for one torrent, as returned by a scrape
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq)]
+pub struct ScrapeStats {
+    /// Number of peers with the complete torrent
+    pub seeders: u32,
+    /// Number of times the torrent was downloaded completely
+    pub completed: u32,
+    /// Number of peers still downloading
+    pub leechers: u32,
+}
+
+/// Parse compact peers: a 4-byte (or 16-byte for IPv6) address followed by a 2-byte port each
+pub(crate) fn parse_peers(data: &[u8], ipv6: bool) -> Option<Vec<SocketAddr>> {
+    let ip_len = if ipv6 { 16 } else { 4 };
+    if !data.len().is_multiple_of(ip_len + 2) {
+        return None;
+    }
+    let peers = data
+        .chunks_exact(ip_len + 2)
+        .map(|peer| {
+            let (ip, port) = peer.split_at(ip_len);
+            let port = u16::from_be_bytes([port[0], port[1]]);
+            match <[u8; 16]>::try_from(ip) {
+                Ok(ip) => SocketAddr::from((ip, port)),
+                Err(_) => SocketAddr::from(([ip[0], ip[1], ip[2], ip[3]], port)),
+            }
+        })
+        .collect();
+    Some(peers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_peers, AnnounceRequest};
+    use crate::{InfoHash, Magnet};
+
+    #[test]
+    fn announce_request_test() {
+        let info_hash = InfoHash::new([1; 20]);
+        let first = AnnounceRequest::new(info_hash, 6881);
+        let second = AnnounceRequest::new(info_hash, 6881);
+        assert_eq!(&first.peer_id[..8], b"-MU3000-");
+        assert!(first.peer_id[8..].iter().all(u8::is_ascii_digit));
+        assert_ne!(first.peer_id, second.peer_id);
+
+        let magnet =
+            Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&xl=42")
+                .unwrap();
+        assert_eq!(AnnounceRequest::from_magnet(&magnet, 1).unwrap().left, 42);
+        let magnet =
+            Magnet::new("magnet:?xt=urn:sha1:08ada5a7a6183aae1e09d831df6748d566095a10").unwrap();
+        assert!(AnnounceRequest::from_magnet(&magnet, 1).is_none());
+    }
+
+    #[test]
+    fn parse_peers_test() {
+        let peers = parse_peers(&[10, 0, 0, 1, 0x1a, 0xe1], false).unwrap();
+        assert_eq!(peers, ["10.0.0.1:6881".parse().unwrap()]);
+
+        let mut data = [0u8; 18];
+        data[15] = 1;
+        data[17] = 80;
+        assert_eq!(
+            parse_peers(&data, true).unwrap(),
+            ["[::1]:80".parse().unwrap()]
+        );
+
+        assert!(parse_peers(&[1, 2, 3], false).is_none());
+        assert_eq!(parse_peers(&[], true), Some(alloc::vec![]));
+    }
+}
@@ -0,0 +1,582 @@
+//! Curated tracker lists, merged into magnets
+//!
+//! Public "best trackers" lists are published as plain text, with one URL per line (blank lines
+//! in between are common), or as JSON. [`TrackerList`] reads both, and merges the trackers into a
+//! [`Magnet`] or [`MagnetBuilder`] without adding trackers the magnet already has.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::encoding::{hex_decode, percent_decode, percent_encode};
+use crate::{Magnet, MagnetBuilder};
+
+/// The ways loading a tracker list can fail
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum TrackerListError {
+    /// The text is not valid JSON
+    InvalidJson,
+    /// The JSON is not an array of tracker URLs, or an object with a `trackers` array
+    UnexpectedJson,
+}
+
+impl fmt::Display for TrackerListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrackerListError::InvalidJson => write!(f, "tracker list is not valid JSON"),
+            TrackerListError::UnexpectedJson => {
+                write!(f, "JSON does not contain a list of tracker URLs")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TrackerListError {}
+
+/// The protocol a tracker is reached with
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum Transport {
+    /// `udp://` trackers
+    Udp,
+    /// `http://` and `https://` trackers
+    Http,
+    /// `ws://` and `wss://` trackers, used by WebTorrent
+    WebSocket,
+}
+
+impl Transport {
+    /// Get the transport of a tracker URL from its scheme
+    pub fn of(url: &str) -> Option<Self> {
+        let (scheme, _) = url.split_once("://")?;
+        match scheme.to_ascii_lowercase().as_str() {
+            "udp" => Some(Transport::Udp),
+            "http" | "https" => Some(Transport::Http),
+            "ws" | "wss" => Some(Transport::WebSocket),
+            _ => None,
+        }
+    }
+}
+
+/// A list of tracker URLs to add to magnets
+///
+/// URLs are normalized when the list is loaded, and duplicates are dropped: the scheme and host
+/// are lowercased, default ports and empty paths are removed, and percent-encoding is decoded.
+///
+/// # Example
+///
+/// ```
+/// use magnet_url::{Magnet, TrackerList, Transport};
+///
+/// let list = TrackerList::parse("udp://tracker.opentrackr.org:1337/announce\n\nhttps://tracker.example.com:443/announce\n\nwss://tracker.openwebtorrent.com\n")
+///     .transports(&[Transport::Udp, Transport::Http])
+///     .max_trackers(10);
+///
+/// let mut magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337%2Fannounce").unwrap();
+/// magnet.merge_trackers(&list);
+/// assert_eq!(magnet.trackers().len(), 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackerList {
+    trackers: Vec<String>,
+    transports: Vec<Transport>,
+    max_trackers: Option<usize>,
+}
+
+impl TrackerList {
+    /// Read a plain text list with one tracker URL per line
+    ///
+    /// Blank lines, lines starting with `#` and lines that are not URLs are skipped.
+    pub fn parse(text: &str) -> Self {
+        let mut list = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if !line.starts_with('#') {
+                list.push(line);
+            }
+        }
+        list
+    }
+
+    /// Read a JSON list of tracker URLs
+    ///
+    /// The JSON is either an array or an object with a `trackers` array. The array may also
+    /// hold arrays of URLs (tiers, as in a torrent's `announce-list`), which are flattened.
+    /// Strings that are not URLs are skipped.
+    pub fn from_json(text: &str) -> Result<Self, TrackerListError> {
+        let json = Json::parse(text).ok_or(TrackerListError::InvalidJson)?;
+        let urls = match json {
+            Json::Array(items) => items,
+            Json::Object(fields) => match fields.into_iter().find(|(key, _)| key == "trackers") {
+                Some((_, Json::Array(items))) => items,
+                _ => return Err(TrackerListError::UnexpectedJson),
+            },
+            _ => return Err(TrackerListError::UnexpectedJson),
+        };
+
+        let mut list = Self::default();
+        for item in urls {
+            match item {
+                Json::String(url) => list.push(&url),
+                Json::Array(tier) => {
+                    for url in tier {
+                        let Json::String(url) = url else {
+                            return Err(TrackerListError::UnexpectedJson);
+                        };
+                        list.push(&url);
+                    }
+                }
+                _ => return Err(TrackerListError::UnexpectedJson),
+            }
+        }
+        Ok(list)
+    }
+
+    /// Only add trackers using one of `transports`. Trackers of unknown protocols are skipped
+    /// too.
+    pub fn transports(mut self, transports: &[Transport]) -> Self {
+        self.transports = transports.to_vec();
+        self
+    }
+
+    /// Stop adding trackers once a magnet has `max_trackers`
+    ///
+    /// The magnet's own trackers are never removed, even when it already has more.
+    pub fn max_trackers(mut self, max_trackers: usize) -> Self {
+        self.max_trackers = Some(max_trackers);
+        self
+    }
+
+    /// Get the normalized tracker URLs, in the order they were read
+    pub fn trackers(&self) -> &[String] {
+        &self.trackers
+    }
+
+    /// Get the number of trackers in the list
+    pub fn len(&self) -> usize {
+        self.trackers.len()
+    }
+
+    /// Check whether the list has no trackers
+    pub fn is_empty(&self) -> bool {
+        self.trackers.is_empty()
+    }
+
+    fn push(&mut self, url: &str) {
+        if let Some(url) = normalize(url) {
+            if !self.trackers.contains(&url) {
+                self.trackers.push(url);
+            }
+        }
+    }
+
+    /// Add the trackers that pass the filters and are not in `trackers` yet
+    fn merge_into(&self, trackers: &mut Vec<String>) {
+        let mut known: Vec<String> = trackers
+            .iter()
+            .map(|tracker| normalize(tracker).unwrap_or_else(|| tracker.clone()))
+            .collect();
+
+        for url in &self.trackers {
+            if self.max_trackers.is_some_and(|max| trackers.len() >= max) {
+                break;
+            }
+            if !self.transports.is_empty()
+                && !Transport::of(url).is_some_and(|t| self.transports.contains(&t))
+            {
+                continue;
+            }
+            if !known.contains(url) {
+                trackers.push(percent_encode(url));
+                known.push(url.clone());
+            }
+        }
+    }
+}
+
+impl Magnet {
+    /// Add the trackers of a list that the magnet doesn't have yet (see [`TrackerList`])
+    pub fn merge_trackers(&mut self, list: &TrackerList) {
+        list.merge_into(&mut self.trackers);
+    }
+}
+
+impl MagnetBuilder {
+    /// Add the trackers of a list that were not added yet (see [`TrackerList`])
+    pub fn merge_trackers(mut self, list: &TrackerList) -> Self {
+        list.merge_into(&mut self.magnet.trackers);
+        self
+    }
+}
+
+/// Normalize a tracker URL so that equivalent URLs compare equal, or `None` if it isn't a URL
+fn normalize(url: &str) -> Option<String> {
+    let url = url.trim();
+    let url = percent_decode(url).unwrap_or_else(|| url.into());
+    let (scheme, rest) = url.split_once("://")?;
+    let scheme = scheme.to_ascii_lowercase();
+    if scheme.is_empty()
+        || !scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    {
+        return None;
+    }
+
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(end);
+    let mut authority = authority.to_ascii_lowercase();
+    if authority.is_empty() || authority.contains(char::is_whitespace) {
+        return None;
+    }
+    let default_port = match scheme.as_str() {
+        "http" | "ws" => Some(":80"),
+        "https" | "wss" => Some(":443"),
+        _ => None,
+    };
+    if let Some(port) = default_port {
+        if authority.ends_with(port) {
+            authority.truncate(authority.len() - port.len());
+        }
+    }
+    let path = if path == "/" { "" } else { path };
+
+    Some(alloc::format!("{}://{}{}", scheme, authority, path))
+}
+
+/// Just enough of a JSON parser to read tracker lists
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    /// A null, boolean or number, which tracker lists don't use
+    Other,
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parse a whole JSON document
+    fn parse(text: &str) -> Option<Json> {
+        let mut parser = JsonParser {
+            text: text.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        (parser.pos == text.len()).then_some(value)
+    }
+}
+
+/// Nesting limit, so deeply nested input can't overflow the stack
+const MAX_DEPTH: usize = 64;
+
+struct JsonParser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn value(&mut self) -> Option<Json> {
+        self.skip_whitespace();
+        match *self.text.get(self.pos)? {
+            b'n' => self.literal("null"),
+            b't' => self.literal("true"),
+            b'f' => self.literal("false"),
+            b'"' => self.string().map(Json::String),
+            b'[' => self.nested(b']', |parser| parser.value()).map(Json::Array),
+            b'{' => self
+                .nested(b'}', |parser| {
+                    parser.skip_whitespace();
+                    let key = parser.string()?;
+                    parser.skip_whitespace();
+                    parser.expect(b':')?;
+                    Some((key, parser.value()?))
+                })
+                .map(Json::Object),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => None,
+        }
+    }
+
+    /// Parse the items of an array or object, from the opening bracket to `close`
+    fn nested<T>(
+        &mut self,
+        close: u8,
+        mut item: impl FnMut(&mut Self) -> Option<T>,
+    ) -> Option<Vec<T>> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return None;
+        }
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.text.get(self.pos) == Some(&close) {
+            self.pos += 1;
+        } else {
+            loop {
+                items.push(item(self)?);
+                self.skip_whitespace();
+                match *self.text.get(self.pos)? {
+                    b',' => self.pos += 1,
+                    c if c == close => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        self.depth -= 1;
+        Some(items)
+    }
+
+    fn literal(&mut self, literal: &str) -> Option<Json> {
+        let end = self.pos + literal.len();
+        (self.text.get(self.pos..end)? == literal.as_bytes()).then(|| {
+            self.pos = end;
+            Json::Other
+        })
+    }
+
+    fn number(&mut self) -> Option<Json> {
+        let start = self.pos;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.text.get(self.pos) {
+            self.pos += 1;
+        }
+        let number = core::str::from_utf8(&self.text[start..self.pos]).ok()?;
+        number.parse::<f64>().ok().map(|_| Json::Other)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.expect(b'"')?;
+        let mut out = String::new();
+        loop {
+            let start = self.pos;
+            while !matches!(*self.text.get(self.pos)?, b'"' | b'\\') {
+                self.pos += 1;
+            }
+            out.push_str(core::str::from_utf8(&self.text[start..self.pos]).ok()?);
+
+            self.pos += 1;
+            if self.text[self.pos - 1] == b'"' {
+                return Some(out);
+            }
+            let escaped = *self.text.get(self.pos)?;
+            self.pos += 1;
+            out.push(match escaped {
+                b'"' => '"',
+                b'\\' => '\\',
+                b'/' => '/',
+                b'b' => '\u{8}',
+                b'f' => '\u{c}',
+                b'n' => '\n',
+                b'r' => '\r',
+                b't' => '\t',
+                b'u' => {
+                    let first = self.hex_u16()?;
+                    if (0xd800..0xdc00).contains(&first) {
+                        // A surrogate pair, for characters outside the Basic Multilingual Plane
+                        self.expect(b'\\')?;
+                        self.expect(b'u')?;
+                        let second = self.hex_u16()?;
+                        char::decode_utf16([first, second]).next()?.ok()?
+                    } else {
+                        char::from_u32(u32::from(first))?
+                    }
+                }
+                _ => return None,
+            });
+        }
+    }
+
+    fn hex_u16(&mut self) -> Option<u16> {
+        let digits = core::str::from_utf8(self.text.get(self.pos..self.pos + 4)?).ok()?;
+        self.pos += 4;
+        hex_decode::<2>(digits).map(u16::from_be_bytes)
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.text.get(self.pos) == Some(&byte)).then(|| self.pos += 1)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.text.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, Json, TrackerList, TrackerListError, Transport};
+    use crate::{Magnet, MagnetBuilder};
+    use alloc::string::ToString;
+
+    const HASH: &str = "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10";
+
+    #[test]
+    fn normalize_test() {
+        assert_eq!(
+            normalize(" UDP://Tracker.Example.com:6969/announce ").as_deref(),
+            Some("udp://tracker.example.com:6969/announce")
+        );
+        assert_eq!(
+            normalize("udp%3A%2F%2Ftracker.example.com%3A6969").as_deref(),
+            Some("udp://tracker.example.com:6969")
+        );
+        assert_eq!(
+            normalize("https://tracker.example.com:443/").as_deref(),
+            Some("https://tracker.example.com")
+        );
+        assert_eq!(
+            normalize("http://tracker.example.com:8080/announce?passkey=AbC").as_deref(),
+            Some("http://tracker.example.com:8080/announce?passkey=AbC")
+        );
+        assert_eq!(normalize("not a tracker"), None);
+        assert_eq!(normalize("udp:///announce"), None);
+        assert_eq!(normalize("://tracker.example.com"), None);
+    }
+
+    #[test]
+    fn parse_test() {
+        let list = TrackerList::parse(
+            "# best trackers\r\nudp://tracker.opentrackr.org:1337/announce\r\n\r\n\
+             http://tracker.example.com:80/announce\n\nUDP://tracker.opentrackr.org:1337/announce\n\
+             garbage\n\nwss://tracker.openwebtorrent.com\n",
+        );
+        assert_eq!(
+            list.trackers(),
+            [
+                "udp://tracker.opentrackr.org:1337/announce",
+                "http://tracker.example.com/announce",
+                "wss://tracker.openwebtorrent.com",
+            ]
+        );
+        assert!(TrackerList::parse("").is_empty());
+    }
+
+    #[test]
+    fn from_json_test() {
+        let list = TrackerList::from_json(
+            r#" ["udp://a.example.com:1", "udp:\/\/b.example.com:2", "udp://a.example.com:1"] "#,
+        )
+        .unwrap();
+        assert_eq!(
+            list.trackers(),
+            ["udp://a.example.com:1", "udp://b.example.com:2"]
+        );
+
+        // An object with tiers, and fields that aren't trackers
+        let list = TrackerList::from_json(
+            r#"{"updated": 1700000000, "stable": true, "name": "café 🧲",
+                "trackers": [["udp://a.example.com:1"], ["http://b.example.com/announce", "nope"]]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            list.trackers(),
+            ["udp://a.example.com:1", "http://b.example.com/announce"]
+        );
+        assert!(TrackerList::from_json("[]").unwrap().is_empty());
+
+        assert_eq!(
+            TrackerList::from_json(r#"["udp://a.example.com:1""#),
+            Err(TrackerListError::InvalidJson)
+        );
+        assert_eq!(
+            TrackerList::from_json(r#"["a"] x"#),
+            Err(TrackerListError::InvalidJson)
+        );
+        assert_eq!(
+            TrackerList::from_json(r#"{"list": []}"#),
+            Err(TrackerListError::UnexpectedJson)
+        );
+        assert_eq!(
+            TrackerList::from_json("[1, 2]"),
+            Err(TrackerListError::UnexpectedJson)
+        );
+    }
+
+    #[test]
+    fn json_test() {
+        assert_eq!(
+            Json::parse(r#""a\"b\\c\ndA""#),
+            Some(Json::String("a\"b\\c\ndA".into()))
+        );
+        assert_eq!(
+            Json::parse(r#""🧲""#),
+            Some(Json::String("\u{1f9f2}".into()))
+        );
+        assert_eq!(Json::parse(r#""\ud83e""#), None);
+        assert_eq!(Json::parse("-1.5e3"), Some(Json::Other));
+        assert_eq!(Json::parse("nul"), None);
+        assert_eq!(Json::parse("[1,]"), None);
+        assert_eq!(Json::parse(r#"{"a" 1}"#), None);
+
+        // Nesting is limited
+        let deep = "[".repeat(1000) + &"]".repeat(1000);
+        assert_eq!(Json::parse(&deep), None);
+    }
+
+    #[test]
+    fn merge_test() {
+        let list = TrackerList::parse(
+            "udp://tracker.opentrackr.org:1337/announce\n\
+             https://tracker.example.com/announce?key=a&b\n\
+             wss://tracker.openwebtorrent.com\n\
+             udp://open.stealth.si:80/announce\n",
+        );
+
+        let mut magnet = Magnet::new(&alloc::format!(
+            "{}&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337%2Fannounce",
+            HASH
+        ))
+        .unwrap();
+        magnet.merge_trackers(&list);
+        assert_eq!(
+            magnet.trackers(),
+            [
+                "udp%3A%2F%2Ftracker.opentrackr.org%3A1337%2Fannounce",
+                "https://tracker.example.com/announce?key%3Da%26b",
+                "wss://tracker.openwebtorrent.com",
+                "udp://open.stealth.si:80/announce",
+            ]
+        );
+        // Merged trackers survive a round trip through the magnet URL
+        let reparsed = Magnet::new(&magnet.to_string()).unwrap();
+        assert_eq!(reparsed.trackers(), magnet.trackers());
+
+        // Merging again adds nothing
+        let before = magnet.trackers().len();
+        magnet.merge_trackers(&list);
+        assert_eq!(magnet.trackers().len(), before);
+
+        let udp_only = list.clone().transports(&[Transport::Udp]);
+        let magnet = MagnetBuilder::new()
+            .hash_type("btih")
+            .hash("08ada5a7a6183aae1e09d831df6748d566095a10")
+            .add_tracker("http://tracker.example.com:80/")
+            .merge_trackers(&udp_only)
+            .build();
+        assert_eq!(
+            magnet.trackers(),
+            [
+                "http://tracker.example.com:80/",
+                "udp://tracker.opentrackr.org:1337/announce",
+                "udp://open.stealth.si:80/announce",
+            ]
+        );
+
+        let limited = list.max_trackers(2);
+        let magnet = MagnetBuilder::new()
+            .add_tracker("udp://one.example.com:1")
+            .merge_trackers(&limited)
+            .build();
+        assert_eq!(magnet.trackers().len(), 2);
+        assert_eq!(Transport::of(&magnet.trackers()[1]), Some(Transport::Udp));
+        assert_eq!(Transport::of("WSS://a"), Some(Transport::WebSocket));
+        assert_eq!(Transport::of("magnet:?xt"), None);
+    }
+}
@@ -0,0 +1,608 @@
+//! UDP tracker client (BEP 15), enabled by the `udp-tracker` feature
+//!
+//! The client speaks the connect, announce and scrape exchanges of
+//! [BEP 15](https://www.bittorrent.org/beps/bep_0015.html). Requests that get no answer are
+//! retransmitted after `15 * 2^n` seconds, up to 8 times, as the BEP describes; both numbers can
+//! be lowered with [`UdpTracker::timeout`] and [`UdpTracker::max_retries`].
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Instant;
+
+use crate::encoding::percent_decode;
+use crate::random::random_u64;
+use crate::tracker::parse_peers;
+use crate::{InfoHash, Magnet};
+
+pub use crate::tracker::{AnnounceEvent, AnnounceRequest, AnnounceResponse, ScrapeStats};
+
+/// Magic constant sent in every connect request
+const PROTOCOL_ID: u64 = 0x0417_2710_1980;
+
+const ACTION_CONNECT: u32 = 0;
+const ACTION_ANNOUNCE: u32 = 1;
+const ACTION_SCRAPE: u32 = 2;
+const ACTION_ERROR: u32 = 3;
+
+/// How long a connection ID may be used after the tracker handed it out
+const CONNECTION_LIFETIME: Duration = Duration::from_secs(60);
+
+/// Most info hashes a single scrape request may carry
+const MAX_SCRAPE_HASHES: usize = 74;
+
+/// The ways a UDP tracker request can fail
+#[derive(Debug)]
+pub enum UdpTrackerError {
+    /// The tracker URL is not a `udp://host:port` URL
+    InvalidUrl,
+    /// Resolving the tracker or talking to it failed
+    Io(io::Error),
+    /// The tracker did not answer, even after all retransmissions
+    Timeout,
+    /// The tracker answered with an error message
+    Tracker(String),
+    /// The tracker answered with a malformed packet
+    InvalidResponse,
+}
+
+impl fmt::Display for UdpTrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UdpTrackerError::InvalidUrl => write!(f, "not a valid UDP tracker URL"),
+            UdpTrackerError::Io(err) => write!(f, "tracker I/O error: {}", err),
+            UdpTrackerError::Timeout => write!(f, "tracker did not respond"),
+            UdpTrackerError::Tracker(message) => write!(f, "tracker error: {}", message),
+            UdpTrackerError::InvalidResponse => write!(f, "tracker sent an invalid response"),
+        }
+    }
+}
+
+impl std::error::Error for UdpTrackerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UdpTrackerError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for UdpTrackerError {
+    fn from(err: io::Error) -> Self {
+        UdpTrackerError::Io(err)
+    }
+}
+
+/// A client for a single UDP tracker
+///
+/// # Example
+///
+/// ```no_run
+/// use magnet_url::Magnet;
+/// use magnet_url::udp_tracker::{AnnounceRequest, UdpTracker};
+///
+/// let magnet = Magnet::new("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&tr=udp%3A%2F%2Fexplodie.org%3A6969").unwrap();
+/// let request = AnnounceRequest::from_magnet(&magnet, 6881).unwrap();
+///
+/// for url in magnet.udp_trackers() {
+///     let mut tracker = UdpTracker::with_decoded_url(&url).unwrap();
+///     match tracker.announce(&request) {
+///         Ok(response) => println!("{}: {:?}", url, response.peers),
+///         Err(err) => eprintln!("{}: {}", url, err),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct UdpTracker {
+    socket: UdpSocket,
+    timeout: Duration,
+    max_retries: u32,
+    /// The current connection ID and when it was received
+    connection: Option<(u64, Instant)>,
+}
+
+impl UdpTracker {
+    /// Resolve a `udp://host:port` tracker URL, which may still be percent-encoded as it
+    /// appears in a magnet
+    pub fn new(url: &str) -> Result<Self, UdpTrackerError> {
+        let url = percent_decode(url).ok_or(UdpTrackerError::InvalidUrl)?;
+        Self::with_decoded_url(&url)
+    }
+
+    /// Resolve a `udp://host:port` tracker URL that is already percent-decoded, like the ones
+    /// returned by [`Magnet::udp_trackers`]
+    pub fn with_decoded_url(url: &str) -> Result<Self, UdpTrackerError> {
+        let authority = udp_authority(url).ok_or(UdpTrackerError::InvalidUrl)?;
+        let addr = authority
+            .to_socket_addrs()?
+            .next()
+            .ok_or(UdpTrackerError::InvalidUrl)?;
+        Self::with_addr(addr)
+    }
+
+    /// Create a client for the tracker at `addr`
+    pub fn with_addr(addr: SocketAddr) -> Result<Self, UdpTrackerError> {
+        let local: SocketAddr = match addr {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+
+        Ok(Self {
+            socket,
+            timeout: Duration::from_secs(15),
+            max_retries: 8,
+            connection: None,
+        })
+    }
+
+    /// Set how long to wait for the first response; every retransmission waits twice as long
+    /// as the one before
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how many times an unanswered request is sent again before giving up
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Announce to the tracker and get a list of peers
+    pub fn announce(
+        &mut self,
+        request: &AnnounceRequest,
+    ) -> Result<AnnounceResponse, UdpTrackerError> {
+        let mut body = Vec::with_capacity(82);
+        body.extend_from_slice(request.info_hash.as_bytes());
+        body.extend_from_slice(&request.peer_id);
+        body.extend_from_slice(&request.downloaded.to_be_bytes());
+        body.extend_from_slice(&request.left.to_be_bytes());
+        body.extend_from_slice(&request.uploaded.to_be_bytes());
+        body.extend_from_slice(&event_code(request.event).to_be_bytes());
+        body.extend_from_slice(&0u32.to_be_bytes()); // Let the tracker use the sender's IP
+        body.extend_from_slice(&request.key.to_be_bytes());
+        let num_want = request
+            .num_want
+            .map_or(-1, |n| n.min(i32::MAX as u32) as i32);
+        body.extend_from_slice(&num_want.to_be_bytes());
+        body.extend_from_slice(&request.port.to_be_bytes());
+
+        let response = self.request(ACTION_ANNOUNCE, &body)?;
+        if response.len() < 20 {
+            return Err(UdpTrackerError::InvalidResponse);
+        }
+
+        // Peers are IPv6 addresses only when talking to the tracker over IPv6
+        let peers = parse_peers(&response[20..], self.socket.peer_addr()?.is_ipv6());
+        Ok(AnnounceResponse {
+            interval: Duration::from_secs(u64::from(read_u32(&response, 8))),
+            leechers: read_u32(&response, 12),
+            seeders: read_u32(&response, 16),
+            peers: peers.ok_or(UdpTrackerError::InvalidResponse)?,
+        })
+    }
+
+    /// Get the tracker's statistics for each of `info_hashes`, in the same order
+    ///
+    /// More than 74 hashes are split over several requests.
+    pub fn scrape(
+        &mut self,
+        info_hashes: &[InfoHash],
+    ) -> Result<Vec<ScrapeStats>, UdpTrackerError> {
+        let mut stats = Vec::with_capacity(info_hashes.len());
+        for chunk in info_hashes.chunks(MAX_SCRAPE_HASHES) {
+            let body: Vec<u8> = chunk.iter().flat_map(|h| *h.as_bytes()).collect();
+            let response = self.request(ACTION_SCRAPE, &body)?;
+            if response.len() != 8 + chunk.len() * 12 {
+                return Err(UdpTrackerError::InvalidResponse);
+            }
+
+            stats.extend(response[8..].chunks_exact(12).map(|entry| ScrapeStats {
+                seeders: read_u32(entry, 0),
+                completed: read_u32(entry, 4),
+                leechers: read_u32(entry, 8),
+            }));
+        }
+        Ok(stats)
+    }
+
+    /// Send a request, connecting first if needed, and return the whole response packet
+    fn request(&mut self, action: u32, body: &[u8]) -> Result<Vec<u8>, UdpTrackerError> {
+        let transaction_id = random_u64() as u32;
+        let mut attempt = 0;
+        let mut connected = false;
+        loop {
+            if attempt > self.max_retries {
+                return Err(UdpTrackerError::Timeout);
+            }
+            let timeout = self.timeout.saturating_mul(1 << attempt.min(16));
+
+            // Connection IDs expire, so one may have to be fetched again between retransmissions
+            let connection_id = match self.connection {
+                Some((id, received)) if received.elapsed() < CONNECTION_LIFETIME => id,
+                _ => {
+                    let connect_id = random_u64() as u32;
+                    let packet = packet(PROTOCOL_ID, ACTION_CONNECT, connect_id, &[]);
+                    match self.exchange(&packet, ACTION_CONNECT, connect_id, timeout)? {
+                        Some(response) if response.len() >= 16 => {
+                            self.connection = Some((read_u64(&response, 8), Instant::now()));
+                            connected = true;
+                        }
+                        Some(_) => return Err(UdpTrackerError::InvalidResponse),
+                        None => attempt += 1,
+                    }
+                    continue;
+                }
+            };
+
+            let packet = packet(connection_id, action, transaction_id, body);
+            match self.exchange(&packet, action, transaction_id, timeout) {
+                Ok(Some(response)) => return Ok(response),
+                Ok(None) => attempt += 1,
+                // A tracker that restarted rejects the connection IDs it gave out before, so
+                // an error for a cached ID is retried once with a new connection
+                Err(UdpTrackerError::Tracker(_)) if !connected => self.connection = None,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Send a packet once and wait for its response, returning `None` on timeout
+    ///
+    /// Packets for other transactions (like late answers to an earlier request) are ignored.
+    fn exchange(
+        &self,
+        packet: &[u8],
+        action: u32,
+        transaction_id: u32,
+        timeout: Duration,
+    ) -> Result<Option<Vec<u8>>, UdpTrackerError> {
+        self.socket.send(packet)?;
+        let deadline = Instant::now() + timeout;
+        let mut buffer = [0u8; 2048];
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            self.socket.set_read_timeout(Some(remaining))?;
+
+            let len = match self.socket.recv(&mut buffer) {
+                Ok(len) => len,
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None);
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let response = &buffer[..len];
+            if len < 8 || read_u32(response, 4) != transaction_id {
+                continue;
+            }
+
+            match read_u32(response, 0) {
+                ACTION_ERROR => {
+                    let message = String::from_utf8_lossy(&response[8..]).to_string();
+                    return Err(UdpTrackerError::Tracker(message));
+                }
+                a if a == action => return Ok(Some(response.to_vec())),
+                _ => return Err(UdpTrackerError::InvalidResponse),
+            }
+        }
+    }
+}
+
+impl Magnet {
+    /// Get the magnet's `udp://` tracker URLs, percent-decoded
+    pub fn udp_trackers(&self) -> Vec<String> {
+        self.trackers()
+            .iter()
+            .filter_map(|tracker| percent_decode(tracker))
+            .filter(|tracker| udp_authority(tracker).is_some())
+            .collect()
+    }
+}
+
+/// Get the `host:port` part of a `udp://` URL
+fn udp_authority(url: &str) -> Option<&str> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("udp") {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next()?;
+    (!authority.is_empty()).then_some(authority)
+}
+
+fn event_code(event: AnnounceEvent) -> u32 {
+    match event {
+        AnnounceEvent::None => 0,
+        AnnounceEvent::Completed => 1,
+        AnnounceEvent::Started => 2,
+        AnnounceEvent::Stopped => 3,
+    }
+}
+
+/// Build a request packet: the common header followed by the action specific body
+fn packet(connection_id: u64, action: u32, transaction_id: u32, body: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(16 + body.len());
+    packet.extend_from_slice(&connection_id.to_be_bytes());
+    packet.extend_from_slice(&action.to_be_bytes());
+    packet.extend_from_slice(&transaction_id.to_be_bytes());
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], at: usize) -> u64 {
+    u64::from_be_bytes(data[at..at + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_u32, read_u64, AnnounceEvent, AnnounceRequest, ScrapeStats, UdpTracker,
+        UdpTrackerError, PROTOCOL_ID,
+    };
+    use crate::{InfoHash, Magnet};
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::net::{SocketAddr, UdpSocket};
+    use std::thread::{self, JoinHandle};
+
+    const SINTEL: &str = "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel&xl=1000&tr=udp%3A%2F%2Fexplodie.org%3A6969&tr=wss%3A%2F%2Ftracker.btorrent.xyz";
+    const CONNECTION_ID: u64 = 0x1122_3344_5566_7788;
+
+    /// A fake tracker answering `requests` packets on localhost, dropping the first `drop` of
+    /// them. Returns its address and the packets it received.
+    fn fake_tracker(requests: usize, drop: usize) -> (SocketAddr, JoinHandle<Vec<Vec<u8>>>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut received = Vec::new();
+            let mut buffer = [0u8; 2048];
+            for i in 0..requests {
+                let (len, from) = socket.recv_from(&mut buffer).unwrap();
+                let request = buffer[..len].to_vec();
+                if i >= drop {
+                    socket.send_to(&respond(&request), from).unwrap();
+                }
+                received.push(request);
+            }
+            received
+        });
+        (addr, handle)
+    }
+
+    fn respond(request: &[u8]) -> Vec<u8> {
+        let action = read_u32(request, 8);
+        let transaction = &request[12..16];
+        let mut response = Vec::new();
+        if action == 0 {
+            assert_eq!(read_u64(request, 0), PROTOCOL_ID);
+            response.extend_from_slice(&0u32.to_be_bytes());
+            response.extend_from_slice(transaction);
+            response.extend_from_slice(&CONNECTION_ID.to_be_bytes());
+            return response;
+        }
+
+        if read_u64(request, 0) != CONNECTION_ID {
+            response.extend_from_slice(&3u32.to_be_bytes());
+            response.extend_from_slice(transaction);
+            response.extend_from_slice(b"bad connection id");
+            return response;
+        }
+
+        response.extend_from_slice(&action.to_be_bytes());
+        response.extend_from_slice(transaction);
+        match action {
+            1 => {
+                for value in [1800u32, 3, 5] {
+                    response.extend_from_slice(&value.to_be_bytes());
+                }
+                response.extend_from_slice(&[10, 0, 0, 1, 0x1a, 0xe1, 192, 168, 1, 2, 0, 80]);
+            }
+            2 => {
+                for (i, _) in request[16..].chunks(20).enumerate() {
+                    for value in [i as u32, 100, 7] {
+                        response.extend_from_slice(&value.to_be_bytes());
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+        response
+    }
+
+    fn tracker(addr: SocketAddr) -> UdpTracker {
+        UdpTracker::with_addr(addr)
+            .unwrap()
+            .timeout(Duration::from_millis(100))
+            .max_retries(3)
+    }
+
+    #[test]
+    fn announce_test() {
+        let magnet = Magnet::new(SINTEL).unwrap();
+        let request = AnnounceRequest::from_magnet(&magnet, 6881)
+            .unwrap()
+            .event(AnnounceEvent::Started)
+            .num_want(50);
+
+        let (addr, handle) = fake_tracker(3, 0);
+        let mut tracker = tracker(addr);
+        let response = tracker.announce(&request).unwrap();
+        assert_eq!(response.interval, Duration::from_secs(1800));
+        assert_eq!((response.leechers, response.seeders), (3, 5));
+        assert_eq!(
+            response.peers,
+            [
+                "10.0.0.1:6881".parse().unwrap(),
+                "192.168.1.2:80".parse().unwrap()
+            ]
+        );
+
+        // The connection ID is reused for the second announce
+        tracker
+            .announce(&request.event(AnnounceEvent::None))
+            .unwrap();
+        let received = handle.join().unwrap();
+        assert_eq!(received.len(), 3);
+
+        let announce = &received[1];
+        assert_eq!(announce.len(), 98);
+        assert_eq!(&announce[16..36], magnet.info_hash().unwrap().as_bytes());
+        assert_eq!(&announce[36..44], b"-MU3000-");
+        assert_eq!(read_u64(announce, 64), 1000); // left
+        assert_eq!(read_u32(announce, 80), 2); // started
+        assert_eq!(read_u32(announce, 92), 50); // num_want
+        assert_eq!(&announce[96..], &6881u16.to_be_bytes());
+        assert_eq!(read_u32(&received[2], 80), 0);
+    }
+
+    #[test]
+    fn scrape_test() {
+        let hashes: Vec<InfoHash> = (0..80u8).map(|i| InfoHash::new([i; 20])).collect();
+
+        // One connect, then two scrape requests of 74 and 6 hashes
+        let (addr, handle) = fake_tracker(3, 0);
+        let stats = tracker(addr).scrape(&hashes).unwrap();
+        assert_eq!(stats.len(), 80);
+        assert_eq!(
+            stats[1],
+            ScrapeStats {
+                seeders: 1,
+                completed: 100,
+                leechers: 7
+            }
+        );
+        assert_eq!(stats[74].seeders, 0);
+
+        let received = handle.join().unwrap();
+        assert_eq!(received[1].len(), 16 + 74 * 20);
+        assert_eq!(received[2].len(), 16 + 6 * 20);
+    }
+
+    #[test]
+    fn retransmission_test() {
+        // The first connect request is lost
+        let (addr, handle) = fake_tracker(3, 1);
+        let mut tracker = tracker(addr);
+        let request = AnnounceRequest::new(InfoHash::new([1; 20]), 6881);
+        assert!(tracker.announce(&request).is_ok());
+        handle.join().unwrap();
+
+        // Nobody answers at all
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut tracker = UdpTracker::with_addr(silent.local_addr().unwrap())
+            .unwrap()
+            .timeout(Duration::from_millis(10))
+            .max_retries(2);
+        assert!(matches!(
+            tracker.announce(&request),
+            Err(UdpTrackerError::Timeout)
+        ));
+
+        let mut buffer = [0u8; 64];
+        silent.set_nonblocking(true).unwrap();
+        let mut sent = 0;
+        while silent.recv(&mut buffer).is_ok() {
+            sent += 1;
+        }
+        assert_eq!(sent, 3);
+    }
+
+    #[test]
+    fn tracker_error_test() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut buffer = [0u8; 128];
+            let (len, from) = socket.recv_from(&mut buffer).unwrap();
+            let mut response = 3u32.to_be_bytes().to_vec();
+            response.extend_from_slice(&buffer[12..16]);
+            response.extend_from_slice(b"torrent not registered");
+            assert_eq!(len, 16);
+            socket.send_to(&response, from).unwrap();
+        });
+
+        let err = tracker(addr).scrape(&[InfoHash::new([0; 20])]).unwrap_err();
+        assert!(matches!(&err, UdpTrackerError::Tracker(m) if m == "torrent not registered"));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn reconnect_test() {
+        // The tracker restarted, and rejects the connection ID from before
+        let (addr, handle) = fake_tracker(3, 0);
+        let mut restarted = tracker(addr);
+        restarted.connection = Some((0xdead, std::time::Instant::now()));
+        let request = AnnounceRequest::new(InfoHash::new([1; 20]), 6881);
+        assert!(restarted.announce(&request).is_ok());
+        assert_eq!(restarted.connection.unwrap().0, CONNECTION_ID);
+
+        let received = handle.join().unwrap();
+        assert_eq!(read_u64(&received[0], 0), 0xdead);
+        assert_eq!(read_u32(&received[1], 8), 0); // connect
+        assert_eq!(read_u64(&received[2], 0), CONNECTION_ID);
+
+        // Errors for the new connection are returned: the request is only retried once
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut buffer = [0u8; 2048];
+            for _ in 0..3 {
+                let (len, from) = socket.recv_from(&mut buffer).unwrap();
+                let request = &buffer[..len];
+                let response = if read_u32(request, 8) == 0 {
+                    respond(request)
+                } else {
+                    let mut response = 3u32.to_be_bytes().to_vec();
+                    response.extend_from_slice(&request[12..16]);
+                    response.extend_from_slice(b"torrent not registered");
+                    response
+                };
+                socket.send_to(&response, from).unwrap();
+            }
+        });
+        let mut rejecting = tracker(addr);
+        rejecting.connection = Some((0xdead, std::time::Instant::now()));
+        let err = rejecting.announce(&request).unwrap_err();
+        assert!(matches!(&err, UdpTrackerError::Tracker(m) if m == "torrent not registered"));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn udp_trackers_test() {
+        let magnet = Magnet::new(SINTEL).unwrap();
+        assert_eq!(magnet.udp_trackers(), ["udp://explodie.org:6969"]);
+
+        assert!(matches!(
+            UdpTracker::new("http://example.com:80"),
+            Err(UdpTrackerError::InvalidUrl)
+        ));
+        assert!(matches!(
+            UdpTracker::new("udp://"),
+            Err(UdpTrackerError::InvalidUrl)
+        ));
+        assert!(UdpTracker::new("udp%3A%2F%2F127.0.0.1%3A6969%2Fannounce").is_ok());
+        assert!(UdpTracker::with_decoded_url("udp://127.0.0.1:6969/announce").is_ok());
+        assert!(matches!(
+            UdpTracker::with_decoded_url("udp%3A%2F%2F127.0.0.1%3A6969"),
+            Err(UdpTrackerError::InvalidUrl)
+        ));
+    }
+}
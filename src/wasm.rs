@@ -0,0 +1,282 @@
+//! JavaScript bindings, built with `wasm-bindgen` when the `wasm` feature is enabled
+//!
+//! From JavaScript, `parseMagnet` returns a plain object with one property per field, while the
+//! `Magnet` and `MagnetBuilder` classes mirror the Rust API:
+//!
+//! ```js
+//! import { parseMagnet, Magnet, MagnetBuilder } from "magnet-url";
+//!
+//! const fields = parseMagnet("magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel");
+//! console.log(fields.displayName, fields.trackers);
+//!
+//! const magnet = new MagnetBuilder().displayName("Sintel").hashType("btih").hash("08ad...").build();
+//! console.log(magnet.toString());
+//! ```
+
+use alloc::string::String;
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{Magnet as RustMagnet, MagnetBuilder as RustMagnetBuilder};
+
+/// Parse a magnet URL into a plain object, throwing an `Error` if it is invalid
+///
+/// Missing fields are `null`, `length` is a number, and `trackers` and `peers` are arrays of strings.
+#[wasm_bindgen(js_name = parseMagnet)]
+pub fn parse_magnet(link: &str) -> Result<Object, JsError> {
+    Ok(to_object(&RustMagnet::new(link)?))
+}
+
+/// A parsed magnet URL
+#[wasm_bindgen(js_name = Magnet)]
+pub struct Magnet(RustMagnet);
+
+#[wasm_bindgen(js_class = Magnet)]
+impl Magnet {
+    /// Parse a magnet URL, throwing an `Error` if it is invalid
+    #[wasm_bindgen(constructor)]
+    pub fn new(link: &str) -> Result<Magnet, JsError> {
+        Ok(Magnet(RustMagnet::new(link)?))
+    }
+
+    #[wasm_bindgen(getter, js_name = displayName)]
+    pub fn display_name(&self) -> Option<String> {
+        self.0.display_name().map(String::from)
+    }
+
+    #[wasm_bindgen(getter, js_name = hashType)]
+    pub fn hash_type(&self) -> Option<String> {
+        self.0.hash_type().map(String::from)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn hash(&self) -> Option<String> {
+        self.0.hash().map(String::from)
+    }
+
+    /// The length in bytes, as a number
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> Option<f64> {
+        self.0.length().map(|length| length as f64)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn source(&self) -> Option<String> {
+        self.0.source().map(String::from)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn trackers(&self) -> Array {
+        self.0.trackers().iter().map(|t| JsValue::from_str(t)).collect()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn peers(&self) -> Array {
+        self.0.peers().iter().map(|p| JsValue::from_str(p)).collect()
+    }
+
+    #[wasm_bindgen(getter, js_name = searchKeywords)]
+    pub fn search_keywords(&self) -> Option<String> {
+        self.0.search_keywords().map(String::from)
+    }
+
+    #[wasm_bindgen(getter, js_name = webSeed)]
+    pub fn web_seed(&self) -> Option<String> {
+        self.0.web_seed().map(String::from)
+    }
+
+    #[wasm_bindgen(getter, js_name = acceptableSource)]
+    pub fn acceptable_source(&self) -> Option<String> {
+        self.0.acceptable_source().map(String::from)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn manifest(&self) -> Option<String> {
+        self.0.manifest().map(String::from)
+    }
+
+    /// Generate the magnet URL string
+    #[wasm_bindgen(js_name = toString)]
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        alloc::string::ToString::to_string(&self.0)
+    }
+
+    /// Copy all fields into a plain object, like `parseMagnet` returns
+    #[wasm_bindgen(js_name = toObject)]
+    pub fn to_object(&self) -> Object {
+        to_object(&self.0)
+    }
+}
+
+/// Builder for creating magnet URLs, with chainable setters
+#[wasm_bindgen(js_name = MagnetBuilder)]
+pub struct MagnetBuilder(RustMagnetBuilder);
+
+#[wasm_bindgen(js_class = MagnetBuilder)]
+impl MagnetBuilder {
+    #[wasm_bindgen(constructor)]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> MagnetBuilder {
+        MagnetBuilder(RustMagnetBuilder::new())
+    }
+
+    #[wasm_bindgen(js_name = displayName)]
+    pub fn display_name(self, name: &str) -> MagnetBuilder {
+        MagnetBuilder(self.0.display_name(name))
+    }
+
+    #[wasm_bindgen(js_name = hashType)]
+    pub fn hash_type(self, hash_type: &str) -> MagnetBuilder {
+        MagnetBuilder(self.0.hash_type(hash_type))
+    }
+
+    pub fn hash(self, hash: &str) -> MagnetBuilder {
+        MagnetBuilder(self.0.hash(hash))
+    }
+
+    /// Set the length in bytes, which must be a non-negative integer
+    pub fn length(self, length: f64) -> Result<MagnetBuilder, JsError> {
+        if length < 0.0 || length.fract() != 0.0 || length > u64::MAX as f64 {
+            return Err(JsError::new("length must be a non-negative integer"));
+        }
+        Ok(MagnetBuilder(self.0.length(length as u64)))
+    }
+
+    pub fn source(self, source: &str) -> MagnetBuilder {
+        MagnetBuilder(self.0.source(source))
+    }
+
+    #[wasm_bindgen(js_name = addTracker)]
+    pub fn add_tracker(self, tracker: &str) -> MagnetBuilder {
+        MagnetBuilder(self.0.add_tracker(tracker))
+    }
+
+    /// Add every string of an array of tracker URLs
+    #[wasm_bindgen(js_name = addTrackers)]
+    pub fn add_trackers(self, trackers: Array) -> MagnetBuilder {
+        let mut builder = self.0;
+        for tracker in trackers.iter().filter_map(|t| t.as_string()) {
+            builder = builder.add_tracker(&tracker);
+        }
+        MagnetBuilder(builder)
+    }
+
+    #[wasm_bindgen(js_name = searchKeywords)]
+    pub fn search_keywords(self, keywords: &str) -> MagnetBuilder {
+        MagnetBuilder(self.0.search_keywords(keywords))
+    }
+
+    #[wasm_bindgen(js_name = webSeed)]
+    pub fn web_seed(self, web_seed: &str) -> MagnetBuilder {
+        MagnetBuilder(self.0.web_seed(web_seed))
+    }
+
+    #[wasm_bindgen(js_name = acceptableSource)]
+    pub fn acceptable_source(self, source: &str) -> MagnetBuilder {
+        MagnetBuilder(self.0.acceptable_source(source))
+    }
+
+    pub fn manifest(self, manifest: &str) -> MagnetBuilder {
+        MagnetBuilder(self.0.manifest(manifest))
+    }
+
+    pub fn build(self) -> Magnet {
+        Magnet(self.0.build())
+    }
+}
+
+fn to_object(magnet: &RustMagnet) -> Object {
+    let object = Object::new();
+    let string = |value: Option<&str>| value.map_or(JsValue::NULL, JsValue::from_str);
+    let trackers: Array = magnet.trackers().iter().map(|t| JsValue::from_str(t)).collect();
+    let peers: Array = magnet.peers().iter().map(|p| JsValue::from_str(p)).collect();
+
+    let fields = [
+        ("displayName", string(magnet.display_name())),
+        ("hashType", string(magnet.hash_type())),
+        ("hash", string(magnet.hash())),
+        ("length", magnet.length().map_or(JsValue::NULL, |l| JsValue::from_f64(l as f64))),
+        ("trackers", trackers.into()),
+        ("peers", peers.into()),
+        ("webSeed", string(magnet.web_seed())),
+        ("source", string(magnet.source())),
+        ("searchKeywords", string(magnet.search_keywords())),
+        ("acceptableSource", string(magnet.acceptable_source())),
+        ("manifest", string(magnet.manifest())),
+    ];
+    for (key, value) in fields {
+        // Setting a property on a fresh plain object cannot fail
+        let _ = Reflect::set(&object, &JsValue::from_str(key), &value);
+    }
+
+    object
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::{parse_magnet, Magnet, MagnetBuilder};
+    use js_sys::{Array, Reflect};
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const SINTEL: &str = "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel&xl=42&tr=udp%3A%2F%2Fexplodie.org%3A6969";
+
+    fn get(object: &JsValue, key: &str) -> JsValue {
+        Reflect::get(object, &JsValue::from_str(key)).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn parse_magnet_test() {
+        let object: JsValue = parse_magnet(SINTEL).unwrap().into();
+        assert_eq!(get(&object, "displayName").as_string().as_deref(), Some("Sintel"));
+        assert_eq!(get(&object, "hashType").as_string().as_deref(), Some("btih"));
+        assert_eq!(get(&object, "length").as_f64(), Some(42.0));
+        assert!(get(&object, "webSeed").is_null());
+
+        let trackers = Array::from(&get(&object, "trackers"));
+        assert_eq!(trackers.length(), 1);
+        assert_eq!(trackers.get(0).as_string().as_deref(), Some("udp%3A%2F%2Fexplodie.org%3A6969"));
+        assert_eq!(Array::from(&get(&object, "peers")).length(), 0);
+
+        assert!(parse_magnet("https://example.com").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn magnet_class_test() {
+        let magnet = Magnet::new(SINTEL).unwrap();
+        assert_eq!(magnet.display_name().as_deref(), Some("Sintel"));
+        assert_eq!(magnet.hash().as_deref(), Some("08ada5a7a6183aae1e09d831df6748d566095a10"));
+        assert_eq!(magnet.length(), Some(42.0));
+        assert_eq!(magnet.trackers().length(), 1);
+        assert_eq!(magnet.peers().length(), 0);
+        assert_eq!(magnet.manifest(), None);
+        assert_eq!(magnet.to_string(), SINTEL);
+    }
+
+    #[wasm_bindgen_test]
+    fn builder_test() {
+        let trackers: Array = ["udp://a.example:1", "udp://b.example:2"]
+            .iter()
+            .map(|t| JsValue::from_str(t))
+            .collect();
+
+        let magnet = MagnetBuilder::new()
+            .display_name("Test")
+            .hash_type("btih")
+            .hash("1234567890abcdef1234567890abcdef12345678")
+            .length(12345.0)
+            .unwrap()
+            .add_trackers(trackers)
+            .build();
+
+        assert_eq!(
+            magnet.to_string(),
+            "magnet:?xt=urn:btih:1234567890abcdef1234567890abcdef12345678&dn=Test&xl=12345&tr=udp://a.example:1&tr=udp://b.example:2"
+        );
+        assert!(MagnetBuilder::new().length(-1.0).is_err());
+        assert!(MagnetBuilder::new().length(1.5).is_err());
+    }
+}
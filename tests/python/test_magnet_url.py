@@ -0,0 +1,101 @@
+"""Tests for the Python bindings.
+
+Run them with:
+
+    maturin develop
+    python -m unittest discover tests/python
+"""
+
+import unittest
+
+from magnet_url import Magnet, MagnetBuilder, MagnetError
+
+SINTEL = (
+    "magnet:?xt=urn:btih:08ada5a7a6183aae1e09d831df6748d566095a10&dn=Sintel"
+    "&tr=udp%3A%2F%2Fexplodie.org%3A6969&tr=wss%3A%2F%2Ftracker.btorrent.xyz"
+    "&ws=https%3A%2F%2Fwebtorrent.io%2Ftorrents%2F"
+)
+
+
+class MagnetTest(unittest.TestCase):
+    def test_properties(self):
+        magnet = Magnet(SINTEL)
+        self.assertEqual(magnet.display_name, "Sintel")
+        self.assertEqual(magnet.hash_type, "btih")
+        self.assertEqual(magnet.hash, "08ada5a7a6183aae1e09d831df6748d566095a10")
+        self.assertIsNone(magnet.length)
+        self.assertEqual(
+            magnet.trackers,
+            ["udp%3A%2F%2Fexplodie.org%3A6969", "wss%3A%2F%2Ftracker.btorrent.xyz"],
+        )
+        self.assertEqual(magnet.peers, [])
+        self.assertEqual(magnet.web_seed, "https%3A%2F%2Fwebtorrent.io%2Ftorrents%2F")
+        self.assertIsNone(magnet.source)
+        self.assertIsNone(magnet.search_keywords)
+        self.assertIsNone(magnet.acceptable_source)
+        self.assertIsNone(magnet.manifest)
+
+    def test_peers(self):
+        magnet = Magnet(SINTEL + "&x.pe=10.0.0.1:6881&x.pe=peer.example.com:51413")
+        self.assertEqual(magnet.peers, ["10.0.0.1:6881", "peer.example.com:51413"])
+
+    def test_str_round_trips(self):
+        magnet = Magnet(SINTEL)
+        self.assertEqual(str(magnet), SINTEL)
+        self.assertEqual(Magnet(str(magnet)), magnet)
+        self.assertEqual(repr(magnet), "Magnet(%r)" % SINTEL)
+
+    def test_eq_and_hash(self):
+        a = Magnet(SINTEL)
+        b = Magnet(SINTEL)
+        c = Magnet(SINTEL + "&tr=https://example.com/fake_tracker")
+        self.assertEqual(a, b)
+        self.assertEqual(hash(a), hash(b))
+        self.assertNotEqual(a, c)
+        self.assertEqual(len({a, b, c}), 2)
+
+    def test_invalid(self):
+        with self.assertRaises(MagnetError) as context:
+            Magnet("https://example.com")
+        self.assertEqual(str(context.exception), "provided link is not a valid magnet URL")
+        self.assertTrue(issubclass(MagnetError, ValueError))
+
+        with self.assertRaises(MagnetError):
+            Magnet.from_torrent(b"garbage")
+
+    def test_from_torrent(self):
+        torrent = (
+            b"d4:infod6:lengthi5e4:name5:hello12:piece lengthi16384e"
+            b"6:pieces20:aaaaaaaaaaaaaaaaaaaaee"
+        )
+        magnet = Magnet.from_torrent(torrent)
+        self.assertEqual(magnet.display_name, "hello")
+        self.assertEqual(magnet.length, 5)
+
+
+class MagnetBuilderTest(unittest.TestCase):
+    def test_build(self):
+        magnet = (
+            MagnetBuilder()
+            .display_name("Test")
+            .hash_type("btih")
+            .hash("1234567890abcdef1234567890abcdef12345678")
+            .length(12345)
+            .add_tracker("udp://a.example:1")
+            .add_trackers(["udp://b.example:2", "udp://c.example:3"])
+            .web_seed("https://example.com/seed")
+            .build()
+        )
+        self.assertEqual(magnet.length, 12345)
+        self.assertEqual(len(magnet.trackers), 3)
+        self.assertEqual(
+            str(magnet),
+            "magnet:?xt=urn:btih:1234567890abcdef1234567890abcdef12345678&dn=Test&xl=12345"
+            "&tr=udp://a.example:1&tr=udp://b.example:2&tr=udp://c.example:3"
+            "&ws=https://example.com/seed",
+        )
+        self.assertEqual(Magnet(str(magnet)), magnet)
+
+
+if __name__ == "__main__":
+    unittest.main()